@@ -0,0 +1,13 @@
+use cosmwasm_std::{Addr, DepsMut, StdResult};
+use ics721::{execute::Ics721Execute, state::CollectionData, utils::get_collection_data};
+
+use crate::{msg::FairBurnMsg, state::Ics721FairBurnContract};
+
+impl Ics721Execute<FairBurnMsg> for Ics721FairBurnContract {
+    type ClassData = CollectionData;
+
+    /// Default ics721-base contract collections collection data from cw721 contract.
+    fn get_class_data(&self, deps: &DepsMut, sender: &Addr) -> StdResult<Option<Self::ClassData>> {
+        get_collection_data(deps, sender).map(Option::Some)
+    }
+}
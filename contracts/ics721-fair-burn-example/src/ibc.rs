@@ -0,0 +1,24 @@
+use cosmwasm_std::{from_json, CosmosMsg, Deps, Env, IbcPacket, SubMsg};
+use ics721::{error::ContractError, ibc::Ics721Ibc};
+use ics721_types::ibc_types::NonFungibleTokenPacketData;
+
+use crate::{msg::FairBurnMsg, state::Ics721FairBurnContract};
+
+impl Ics721Ibc<FairBurnMsg> for Ics721FairBurnContract {
+    /// Funds the fair burn pool on behalf of the receiver for each received packet. It is dispatched after the
+    /// voucher messages, so if the chain rejects it, the packet is acknowledged with an error and NFTs are
+    /// returned to the sender.
+    fn receive_custom_msgs(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        packet: &IbcPacket,
+    ) -> Result<Vec<SubMsg<FairBurnMsg>>, ContractError> {
+        let data: NonFungibleTokenPacketData = from_json(&packet.data)?;
+        Ok(vec![SubMsg::new(CosmosMsg::Custom(
+            FairBurnMsg::FundFairburnPool {
+                receiver: data.receiver,
+            },
+        ))])
+    }
+}
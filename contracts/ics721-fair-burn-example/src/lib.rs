@@ -0,0 +1,127 @@
+pub mod execute;
+pub mod ibc;
+pub mod msg;
+pub mod query;
+pub mod state;
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    Binary, Deps, DepsMut, Env, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg,
+    IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, Never, Reply, Response, StdResult,
+};
+use cw2::set_contract_version;
+use ics721::{
+    error::ContractError,
+    execute::Ics721Execute,
+    ibc::Ics721Ibc,
+    msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg},
+    query::Ics721Query,
+};
+use msg::FairBurnMsg;
+use state::Ics721FairBurnContract;
+
+const CONTRACT_NAME: &str = "crates.io:ics721-fair-burn-example";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response<FairBurnMsg>> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ics721FairBurnContract::default().instantiate(deps, env, info, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().execute(deps, env, info, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    Ics721FairBurnContract::default().query(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    msg: MigrateMsg,
+) -> Result<Response<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().migrate(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+    deps: DepsMut,
+    env: Env,
+    reply: Reply,
+) -> Result<Response<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().reply(deps, env, reply)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    Ics721FairBurnContract::default().ibc_channel_open(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().ibc_channel_connect(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().ibc_channel_close(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse<FairBurnMsg>, Never> {
+    Ics721FairBurnContract::default().ibc_packet_receive(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    env: Env,
+    ack: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().ibc_packet_ack(deps, env, ack)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse<FairBurnMsg>, ContractError> {
+    Ics721FairBurnContract::default().ibc_packet_timeout(deps, env, msg)
+}
+
+#[cfg(test)]
+mod testing;
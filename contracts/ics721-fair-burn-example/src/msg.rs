@@ -0,0 +1,11 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::CustomMsg;
+
+/// Chain specific message handled by the chain's custom module, like
+/// Stargaze's fair burn.
+#[cw_serde]
+pub enum FairBurnMsg {
+    FundFairburnPool { receiver: String },
+}
+
+impl CustomMsg for FairBurnMsg {}
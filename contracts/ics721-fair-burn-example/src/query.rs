@@ -0,0 +1,5 @@
+use ics721::query::Ics721Query;
+
+use crate::state::Ics721FairBurnContract;
+
+impl Ics721Query for Ics721FairBurnContract {}
@@ -0,0 +1,230 @@
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MOCK_CONTRACT_ADDR},
+    to_json_binary, Addr, Binary, CosmosMsg, Empty, IbcAcknowledgement, IbcEndpoint, IbcPacket,
+    IbcPacketReceiveMsg, IbcTimeout, MemoryStorage, OwnedDeps, Reply, SubMsg, SubMsgResult,
+    Timestamp, WasmMsg,
+};
+use cw_multi_test::{
+    custom_handler::CachingCustomHandler, BasicAppBuilder, Contract, ContractWrapper, Executor,
+};
+use ics721::{
+    ibc_helpers::{ack_fail_with_code, try_get_ack_error},
+    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg},
+    state::{ClassIdInfo, CLASS_ID_AND_NFT_CONTRACT_INFO, CW721_CODE_ID, PO},
+};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{ClassId, TokenId},
+    types::Ics721ErrorCode,
+};
+
+use crate::{
+    execute, ibc_packet_receive, instantiate, msg::FairBurnMsg, query, reply, ContractError,
+};
+
+const CONTRACT_PORT: &str = "wasm.address1";
+const REMOTE_PORT: &str = "stars.address1";
+const CHANNEL_ID: &str = "channel-1";
+const RELAYER_ADDR: &str = "relayer";
+const ICS721_CREATOR: &str = "ics721-creator";
+const CW721_BASE_CODE_ID: u64 = 0;
+
+fn ics721_contract() -> Box<dyn Contract<FairBurnMsg>> {
+    let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
+    Box::new(contract)
+}
+
+fn mock_packet(data: Binary) -> IbcPacket {
+    IbcPacket::new(
+        data,
+        IbcEndpoint {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: CHANNEL_ID.to_string(),
+        },
+        IbcEndpoint {
+            port_id: CONTRACT_PORT.to_string(),
+            channel_id: CHANNEL_ID.to_string(),
+        },
+        42, // Packet sequence number.
+        IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+    )
+}
+
+fn mock_receive_packet(receiver: &str) -> IbcPacketReceiveMsg {
+    let data = to_json_binary(&NonFungibleTokenPacketData {
+        class_id: ClassId::new("id"),
+        class_uri: None,
+        class_data: None,
+        token_ids: vec![TokenId::new("1")],
+        token_uris: None,
+        token_data: None,
+        sender: "violet".to_string(),
+        receiver: receiver.to_string(),
+        memo: None,
+    })
+    .unwrap();
+    IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR))
+}
+
+/// Contract state for receiving a packet, with a known voucher collection for its class id.
+fn mock_ics721() -> OwnedDeps<MemoryStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &dest_class_id,
+            &ClassIdInfo {
+                class_id: ClassId::new(dest_class_id.clone()),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    deps
+}
+
+/// Receives a packet and returns its submessage id and the operand dispatching custom messages.
+fn receive_custom_msgs_operand(
+    deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+    receiver: &str,
+) -> (u64, Binary) {
+    let response =
+        ibc_packet_receive(deps.as_mut(), mock_env(), mock_receive_packet(receiver)).unwrap();
+    assert_eq!(response.messages.len(), 1);
+    let operands = match response.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    // voucher, channel and custom messages
+    assert_eq!(operands.len(), 3);
+    match operands[2].clone() {
+        WasmMsg::Execute {
+            contract_addr, msg, ..
+        } => {
+            assert_eq!(contract_addr, MOCK_CONTRACT_ADDR);
+            (response.messages[0].id, msg)
+        }
+        _ => panic!("unexpected wasm msg"),
+    }
+}
+
+#[test]
+fn test_receive_custom_msg() {
+    let mut deps = mock_ics721();
+    let (reply_id, custom_msgs) = receive_custom_msgs_operand(&mut deps, "blue");
+
+    let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        from_json(custom_msgs).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(CosmosMsg::Custom(
+            FairBurnMsg::FundFairburnPool {
+                receiver: "blue".to_string()
+            }
+        ))]
+    );
+
+    // a failing custom message fails the receive, and an error ack is sent
+    let response = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: reply_id,
+            result: SubMsgResult::Err("insufficient funds".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        response.data,
+        Some(ack_fail_with_code(
+            "insufficient funds".to_string(),
+            Ics721ErrorCode::ExecutionFailed
+        ))
+    );
+
+    // invalid packets are acked with an error, without custom messages
+    let response = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_packet(Binary::from(b"invalid".to_vec())),
+            Addr::unchecked(RELAYER_ADDR),
+        ),
+    )
+    .unwrap();
+    assert!(response.messages.is_empty());
+    assert!(try_get_ack_error(&IbcAcknowledgement::new(response.acknowledgement)).is_some());
+}
+
+#[test]
+fn test_receive_custom_msg_dispatched_to_chain() {
+    let custom_handler = CachingCustomHandler::<FairBurnMsg, Empty>::new();
+    let mut app = BasicAppBuilder::<FairBurnMsg, Empty>::new_custom()
+        .with_custom(custom_handler.clone())
+        .build(|_, _, _| {});
+    let ics721_id = app.store_code(ics721_contract());
+    let ics721 = app
+        .instantiate_contract(
+            ics721_id,
+            Addr::unchecked(ICS721_CREATOR),
+            &InstantiateMsg {
+                cw721_base_code_id: CW721_BASE_CODE_ID,
+                incoming_proxy: None,
+                outgoing_proxy: None,
+                pauser: None,
+                cw721_admin: None,
+                contract_addr_length: None,
+                class_data_refresh_policy: None,
+                update_voucher_collection_info: None,
+                token_metadata_policy: None,
+                voucher_code_id_rules: None,
+                claim_mode: None,
+                callback_gas_limits: None,
+            },
+            &[],
+            "ics721-fair-burn-example",
+            None,
+        )
+        .unwrap();
+
+    // custom messages operand as created by the receive
+    let mut deps = mock_ics721();
+    let (_, custom_msgs) = receive_custom_msgs_operand(&mut deps, "blue");
+    let custom_msgs = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: ics721.to_string(),
+        msg: custom_msgs,
+        funds: vec![],
+    });
+
+    // only ICS721 itself may dispatch them
+    let err: ContractError = app
+        .execute(Addr::unchecked(RELAYER_ADDR), custom_msgs.clone())
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    assert!(custom_handler.state().execs().is_empty());
+
+    app.execute(ics721, custom_msgs).unwrap();
+    assert_eq!(
+        custom_handler.state().execs().to_vec(),
+        vec![FairBurnMsg::FundFairburnPool {
+            receiver: "blue".to_string()
+        }]
+    );
+}
@@ -1,9 +1,14 @@
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, ContractInfoResponse, Deps, DepsMut, Env, StdResult,
+    to_json_binary, Addr, Binary, ContractInfoResponse, Deps, DepsMut, Empty, Env, StdResult,
 };
 use cw721::{CollectionExtension, RoyaltyInfo};
-use ics721::{execute::Ics721Execute, state::CollectionData, utils::get_collection_data};
-use ics721_types::token_types::Class;
+use ics721::{
+    execute::Ics721Execute,
+    interop::collection_data_from_class,
+    state::CollectionData,
+    utils::{apply_destination_profile, get_collection_data, get_royalty_payee},
+};
+use ics721_types::token_types::{Class, ClassId};
 
 use sg721::RoyaltyInfoResponse;
 use sg721_base::msg::{CollectionInfoResponse, QueryMsg};
@@ -22,6 +27,7 @@ impl Ics721Execute for SgIcs721Contract {
             symbol,
             extension: _, // ignore extension coming from standard cw721, since sg721 has its own extension (collection info)
             num_tokens,
+            destination_profile: _, // only set on send
         } = get_collection_data(deps, sender)?;
         let collection_info: CollectionInfoResponse = deps
             .querier
@@ -46,6 +52,7 @@ impl Ics721Execute for SgIcs721Contract {
             symbol,
             num_tokens,
             extension,
+            destination_profile: None,
         }))
     }
 
@@ -73,8 +80,8 @@ impl Ics721Execute for SgIcs721Contract {
             collection_info: sg721::CollectionInfo {
                 // source owner could be: 1. regular wallet, 2. contract, or 3. multisig
                 // bech32 calculation for 2. and 3. leads to unknown address
-                // therefore, we use ics721 creator as owner
-                creator: cw721_admin_or_ics721_admin_or_ics721_creator.clone(),
+                // therefore, ICS721 is creator, so it can update collection info and hand over creator to collection owner
+                creator: env.contract.address.to_string(),
                 description: "".to_string(),
                 // remaining props is set below, in case there's collection data
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(), // use Stargaze icon as placeholder
@@ -86,23 +93,25 @@ impl Ics721Execute for SgIcs721Contract {
         };
 
         // use collection data for setting name and symbol
-        let collection_data = class
-            .data
-            .clone()
-            .and_then(|binary| from_json::<CollectionData>(binary).ok());
-        if let Some(collection_data) = collection_data {
+        if let Some(collection_data) = collection_data_from_class(class) {
+            // collection owner on source chain may have defined name, symbol, image and royalty payee
+            let (collection_data, profile_royalty_payee) =
+                apply_destination_profile(deps.api, collection_data);
+            let royalty_payee = get_royalty_payee(deps, &class.id, profile_royalty_payee)?;
             instantiate_msg.name = collection_data.name;
             instantiate_msg.symbol = collection_data.symbol;
             if let Some(collection_info_extension_msg) =
                 collection_data.extension.map(|ext| sg721::CollectionInfo {
-                    creator: cw721_admin_or_ics721_admin_or_ics721_creator.clone(),
+                    creator: env.contract.address.to_string(),
                     description: ext.description,
                     image: ext.image,
                     external_link: ext.external_link,
                     explicit_content: ext.explicit_content,
                     start_trading_time: ext.start_trading_time,
                     royalty_info: ext.royalty_info.map(|r| RoyaltyInfoResponse {
-                        payment_address: cw721_admin_or_ics721_admin_or_ics721_creator, // r.payment_address cant be used, since it is from another chain
+                        // r.payment_address cant be used, since it is from another chain
+                        payment_address: royalty_payee
+                            .unwrap_or(cw721_admin_or_ics721_admin_or_ics721_creator),
                         share: r.share,
                     }),
                 })
@@ -113,4 +122,67 @@ impl Ics721Execute for SgIcs721Contract {
 
         to_json_binary(&instantiate_msg)
     }
+
+    /// sg721 doesn't support updating name and symbol, so only collection info is updated.
+    fn update_collection_info_msg(
+        &self,
+        deps: Deps,
+        env: &Env,
+        class_id: &ClassId,
+        collection_data: CollectionData,
+        cw721_admin: Option<String>,
+    ) -> StdResult<Binary> {
+        let ContractInfoResponse { creator, admin, .. } = deps
+            .querier
+            .query_wasm_contract_info(env.contract.address.to_string())?;
+        let (collection_data, profile_royalty_payee) =
+            apply_destination_profile(deps.api, collection_data);
+        let royalty_payee = get_royalty_payee(deps, class_id, profile_royalty_payee)?;
+        let cw721_admin_or_ics721_admin_or_ics721_creator =
+            cw721_admin.or(admin).unwrap_or(creator);
+        let collection_info = match collection_data.extension {
+            Some(ext) => sg721::UpdateCollectionInfoMsg {
+                description: Some(ext.description),
+                image: Some(ext.image),
+                external_link: Some(ext.external_link),
+                explicit_content: ext.explicit_content,
+                royalty_info: Some(ext.royalty_info.map(|r| RoyaltyInfoResponse {
+                    // r.payment_address cant be used, since it is from another chain
+                    payment_address:
+                        royalty_payee.unwrap_or(cw721_admin_or_ics721_admin_or_ics721_creator),
+                    share: r.share,
+                })),
+                creator: None,
+            },
+            // nothing to update
+            None => sg721::UpdateCollectionInfoMsg {
+                description: None,
+                image: None,
+                external_link: None,
+                explicit_content: None,
+                royalty_info: None,
+                creator: None,
+            },
+        };
+        to_json_binary(&sg721::ExecuteMsg::<Empty, Empty>::UpdateCollectionInfo { collection_info })
+    }
+
+    /// sg721 has no ownership transfer, creator is set directly in collection info.
+    fn transfer_collection_ownership_msg(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        new_owner: &Addr,
+    ) -> StdResult<Binary> {
+        to_json_binary(&sg721::ExecuteMsg::<Empty, Empty>::UpdateCollectionInfo {
+            collection_info: sg721::UpdateCollectionInfoMsg {
+                description: None,
+                image: None,
+                external_link: None,
+                explicit_content: None,
+                royalty_info: None,
+                creator: Some(new_owner.to_string()),
+            },
+        })
+    }
 }
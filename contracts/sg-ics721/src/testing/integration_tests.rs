@@ -354,6 +354,12 @@ impl Test {
                     pauser: admin.clone(),
                     cw721_admin: admin.clone(),
                     contract_addr_length: None,
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 },
                 &[],
                 "sg-ics721",
@@ -654,6 +660,7 @@ fn test_do_instantiate_and_mint_weird_data() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -754,8 +761,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -887,6 +894,7 @@ fn test_do_instantiate_and_mint() {
                                         start_trading_time: Some(Timestamp::from_seconds(42)),
                                     }),
                                     num_tokens: Some(1),
+                                    destination_profile: None,
                                 })
                                 .unwrap(),
                             ),
@@ -951,7 +959,7 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                creator: test.admin_and_pauser.clone().unwrap(),
+                creator: test.ics721.to_string(),
                 description: "description".to_string(),
                 image: "https://ark.pass/image.png".to_string(),
                 external_link: Some("https://interchain.arkprotocol.io".to_string()),
@@ -1127,8 +1135,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1241,6 +1249,7 @@ fn test_do_instantiate_and_mint() {
                                             .addr_make(COLLECTION_OWNER_TARGET_CHAIN)
                                             .to_string(),
                                     ),
+                                    destination_profile: None,
                                 })
                                 .unwrap(),
                             ),
@@ -1305,8 +1314,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1419,6 +1428,7 @@ fn test_do_instantiate_and_mint() {
                                             .addr_make(COLLECTION_OWNER_TARGET_CHAIN)
                                             .to_string(),
                                     ),
+                                    destination_profile: None,
                                 })
                                 .unwrap(),
                             ),
@@ -1483,8 +1493,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1715,8 +1725,8 @@ fn test_do_instantiate_and_mint_2_different_collections() {
         assert_eq!(
             collection_info_1,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1728,8 +1738,8 @@ fn test_do_instantiate_and_mint_2_different_collections() {
         assert_eq!(
             collection_info_2,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ICS721 is creator of nft contract, so it can update collection info
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1930,6 +1940,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -1985,7 +1996,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
     let nft_contract: Addr = test
         .app
         .wrap()
-        .query_wasm_smart(test.ics721, &QueryMsg::NftContract { class_id })
+        .query_wasm_smart(&test.ics721, &QueryMsg::NftContract { class_id })
         .unwrap();
 
     // check collection info is properly set
@@ -1997,7 +2008,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
     assert_eq!(
         collection_info,
         CollectionInfoResponse {
-            creator: test.admin_and_pauser.clone().unwrap(),
+            creator: test.ics721.to_string(),
             description: "description".to_string(),
             image: "https://ark.pass/image.png".to_string(),
             external_link: Some("https://interchain.arkprotocol.io".to_string()),
@@ -2074,6 +2085,7 @@ fn test_do_instantiate_and_mint_permissions() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -2333,6 +2345,7 @@ fn test_receive_nft() {
             }),
             start_trading_time: Some(Timestamp::from_seconds(42)),
         }),
+        destination_profile: None,
     })
     .unwrap();
     assert_eq!(
@@ -2441,6 +2454,12 @@ fn test_pause() {
                     cw721_base_code_id: None,
                     cw721_admin: None,
                     contract_addr_length: None,
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 })
                 .unwrap(),
             }
@@ -2496,6 +2515,12 @@ fn test_migration() {
                     cw721_base_code_id: Some(12345678),
                     cw721_admin: Some(admin.to_string()),
                     contract_addr_length: Some(20), // injective have 20 bytes addresses
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 })
                 .unwrap(),
             }
@@ -2520,6 +2545,12 @@ fn test_migration() {
         cw721_base_code_id: None,
         cw721_admin: Some("".to_string()),
         contract_addr_length: None,
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
     };
     test.app
         .execute(
@@ -1,8 +1,11 @@
 use cosmwasm_schema::{cw_serde, schemars::JsonSchema};
-use cosmwasm_std::{Binary, IbcPacket};
+use cosmwasm_std::{Addr, Binary, IbcAcknowledgement, IbcEndpoint, IbcPacket, Uint64};
 use serde::{Deserialize, Serialize};
 
-use crate::ibc_types::NonFungibleTokenPacketData;
+use crate::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{ClassId, TokenId},
+};
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -27,6 +30,107 @@ pub struct Ics721Callbacks {
     /// The address that will receive the callback message
     /// Defaults to the receiver address
     pub receive_callback_addr: Option<String>,
+    /// Version of callback messages. In case of None, `V1` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Ics721CallbackVersion>,
+    /// Gas limit of the ack callback, capped by ICS721's max gas limit.
+    /// In case of None, ICS721's default gas limit is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ack_callback_gas_limit: Option<u64>,
+    /// Gas limit of the receive callback, capped by ICS721's max gas limit.
+    /// In case of None, ICS721's default gas limit is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receive_callback_gas_limit: Option<u64>,
+    /// Whether a failing receive callback fails the transfer. In case of None, `Required` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receive_callback_mode: Option<ReceiveCallbackMode>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum ReceiveCallbackMode {
+    /// Receive callback is executed within the transfer, failing it fails the transfer.
+    #[default]
+    Required,
+    /// Receive callback is executed after NFTs have been received, failing it is recorded by ICS721 but doesn't fail
+    /// the transfer.
+    BestEffort,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum Ics721CallbackVersion {
+    /// `Ics721ReceiveCallback` and `Ics721AckCallback`.
+    #[default]
+    V1,
+    /// `Ics721ReceiveCallbackV2` and `Ics721AckCallbackV2`, additionally holding the IBC context of the transfer.
+    V2,
+}
+
+/// IBC context of a transfer, so contracts can verify it came from an expected route.
+#[cw_serde]
+pub struct Ics721CallbackContext {
+    /// Endpoint on this chain, the NFT arrived on (receive) or has been sent from (ack).
+    pub local_endpoint: IbcEndpoint,
+    pub counterparty_endpoint: IbcEndpoint,
+    /// Class ID on this chain, e.g. `{port}/{channel}/{class}` for vouchers.
+    pub local_class_id: ClassId,
+    pub sequence: u64,
+    pub relayer: String,
+}
+
+/// ADR-008 (ibc-go callbacks middleware) like memo in plain JSON, e.g.
+/// `{"src_callback":{"address":"cosmos1...","gas_limit":"100000"},"dest_callback":{"address":"cosmos1..."}}`.
+/// Unknown fields are ignored, so it can be combined with other memo formats.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[schemars(crate = "cosmwasm_schema::schemars")]
+#[serde(crate = "cosmwasm_schema::serde")]
+pub struct IbcCallbacksMemo {
+    /// Called on ack or timeout (source side), only in case address is the packet sender.
+    pub src_callback: Option<IbcCallbackData>,
+    /// Called on receive (destination side).
+    pub dest_callback: Option<IbcCallbackData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[schemars(crate = "cosmwasm_schema::schemars")]
+#[serde(crate = "cosmwasm_schema::serde")]
+pub struct IbcCallbackData {
+    pub address: String,
+    /// Gas limit of the callback, capped by ICS721's max gas limit. In case of None, ICS721's default gas limit
+    /// is used.
+    pub gas_limit: Option<Uint64>,
+}
+
+/// Same as `cosmwasm_std::IbcSourceCallbackMsg` (CosmWasm 2.1+), sent on ack or timeout in case of a
+/// `src_callback` memo.
+#[cw_serde]
+pub enum IbcSourceCallbackMsg {
+    Acknowledgement(IbcAckCallbackMsg),
+    Timeout(IbcTimeoutCallbackMsg),
+}
+
+#[cw_serde]
+pub struct IbcAckCallbackMsg {
+    pub acknowledgement: IbcAcknowledgement,
+    pub original_packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+#[cw_serde]
+pub struct IbcTimeoutCallbackMsg {
+    pub packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+/// Same as `cosmwasm_std::IbcDestinationCallbackMsg` (CosmWasm 2.1+), sent on receive in case of a
+/// `dest_callback` memo.
+#[cw_serde]
+pub struct IbcDestinationCallbackMsg {
+    pub packet: IbcPacket,
+    pub ack: IbcAcknowledgement,
 }
 
 /// A message is that is being called on receiving the NFT after transfer was completed.
@@ -39,6 +143,19 @@ pub struct Ics721ReceiveCallbackMsg {
     pub msg: Binary,
 }
 
+/// Same as `Ics721ReceiveCallbackMsg`, with IBC context of the transfer.
+#[cw_serde]
+pub struct Ics721ReceiveCallbackMsgV2 {
+    pub nft_contract: String,
+    pub original_packet: NonFungibleTokenPacketData,
+    pub msg: Binary,
+    pub context: Ics721CallbackContext,
+    /// Vouchers minted for the receiver, empty in case of redemption.
+    pub minted_token_ids: Vec<TokenId>,
+    /// NFTs released from escrow to the receiver, empty in case vouchers are minted.
+    pub redeemed_token_ids: Vec<TokenId>,
+}
+
 /// A message to update your contract of the status of a transfer
 /// status = Ics721Status::Success - the transfer was successful and NFT is on the other chain
 /// status = Ics721Status::Failed - Transfer failed and contract still owns the NFT
@@ -48,6 +165,21 @@ pub struct Ics721AckCallbackMsg {
     pub nft_contract: String,
     pub original_packet: NonFungibleTokenPacketData,
     pub msg: Binary,
+    /// Stable error code in case of `Ics721Status::Failed`, if the counterparty provided one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<Ics721ErrorCode>,
+}
+
+/// Same as `Ics721AckCallbackMsg`, with IBC context of the transfer.
+#[cw_serde]
+pub struct Ics721AckCallbackMsgV2 {
+    pub status: Ics721Status,
+    pub nft_contract: String,
+    pub original_packet: NonFungibleTokenPacketData,
+    pub msg: Binary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<Ics721ErrorCode>,
+    pub context: Ics721CallbackContext,
 }
 
 /// The status of a transfer on callback
@@ -57,17 +189,89 @@ pub enum Ics721Status {
     Failed(String),
 }
 
+/// Stable error codes carried in error acks, e.g. `{"error":"...","code":2}`.
+/// Unlike error messages, codes are part of the wire format: existing codes must never
+/// be renumbered, new codes must only be appended.
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum Ics721ErrorCode {
+    /// Error could not be classified, or the code is unknown to this version.
+    Unknown,
+    /// ICS721 on the receiving chain is paused.
+    Paused,
+    /// Packet data is invalid, e.g. mismatching token ids and token data.
+    InvalidPacket,
+    /// Sender (e.g. the outgoing proxy) is not allowed to do this.
+    Unauthorized,
+    /// No NFT contract is known for the class id.
+    UnknownClass,
+    /// NFT is not owned by the expected owner (e.g. not escrowed by ICS721).
+    NftNotOwned,
+    /// Channel is not a valid ICS721 channel.
+    InvalidChannel,
+    /// One of the messages executed on receive failed: e.g. incoming proxy denied the class,
+    /// the receive callback rejected the NFT or the NFT contract failed on mint or transfer.
+    ExecutionFailed,
+    /// Packet timed out before it was received.
+    Timeout,
+}
+
+impl Ics721ErrorCode {
+    /// Numeric code as used in error acks.
+    pub fn code(&self) -> u32 {
+        match self {
+            Ics721ErrorCode::Unknown => 0,
+            Ics721ErrorCode::Paused => 1,
+            Ics721ErrorCode::InvalidPacket => 2,
+            Ics721ErrorCode::Unauthorized => 3,
+            Ics721ErrorCode::UnknownClass => 4,
+            Ics721ErrorCode::NftNotOwned => 5,
+            Ics721ErrorCode::InvalidChannel => 6,
+            Ics721ErrorCode::ExecutionFailed => 7,
+            Ics721ErrorCode::Timeout => 8,
+        }
+    }
+
+    /// Reverse of `code()`. Unknown codes (e.g. sent by a newer counterparty) map to `Unknown`.
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            1 => Ics721ErrorCode::Paused,
+            2 => Ics721ErrorCode::InvalidPacket,
+            3 => Ics721ErrorCode::Unauthorized,
+            4 => Ics721ErrorCode::UnknownClass,
+            5 => Ics721ErrorCode::NftNotOwned,
+            6 => Ics721ErrorCode::InvalidChannel,
+            7 => Ics721ErrorCode::ExecutionFailed,
+            8 => Ics721ErrorCode::Timeout,
+            _ => Ics721ErrorCode::Unknown,
+        }
+    }
+}
+
 /// This is a wrapper for ics721 callbacks
 /// so contracts will be able to recieve both status update and on receive hook.
 #[cw_serde]
 pub enum ReceiverExecuteMsg {
     /// Being called on receiving the NFT after transfer was completed. (destination side)
     /// `on_recieve` hook
-    /// Note - Failing this message will fail the transfer.
+    /// Note - Failing this message will fail the transfer, unless `ReceiveCallbackMode::BestEffort` is used.
     Ics721ReceiveCallback(Ics721ReceiveCallbackMsg),
     /// Being called as a status update of the transfer. (source side)
     /// Note - Failing this message will NOT fail the transfer, its just a status update.
     Ics721AckCallback(Ics721AckCallbackMsg),
+    /// Same as `Ics721ReceiveCallback`, sent in case callback version `V2` is requested in memo.
+    Ics721ReceiveCallbackV2(Ics721ReceiveCallbackMsgV2),
+    /// Same as `Ics721AckCallback`, sent in case callback version `V2` is requested in memo.
+    Ics721AckCallbackV2(Ics721AckCallbackMsgV2),
+    /// ADR-008 like source callback, being called on ack or timeout. (source side)
+    /// ICS721 specific shim: it is sent as execute message, and not as `ibc_source_callback` entry point.
+    /// Note - Failing this message will NOT fail the transfer.
+    IbcSourceCallback(IbcSourceCallbackMsg),
+    /// ADR-008 like destination callback, being called after NFT has been received. (destination side)
+    /// ICS721 specific shim: it is sent as execute message, and not as `ibc_destination_callback` entry point.
+    /// Note - unlike ibc-go, where only out of gas fails the transfer, failing this message for any reason will fail
+    /// the transfer.
+    IbcDestinationCallback(IbcDestinationCallbackMsg),
 
     /// Being called on receiving the NFT before transfer is completed. (destination side)
     /// `on_recieve` hook
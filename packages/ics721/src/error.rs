@@ -1,7 +1,7 @@
 use cosmwasm_std::{Binary, Instantiate2AddressError, StdError};
 use cw_pause_once::PauseError;
 use cw_utils::ParseReplyError;
-use ics721_types::error::Ics721Error;
+use ics721_types::{error::Ics721Error, types::Ics721ErrorCode};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -55,6 +55,9 @@ pub enum ContractError {
     #[error("Transfer Doesn't contain any action, no redemption or creation")]
     InvalidTransferNoAction,
 
+    #[error("Invalid wasm memo: {0}")]
+    InvalidWasmHook(String),
+
     #[error("Couldn't find nft contract for class id: {0}")]
     NoNftContractForClassId(String),
 
@@ -68,4 +71,109 @@ pub enum ContractError {
 
     #[error("Couldn't find class id for nft contract: {0}")]
     NoClassIdForNftContract(String),
+
+    #[error("Unknown channel: {0}")]
+    UnknownChannel(String),
+
+    #[error("Not a home collection, class id: {0}")]
+    NotHomeCollection(String),
+
+    #[error("Not a voucher collection, class id: {0}")]
+    NotVoucherCollection(String),
+
+    #[error("Invalid collection ownership proof: {0}")]
+    InvalidOwnershipProof(String),
+
+    #[error("Class id or nft contract already mapped: {0}")]
+    ClassAlreadyMapped(String),
+
+    #[error("ICS721 is not minter of nft contract: {0}")]
+    Ics721NotMinter(String),
+
+    #[error("Token ID {token_id} already exists in nft contract {nft_contract}")]
+    TokenIdCollision {
+        nft_contract: String,
+        token_id: String,
+    },
+
+    #[error("{count} vouchers outstanding for class id: {class_id}")]
+    OutstandingVouchers { class_id: String, count: u64 },
+
+    #[error("Outstanding voucher {token_id} missing in nft contract {nft_contract}")]
+    MissingVoucher {
+        nft_contract: String,
+        token_id: String,
+    },
+
+    #[error("No claimable voucher {token_id} for class id: {class_id}")]
+    NoClaimableVoucher { class_id: String, token_id: String },
+
+    #[error("Claim of voucher {token_id} for class id {class_id} not expired")]
+    ClaimNotExpired { class_id: String, token_id: String },
+
+    #[error("No failed ack callback with id: {0}")]
+    NoFailedAckCallback(u64),
+
+    #[error("Voucher contract migration to code id {code_id} doesn't match its first page")]
+    VoucherMigrationMismatch { code_id: u64 },
+
+    #[error("Unclaimed vouchers can't be returned bypassing the outgoing proxy")]
+    OutgoingProxyBypassed {},
+
+    #[error("Pending royalty payee claim for class id {class_id} is not by {claimer} for {payee}")]
+    RoyaltyPayeeClaimMismatch {
+        class_id: String,
+        claimer: String,
+        payee: String,
+    },
+}
+
+impl ContractError {
+    /// Stable error code sent along with the error message in error acks.
+    pub fn error_code(&self) -> Ics721ErrorCode {
+        match self {
+            ContractError::Ics721Error(_) => Ics721ErrorCode::InvalidPacket,
+            ContractError::Std(err) => match err {
+                StdError::ParseErr { .. }
+                | StdError::SerializeErr { .. }
+                | StdError::InvalidBase64 { .. }
+                | StdError::InvalidUtf8 { .. }
+                | StdError::InvalidDataSize { .. }
+                | StdError::InvalidHex { .. } => Ics721ErrorCode::InvalidPacket,
+                _ => Ics721ErrorCode::Unknown,
+            },
+            ContractError::Pause(_) => Ics721ErrorCode::Paused,
+            ContractError::Unauthorized {}
+            | ContractError::UnknownMsg(_)
+            | ContractError::InvalidOwnershipProof(_)
+            | ContractError::Ics721NotMinter(_)
+            | ContractError::ClaimNotExpired { .. }
+            | ContractError::OutgoingProxyBypassed {}
+            | ContractError::RoyaltyPayeeClaimMismatch { .. } => Ics721ErrorCode::Unauthorized,
+            ContractError::NotEscrowedByIcs721(_)
+            | ContractError::NotOwnerOfNft { .. }
+            | ContractError::OutstandingVouchers { .. }
+            | ContractError::MissingVoucher { .. }
+            | ContractError::NoClaimableVoucher { .. } => Ics721ErrorCode::NftNotOwned,
+            ContractError::OrderedChannel {}
+            | ContractError::InvalidVersion { .. }
+            | ContractError::CantCloseChannel {}
+            | ContractError::UnknownChannel(_) => Ics721ErrorCode::InvalidChannel,
+            ContractError::InvalidTransferBothActions
+            | ContractError::InvalidTransferNoAction
+            | ContractError::InvalidWasmHook(_) => Ics721ErrorCode::InvalidPacket,
+            ContractError::NoNftContractForClassId(_)
+            | ContractError::NoNftContractMatch { .. }
+            | ContractError::NoClassIdForNftContract(_)
+            | ContractError::NotHomeCollection(_)
+            | ContractError::NotVoucherCollection(_)
+            | ContractError::ClassAlreadyMapped(_) => Ics721ErrorCode::UnknownClass,
+            ContractError::TokenIdCollision { .. } => Ics721ErrorCode::ExecutionFailed,
+            ContractError::Instantiate2Error(_)
+            | ContractError::UnrecognisedReplyId {}
+            | ContractError::ParseReplyError(_)
+            | ContractError::NoFailedAckCallback(_)
+            | ContractError::VoucherMigrationMismatch { .. } => Ics721ErrorCode::Unknown,
+        }
+    }
 }
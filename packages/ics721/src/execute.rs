@@ -2,25 +2,32 @@ use std::fmt::Debug;
 
 use cosmwasm_std::{
     from_json, to_json_binary, Addr, Binary, ContractInfoResponse, Deps, DepsMut, Empty, Env,
-    Event, IbcMsg, MessageInfo, Order, Response, StdResult, SubMsg, WasmMsg,
+    Event, IbcMsg, IbcTimeout, MessageInfo, Order, Response, StdResult, SubMsg, WasmMsg,
 };
 use cw721::{
     msg::{NftExtensionMsg, RoyaltyInfoResponse},
     NftExtension,
 };
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, Map};
 use ics721_types::{
     ibc_types::{IbcOutgoingMsg, IbcOutgoingProxyMsg, NonFungibleTokenPacketData},
     token_types::{Class, ClassId, Token, TokenId},
 };
 use serde::{de::DeserializeOwned, Serialize};
-use sha2::{Digest, Sha256};
 
 use crate::{
-    helpers::get_instantiate2_address,
+    helpers::{
+        complete_voucher_migration, get_instantiate2_address, is_admin,
+        queue_collection_info_update,
+    },
     ibc::{
         INSTANTIATE_CW721_REPLY_ID, INSTANTIATE_INCOMING_PROXY_REPLY_ID,
-        INSTANTIATE_OUTGOING_PROXY_REPLY_ID,
+        INSTANTIATE_OUTGOING_PROXY_REPLY_ID, MIGRATE_VOUCHER_REPLY_ID,
+        UPDATE_COLLECTION_INFO_REPLY_ID,
+    },
+    interop::{
+        collection_data_from_class, encode_class_data_for_sdk_module,
+        encode_token_data_for_sdk_module, is_sdk_module_port,
     },
     msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg},
     query::{
@@ -28,15 +35,38 @@ use crate::{
         query_nft_contract_for_class_id, query_nft_contracts,
     },
     state::{
-        ClassIdInfo, CollectionData, UniversalAllNftInfoResponse, CLASS_ID_AND_NFT_CONTRACT_INFO,
-        CLASS_ID_TO_CLASS, CONTRACT_ADDR_LENGTH, CW721_ADMIN, CW721_CODE_ID,
-        IBC_RECEIVE_TOKEN_METADATA, INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY,
-        OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO,
+        ChannelIndexMigration, ClaimableVoucher, ClassIdInfo, CollectionData, DestinationProfile,
+        FailedAckCallback, RoyaltyPayeeClaim, TokenMetadataPolicy, UniversalAllNftInfoResponse,
+        VoucherInitMsg, VoucherMigration, VoucherMigrationStatus, VoucherMigrationTarget,
+        CALLBACK_GAS_LIMITS, CHANNEL_INDEX_MIGRATIONS, CHANNEL_INFOS, CLAIMABLE_VOUCHERS,
+        CLAIM_MODE, CLASS_DATA_REFRESH_POLICY, CLASS_DATA_UPDATED_AT,
+        CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS, CLASS_MAPPINGS,
+        CLASS_METADATA_TRANSLATOR, CONTRACT_ADDR_LENGTH, CW721_ADMIN, CW721_CODE_ID,
+        DESTINATION_PROFILES, FAILED_ACK_CALLBACKS, IBC_RECEIVE_TOKEN_METADATA,
+        INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+        OUTGOING_PROXY, PENDING_VOUCHER_MIGRATIONS, PO, ROYALTY_PAYEES, ROYALTY_PAYEE_CLAIMS,
+        TOKEN_METADATA_POLICY, UNLINKED_NFT_CONTRACTS, UPDATE_VOUCHER_COLLECTION_INFO,
+        VOUCHER_CODE_ID_RULES, VOUCHER_MIGRATIONS, VOUCHER_MIGRATION_TARGET, VOUCHER_SALT_NONCES,
     },
     token_types::{VoucherCreation, VoucherRedemption},
+    translators::{translate_token_data, MetadataTranslator},
+    utils::{
+        adr036_sign_doc_hash, apply_destination_profile, collection_ownership_claim_message,
+        extend_init_msg, get_collection_info_changes, get_collection_minter, get_collection_owner,
+        get_royalty_payee, get_voucher_code_id_rule, get_voucher_salt, is_pubkey_of_account,
+        is_same_account,
+    },
     ContractError,
 };
 
+/// Default and max number of voucher contracts migrated by `MigrateVoucherContracts`.
+const DEFAULT_VOUCHER_MIGRATION_LIMIT: u32 = 10;
+const MAX_VOUCHER_MIGRATION_LIMIT: u32 = 30;
+
+/// Default and max number of channel entries indexed by `MigrateChannelIndexes`.
+const DEFAULT_CHANNEL_INDEX_MIGRATION_LIMIT: u32 = 100;
+const MAX_CHANNEL_INDEX_MIGRATION_LIMIT: u32 = 500;
+
 pub trait Ics721Execute<T = Empty>
 where
     T: Serialize + DeserializeOwned + Clone,
@@ -87,6 +117,25 @@ where
             CONTRACT_ADDR_LENGTH.remove(deps.storage);
         }
 
+        CLASS_DATA_REFRESH_POLICY.save(
+            deps.storage,
+            &msg.class_data_refresh_policy.unwrap_or_default(),
+        )?;
+        UPDATE_VOUCHER_COLLECTION_INFO.save(
+            deps.storage,
+            &msg.update_voucher_collection_info.unwrap_or_default(),
+        )?;
+        let token_metadata_policy = msg.token_metadata_policy.unwrap_or_default();
+        token_metadata_policy.validate(deps.api)?;
+        TOKEN_METADATA_POLICY.save(deps.storage, &token_metadata_policy)?;
+        let voucher_code_id_rules = msg.voucher_code_id_rules.unwrap_or_default();
+        for rule in &voucher_code_id_rules {
+            rule.validate()?;
+        }
+        VOUCHER_CODE_ID_RULES.save(deps.storage, &voucher_code_id_rules)?;
+        CLAIM_MODE.save(deps.storage, &msg.claim_mode.unwrap_or_default())?;
+        CALLBACK_GAS_LIMITS.save(deps.storage, &msg.callback_gas_limits.unwrap_or_default())?;
+
         Ok(Response::default()
             .add_submessages(proxies_instantiate)
             .add_attribute("method", "instantiate")
@@ -134,7 +183,896 @@ where
             } => self.execute_admin_clean_and_unescrow_nft(
                 deps, env, info, recipient, token_id, class_id, collection,
             ),
+            ExecuteMsg::RefreshClassData { collection } => {
+                self.execute_refresh_class_data(deps, env, info, collection)
+            }
+            ExecuteMsg::SetMetadataTranslator {
+                class_id,
+                translator,
+            } => self.execute_set_metadata_translator(deps, env, info, class_id, translator),
+            ExecuteMsg::SetDestinationProfile {
+                collection,
+                channel_id,
+                profile,
+            } => self.execute_set_destination_profile(deps, info, collection, channel_id, profile),
+            ExecuteMsg::RegisterRoyaltyPayee { class_id, payee } => {
+                self.execute_register_royalty_payee(deps, env, info, class_id, payee)
+            }
+            ExecuteMsg::ApproveRoyaltyPayee {
+                class_id,
+                claimer,
+                payee,
+            } => self.execute_approve_royalty_payee(deps, env, info, class_id, claimer, payee),
+            ExecuteMsg::ClaimCollectionOwnership {
+                class_id,
+                pubkey,
+                signature,
+            } => self
+                .execute_claim_collection_ownership(deps, env, info, class_id, pubkey, signature),
+            ExecuteMsg::RegisterClassMapping {
+                class_id,
+                nft_contract,
+            } => self.execute_register_class_mapping(deps, env, info, class_id, nft_contract),
+            ExecuteMsg::RemoveClassMapping { class_id } => {
+                self.execute_remove_class_mapping(deps, env, info, class_id)
+            }
+            ExecuteMsg::RepointClassMapping {
+                class_id,
+                nft_contract,
+            } => self.execute_repoint_class_mapping(deps, env, info, class_id, nft_contract),
+            ExecuteMsg::ReinstantiateVoucherContract { class_id, salt } => {
+                self.execute_reinstantiate_voucher_contract(deps, env, info, class_id, salt)
+            }
+            ExecuteMsg::MigrateVoucherContracts {
+                new_code_id,
+                msg,
+                start_after,
+                limit,
+            } => self.execute_migrate_voucher_contracts(
+                deps,
+                env,
+                info,
+                new_code_id,
+                msg,
+                start_after,
+                limit,
+            ),
+            ExecuteMsg::MigrateChannelIndexes { limit } => {
+                self.execute_migrate_channel_indexes(deps, env, info, limit)
+            }
+            ExecuteMsg::Claim {
+                class_id,
+                token_ids,
+            } => self.execute_claim(deps, info, class_id, token_ids),
+            ExecuteMsg::ReturnUnclaimedVouchers {
+                class_id,
+                token_ids,
+                timeout,
+            } => self
+                .execute_return_unclaimed_vouchers(deps, env, info, class_id, token_ids, timeout),
+            ExecuteMsg::RetryAckCallback { id } => {
+                self.execute_retry_ack_callback(deps, env, info, id)
+            }
+        }
+    }
+
+    fn execute_register_royalty_payee(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        payee: String,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let payee = deps.api.addr_validate(&payee)?;
+        // home collections pay out royalties as defined in collection
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        if nft_contract.to_string() == class_id.to_string() {
+            return Err(ContractError::NotVoucherCollection(class_id.to_string()));
         }
+
+        // same account (key) as owner on source chain, no approval needed
+        let source_owner = CLASS_ID_TO_CLASS
+            .may_load(deps.storage, class_id.clone())?
+            .as_ref()
+            .and_then(collection_data_from_class)
+            .and_then(|collection_data| collection_data.owner);
+        let is_source_owner =
+            source_owner.map_or(false, |owner| is_same_account(info.sender.as_str(), &owner));
+        if !is_source_owner {
+            let claim = RoyaltyPayeeClaim {
+                claimer: info.sender.clone(),
+                payee: payee.clone(),
+            };
+            ROYALTY_PAYEE_CLAIMS.save(deps.storage, (class_id.clone(), info.sender), &claim)?;
+            return Ok(Response::default()
+                .add_attribute("method", "execute_register_royalty_payee")
+                .add_attribute("class_id", class_id)
+                .add_attribute("claimer", claim.claimer)
+                .add_attribute("payee", payee)
+                .add_attribute("status", "pending_approval"));
+        }
+
+        let update = self.save_royalty_payee(deps, &env, &class_id, nft_contract, &payee)?;
+        Ok(Response::default()
+            .add_attribute("method", "execute_register_royalty_payee")
+            .add_attribute("class_id", class_id)
+            .add_attribute("payee", payee)
+            .add_attribute("status", "registered")
+            .add_submessages(update))
+    }
+
+    fn execute_approve_royalty_payee(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        claimer: String,
+        payee: String,
+    ) -> Result<Response<T>, ContractError> {
+        if !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        let class_id = ClassId::new(class_id);
+        let claim = ROYALTY_PAYEE_CLAIMS
+            .may_load(deps.storage, (class_id.clone(), Addr::unchecked(&claimer)))?
+            .filter(|claim| claim.payee.as_str() == payee);
+        let Some(RoyaltyPayeeClaim { claimer, payee }) = claim else {
+            return Err(ContractError::RoyaltyPayeeClaimMismatch {
+                class_id: class_id.to_string(),
+                claimer,
+                payee,
+            });
+        };
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+
+        let update = self.save_royalty_payee(deps, &env, &class_id, nft_contract, &payee)?;
+        Ok(Response::default()
+            .add_attribute("method", "execute_approve_royalty_payee")
+            .add_attribute("class_id", class_id)
+            .add_attribute("claimer", claimer)
+            .add_attribute("payee", payee)
+            .add_submessages(update))
+    }
+
+    fn execute_claim_collection_ownership(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        pubkey: Binary,
+        signature: Binary,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        if nft_contract.to_string() == class_id.to_string() {
+            return Err(ContractError::NotVoucherCollection(class_id.to_string()));
+        }
+        let source_owner = CLASS_ID_TO_CLASS
+            .may_load(deps.storage, class_id.clone())?
+            .as_ref()
+            .and_then(collection_data_from_class)
+            .and_then(|collection_data| collection_data.owner)
+            .ok_or_else(|| {
+                ContractError::InvalidOwnershipProof("no collection owner".to_string())
+            })?;
+        if !is_pubkey_of_account(&pubkey, &source_owner) {
+            return Err(ContractError::InvalidOwnershipProof(format!(
+                "public key doesn't belong to collection owner {source_owner}"
+            )));
+        }
+        // signed by owner on source chain, bound to claimant
+        let message = collection_ownership_claim_message(&env, &class_id, &info.sender);
+        let message_hash = adr036_sign_doc_hash(&source_owner, message.as_bytes());
+        let verified = deps
+            .api
+            .secp256k1_verify(&message_hash, &signature, &pubkey)
+            .map_err(|err| ContractError::InvalidOwnershipProof(err.to_string()))?;
+        if !verified {
+            return Err(ContractError::InvalidOwnershipProof(
+                "invalid signature".to_string(),
+            ));
+        }
+
+        let transfer = WasmMsg::Execute {
+            contract_addr: nft_contract.to_string(),
+            msg: self.transfer_collection_ownership_msg(deps.as_ref(), &env, &info.sender)?,
+            funds: vec![],
+        };
+        Ok(Response::default()
+            .add_message(transfer)
+            .add_attribute("method", "execute_claim_collection_ownership")
+            .add_attribute("class_id", class_id)
+            .add_attribute("nft_contract", nft_contract)
+            .add_attribute("source_owner", source_owner)
+            .add_attribute("new_owner", info.sender))
+    }
+
+    fn execute_register_class_mapping(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        nft_contract: String,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        // collection owner on target chain can't prove control of the class on source chain
+        if !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        let nft_contract = deps.api.addr_validate(&nft_contract)?;
+        // class and nft contract must not be known yet, neither as voucher nor as home collection
+        if CLASS_ID_AND_NFT_CONTRACT_INFO.has(deps.storage, &class_id) {
+            return Err(ContractError::ClassAlreadyMapped(class_id.to_string()));
+        }
+        if load_class_id_for_nft_contract(deps.storage, &nft_contract)?.is_some() {
+            return Err(ContractError::ClassAlreadyMapped(nft_contract.to_string()));
+        }
+        // ICS721 mints incoming NFTs
+        if get_collection_minter(deps.as_ref(), &nft_contract)?
+            != Some(env.contract.address.to_string())
+        {
+            return Err(ContractError::Ics721NotMinter(nft_contract.to_string()));
+        }
+
+        let class_id_info = ClassIdInfo {
+            class_id: class_id.clone(),
+            address: nft_contract.clone(),
+        };
+        CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class_id, &class_id_info)?;
+        CLASS_MAPPINGS.save(deps.storage, class_id.clone(), &nft_contract)?;
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_register_class_mapping")
+            .add_attribute("class_id", class_id)
+            .add_attribute("nft_contract", nft_contract))
+    }
+
+    /// Loads the voucher mapping of a class for repairing it. Only callable by admin.
+    fn load_voucher_class_id_info(
+        &self,
+        deps: Deps,
+        env: &Env,
+        info: &MessageInfo,
+        class_id: &ClassId,
+    ) -> Result<ClassIdInfo, ContractError> {
+        if !is_admin(deps, env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        let class_id_info = CLASS_ID_AND_NFT_CONTRACT_INFO
+            .may_load(deps.storage, class_id)?
+            .ok_or_else(|| ContractError::NoNftContractForClassId(class_id.to_string()))?;
+        // home collections are mapped again on next send
+        if class_id_info.address.to_string() == class_id.to_string() {
+            return Err(ContractError::NotVoucherCollection(class_id.to_string()));
+        }
+        Ok(class_id_info)
+    }
+
+    /// Token IDs of vouchers of a class, which have been received and not yet sent back.
+    fn outstanding_vouchers(&self, deps: Deps, class_id: &ClassId) -> StdResult<Vec<String>> {
+        INCOMING_CLASS_TOKEN_TO_CHANNEL
+            .prefix(class_id.clone())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect()
+    }
+
+    fn execute_remove_class_mapping(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let class_id_info =
+            self.load_voucher_class_id_info(deps.as_ref(), &env, &info, &class_id)?;
+        let outstanding = self.outstanding_vouchers(deps.as_ref(), &class_id)?;
+        if !outstanding.is_empty() {
+            return Err(ContractError::OutstandingVouchers {
+                class_id: class_id.to_string(),
+                count: outstanding.len() as u64,
+            });
+        }
+
+        CLASS_ID_AND_NFT_CONTRACT_INFO.remove(deps.storage, &class_id)?;
+        CLASS_MAPPINGS.remove(deps.storage, class_id.clone());
+        UNLINKED_NFT_CONTRACTS.save(deps.storage, &class_id_info.address, &Empty {})?;
+        // next voucher contract of this class is instantiated with a new salt
+        let nonce = VOUCHER_SALT_NONCES
+            .may_load(deps.storage, class_id.clone())?
+            .unwrap_or_default()
+            + 1;
+        VOUCHER_SALT_NONCES.save(deps.storage, class_id.clone(), &nonce)?;
+
+        let event = Event::new("ics721_class_mapping_repair")
+            .add_attribute("action", "remove")
+            .add_attribute("class_id", class_id)
+            .add_attribute("previous_nft_contract", class_id_info.address)
+            .add_attribute("admin", info.sender);
+        Ok(Response::default()
+            .add_attribute("method", "execute_remove_class_mapping")
+            .add_event(event))
+    }
+
+    fn execute_repoint_class_mapping(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        nft_contract: String,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let class_id_info =
+            self.load_voucher_class_id_info(deps.as_ref(), &env, &info, &class_id)?;
+        let nft_contract = deps.api.addr_validate(&nft_contract)?;
+        if load_class_id_for_nft_contract(deps.storage, &nft_contract)?.is_some() {
+            return Err(ContractError::ClassAlreadyMapped(nft_contract.to_string()));
+        }
+        // ICS721 mints incoming NFTs
+        if get_collection_minter(deps.as_ref(), &nft_contract)?
+            != Some(env.contract.address.to_string())
+        {
+            return Err(ContractError::Ics721NotMinter(nft_contract.to_string()));
+        }
+        // replacement must hold all outstanding vouchers
+        let outstanding = self.outstanding_vouchers(deps.as_ref(), &class_id)?;
+        for token_id in &outstanding {
+            let owner: StdResult<cw721::msg::OwnerOfResponse> = deps.querier.query_wasm_smart(
+                &nft_contract,
+                &cw721_metadata_onchain::msg::QueryMsg::OwnerOf {
+                    token_id: token_id.clone(),
+                    include_expired: None,
+                },
+            );
+            if owner.is_err() {
+                return Err(ContractError::MissingVoucher {
+                    nft_contract: nft_contract.to_string(),
+                    token_id: token_id.clone(),
+                });
+            }
+        }
+
+        let new_class_id_info = ClassIdInfo {
+            class_id: class_id.clone(),
+            address: nft_contract.clone(),
+        };
+        CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class_id, &new_class_id_info)?;
+        UNLINKED_NFT_CONTRACTS.save(deps.storage, &class_id_info.address, &Empty {})?;
+        if CLASS_MAPPINGS.has(deps.storage, class_id.clone()) {
+            CLASS_MAPPINGS.save(deps.storage, class_id.clone(), &nft_contract)?;
+        }
+
+        let event = Event::new("ics721_class_mapping_repair")
+            .add_attribute("action", "repoint")
+            .add_attribute("class_id", class_id)
+            .add_attribute("previous_nft_contract", class_id_info.address)
+            .add_attribute("nft_contract", nft_contract)
+            .add_attribute("outstanding_vouchers", outstanding.len().to_string())
+            .add_attribute("admin", info.sender);
+        Ok(Response::default()
+            .add_attribute("method", "execute_repoint_class_mapping")
+            .add_event(event))
+    }
+
+    fn execute_reinstantiate_voucher_contract(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        salt: Binary,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let class_id_info =
+            self.load_voucher_class_id_info(deps.as_ref(), &env, &info, &class_id)?;
+        let outstanding = self.outstanding_vouchers(deps.as_ref(), &class_id)?;
+        if !outstanding.is_empty() {
+            return Err(ContractError::OutstandingVouchers {
+                class_id: class_id.to_string(),
+                count: outstanding.len() as u64,
+            });
+        }
+        let class = CLASS_ID_TO_CLASS.load(deps.storage, class_id.clone())?;
+
+        // previous mapping is replaced, unique index on address requires removing it first
+        CLASS_ID_AND_NFT_CONTRACT_INFO.remove(deps.storage, &class_id)?;
+        CLASS_MAPPINGS.remove(deps.storage, class_id.clone());
+        UNLINKED_NFT_CONTRACTS.save(deps.storage, &class_id_info.address, &Empty {})?;
+        let (new_class_id_info, instantiate) =
+            self.create_instantiate2_msg(deps.branch(), &env, &class, salt.to_vec())?;
+
+        let event = Event::new("ics721_class_mapping_repair")
+            .add_attribute("action", "reinstantiate")
+            .add_attribute("class_id", class_id)
+            .add_attribute("previous_nft_contract", class_id_info.address)
+            .add_attribute("nft_contract", new_class_id_info.address)
+            .add_attribute("admin", info.sender);
+        Ok(Response::default()
+            .add_attribute("method", "execute_reinstantiate_voucher_contract")
+            .add_event(event)
+            .add_submessage(instantiate))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_migrate_voucher_contracts(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        new_code_id: u64,
+        msg: Binary,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response<T>, ContractError> {
+        if !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        // all pages migrate to the target of the first page
+        let mut target = match start_after {
+            None => VoucherMigrationTarget {
+                code_id: new_code_id,
+                msg: msg.clone(),
+                failed: 0,
+                last_page: false,
+            },
+            Some(_) => VOUCHER_MIGRATION_TARGET
+                .may_load(deps.storage)?
+                .filter(|target| target.code_id == new_code_id && target.msg == msg)
+                .ok_or(ContractError::VoucherMigrationMismatch {
+                    code_id: new_code_id,
+                })?,
+        };
+        let limit = limit
+            .unwrap_or(DEFAULT_VOUCHER_MIGRATION_LIMIT)
+            .min(MAX_VOUCHER_MIGRATION_LIMIT);
+        let nft_contracts =
+            query_nft_contracts(deps.as_ref(), start_after.map(ClassId::new), Some(limit))?;
+
+        let mut pending = vec![];
+        let mut migrations = vec![];
+        for (class_id, nft_contract) in &nft_contracts {
+            // home collections and bound nft contracts are not instantiated by ICS721
+            let ContractInfoResponse { admin, code_id, .. } =
+                deps.querier.query_wasm_contract_info(nft_contract)?;
+            let status = if admin != Some(env.contract.address.to_string()) {
+                VoucherMigrationStatus::Skipped("ICS721 is not admin".to_string())
+            } else if code_id == new_code_id {
+                VoucherMigrationStatus::Skipped("already migrated".to_string())
+            } else {
+                pending.push(nft_contract.clone());
+                migrations.push(SubMsg::<T>::reply_always(
+                    WasmMsg::Migrate {
+                        contract_addr: nft_contract.to_string(),
+                        new_code_id,
+                        msg: msg.clone(),
+                    },
+                    MIGRATE_VOUCHER_REPLY_ID,
+                ));
+                VoucherMigrationStatus::Pending
+            };
+            let migration = VoucherMigration {
+                class_id: ClassId::new(class_id),
+                code_id: new_code_id,
+                status,
+            };
+            VOUCHER_MIGRATIONS.save(deps.storage, nft_contract.clone(), &migration)?;
+        }
+        PENDING_VOUCHER_MIGRATIONS.save(deps.storage, &pending)?;
+
+        // in case page is full, there may be more voucher contracts
+        let next_start_after = match nft_contracts.last() {
+            Some((class_id, _)) if nft_contracts.len() == limit as usize => Some(class_id.clone()),
+            _ => None,
+        };
+        let response = Response::default()
+            .add_attribute("method", "execute_migrate_voucher_contracts")
+            .add_attribute("new_code_id", new_code_id.to_string())
+            .add_attribute("migrations", migrations.len().to_string())
+            .add_attribute(
+                "skipped",
+                (nft_contracts.len() - migrations.len()).to_string(),
+            )
+            .add_submessages(migrations);
+        target.last_page = next_start_after.is_none();
+        VOUCHER_MIGRATION_TARGET.save(deps.storage, &target)?;
+        match next_start_after {
+            Some(next_start_after) => {
+                Ok(response.add_attribute("next_start_after", next_start_after))
+            }
+            // all voucher contracts are dispatched, in case there are no pending migrations code id is switched now,
+            // otherwise on reply of the last migration
+            None => match complete_voucher_migration(deps.storage)? {
+                Some(switched) => {
+                    Ok(response.add_attribute("code_id_switched", switched.to_string()))
+                }
+                None => Ok(response),
+            },
+        }
+    }
+
+    fn execute_migrate_channel_indexes(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        limit: Option<u32>,
+    ) -> Result<Response<T>, ContractError> {
+        if !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        let mut remaining = limit
+            .unwrap_or(DEFAULT_CHANNEL_INDEX_MIGRATION_LIMIT)
+            .min(MAX_CHANNEL_INDEX_MIGRATION_LIMIT) as usize;
+        let mut response =
+            Response::default().add_attribute("method", "execute_migrate_channel_indexes");
+        for (name, class_token_to_channel) in [
+            ("outgoing", &OUTGOING_CLASS_TOKEN_TO_CHANNEL),
+            ("incoming", &INCOMING_CLASS_TOKEN_TO_CHANNEL),
+        ] {
+            let Some(migration) = CHANNEL_INDEX_MIGRATIONS.may_load(deps.storage, name)? else {
+                continue;
+            };
+            let start = migration.start_after.map(Bound::exclusive);
+            let entries = class_token_to_channel
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(remaining)
+                .collect::<StdResult<Vec<((String, String), String)>>>()?;
+            for ((class_id, token_id), channel) in &entries {
+                // saving an entry again writes its index entries
+                class_token_to_channel.save(
+                    deps.storage,
+                    (ClassId::new(class_id), TokenId::new(token_id)),
+                    channel,
+                )?;
+            }
+            response = response.add_attribute(
+                format!("migrated {name} channel entries"),
+                entries.len().to_string(),
+            );
+            // in case page is full, there may be more entries
+            if entries.len() == remaining {
+                let start_after = entries
+                    .last()
+                    .map(|((class_id, token_id), _)| {
+                        (ClassId::new(class_id), TokenId::new(token_id))
+                    })
+                    .or(migration.start_after);
+                CHANNEL_INDEX_MIGRATIONS.save(
+                    deps.storage,
+                    name,
+                    &ChannelIndexMigration { start_after },
+                )?;
+                return Ok(response.add_attribute("pending", "true"));
+            }
+            CHANNEL_INDEX_MIGRATIONS.remove(deps.storage, name);
+            remaining -= entries.len();
+        }
+        Ok(response.add_attribute("pending", "false"))
+    }
+
+    fn load_claimable_voucher(
+        &self,
+        deps: Deps,
+        key: (ClassId, TokenId),
+    ) -> Result<ClaimableVoucher, ContractError> {
+        CLAIMABLE_VOUCHERS
+            .may_load(deps.storage, key.clone())?
+            .ok_or_else(|| ContractError::NoClaimableVoucher {
+                class_id: key.0.to_string(),
+                token_id: key.1.into(),
+            })
+    }
+
+    fn execute_claim(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        class_id: String,
+        token_ids: Vec<String>,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        let mut transfers = Vec::with_capacity(token_ids.len());
+        for token_id in &token_ids {
+            let key = (class_id.clone(), TokenId::new(token_id.clone()));
+            let claimable = self.load_claimable_voucher(deps.as_ref(), key.clone())?;
+            if claimable.receiver != info.sender {
+                return Err(ContractError::Unauthorized {});
+            }
+            CLAIMABLE_VOUCHERS.remove(deps.storage, key);
+            transfers.push(WasmMsg::Execute {
+                contract_addr: nft_contract.to_string(),
+                msg: to_json_binary(&cw721_metadata_onchain::msg::ExecuteMsg::TransferNft {
+                    recipient: info.sender.to_string(),
+                    token_id: token_id.clone(),
+                })?,
+                funds: vec![],
+            });
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_claim")
+            .add_attribute("class_id", class_id)
+            .add_attribute("token_ids", format!("{:?}", token_ids))
+            .add_attribute("receiver", info.sender)
+            .add_messages(transfers))
+    }
+
+    fn execute_return_unclaimed_vouchers(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        token_ids: Vec<String>,
+        timeout: IbcTimeout,
+    ) -> Result<Response<T>, ContractError> {
+        // vouchers are sent by ICS721 on behalf of receiver, which would bypass checks of the outgoing proxy (e.g.
+        // rate limits). Routing them through the proxy would make ICS721 the packet sender, so on failure vouchers
+        // wouldn't be refunded to the receiver.
+        if OUTGOING_PROXY.load(deps.storage)?.is_some() {
+            return Err(ContractError::OutgoingProxyBypassed {});
+        }
+        let class_id = ClassId::new(class_id);
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        let is_admin = is_admin(deps.as_ref(), &env, &info.sender)?;
+
+        let mut response =
+            Response::default().add_attribute("method", "execute_return_unclaimed_vouchers");
+        for token_id in token_ids {
+            let key = (class_id.clone(), TokenId::new(token_id.clone()));
+            let claimable = self.load_claimable_voucher(deps.as_ref(), key.clone())?;
+            if !is_admin && !is_same_account(info.sender.as_str(), &claimable.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            if env.block.time < claimable.expires_at {
+                return Err(ContractError::ClaimNotExpired {
+                    class_id: class_id.to_string(),
+                    token_id,
+                });
+            }
+            let channel_id = INCOMING_CLASS_TOKEN_TO_CHANNEL.load(deps.storage, key.clone())?;
+            CLAIMABLE_VOUCHERS.remove(deps.storage, key.clone());
+
+            // sent back on behalf of receiver, so in case transfer fails the voucher is refunded to receiver
+            let msg = IbcOutgoingMsg {
+                receiver: claimable.sender,
+                channel_id,
+                timeout: timeout.clone(),
+                memo: None,
+            };
+            let sent = self.receive_nft(
+                deps.branch(),
+                env.clone(),
+                &nft_contract,
+                key.1,
+                claimable.receiver.to_string(),
+                to_json_binary(&msg)?,
+            )?;
+            response = response.add_submessages(sent.messages).add_event(
+                Event::new("ics721_return_unclaimed_voucher").add_attributes(sent.attributes),
+            );
+        }
+        Ok(response)
+    }
+
+    /// Re-dispatches a failed ack callback. Unlike on ack, the callback is not a submessage replying on error:
+    /// in case it fails again, retry fails and the callback is kept. No gas limit is applied, since the caller
+    /// pays for gas.
+    fn execute_retry_ack_callback(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<T>, ContractError> {
+        let FailedAckCallback { callback, .. } = FAILED_ACK_CALLBACKS
+            .may_load(deps.storage, id)?
+            .ok_or(ContractError::NoFailedAckCallback(id))?;
+        if info.sender != callback.contract && !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        FAILED_ACK_CALLBACKS.remove(deps.storage, id);
+
+        Ok(Response::default()
+            .add_message(WasmMsg::Execute {
+                contract_addr: callback.contract.to_string(),
+                msg: callback.msg,
+                funds: vec![],
+            })
+            .add_attribute("method", "execute_retry_ack_callback")
+            .add_attribute("id", id.to_string())
+            .add_attribute("contract", callback.contract))
+    }
+
+    /// Saves royalty payee and creates a message for updating royalty info of the voucher contract.
+    fn save_royalty_payee(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        class_id: &ClassId,
+        nft_contract: Addr,
+        payee: &Addr,
+    ) -> StdResult<Vec<SubMsg<T>>> {
+        ROYALTY_PAYEES.save(deps.storage, class_id.clone(), payee)?;
+        // payee is registered, so all pending claims are obsolete
+        let claimers = ROYALTY_PAYEE_CLAIMS
+            .prefix(class_id.clone())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<Addr>>>()?;
+        for claimer in claimers {
+            ROYALTY_PAYEE_CLAIMS.remove(deps.storage, (class_id.clone(), claimer));
+        }
+
+        // only in case voucher contract has royalties
+        let collection_data = CLASS_ID_TO_CLASS
+            .may_load(deps.storage, class_id.clone())?
+            .as_ref()
+            .and_then(collection_data_from_class)
+            .filter(|collection_data| {
+                collection_data
+                    .extension
+                    .as_ref()
+                    .map_or(false, |ext| ext.royalty_info.is_some())
+            });
+        let Some(collection_data) = collection_data else {
+            return Ok(vec![]);
+        };
+        let cw721_admin = CW721_ADMIN.load(deps.storage)?.map(|a| a.to_string());
+        queue_collection_info_update(
+            deps.storage,
+            CollectionInfoUpdate {
+                class_id: class_id.clone(),
+                nft_contract: nft_contract.clone(),
+                changes: vec!["royalty_info".to_string()],
+            },
+        )?;
+        Ok(vec![SubMsg::<T>::reply_always(
+            WasmMsg::Execute {
+                contract_addr: nft_contract.to_string(),
+                msg: self.update_collection_info_msg(
+                    deps.as_ref(),
+                    env,
+                    class_id,
+                    collection_data,
+                    cw721_admin,
+                )?,
+                funds: vec![],
+            },
+            UPDATE_COLLECTION_INFO_REPLY_ID,
+        )])
+    }
+
+    fn execute_set_destination_profile(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        collection: String,
+        channel_id: String,
+        profile: Option<DestinationProfile>,
+    ) -> Result<Response<T>, ContractError> {
+        let nft_contract = deps.api.addr_validate(&collection)?;
+        // only collection creator can call this method, so target chain can rely on it
+        let is_owner = get_collection_owner(deps.as_ref(), &nft_contract)?
+            .map_or(false, |owner| owner == info.sender.as_str());
+        if !is_owner {
+            return Err(ContractError::Unauthorized {});
+        }
+        // vouchers are presented as defined on source chain, only home collections have a profile
+        if let Some(class_id) = load_class_id_for_nft_contract(deps.storage, &nft_contract)? {
+            if class_id.to_string() != nft_contract.to_string() {
+                return Err(ContractError::NotHomeCollection(class_id.to_string()));
+            }
+        }
+
+        let key = (ClassId::new(nft_contract.to_string()), channel_id.clone());
+        match profile.clone() {
+            Some(profile) => DESTINATION_PROFILES.save(deps.storage, key, &profile)?,
+            None => DESTINATION_PROFILES.remove(deps.storage, key),
+        }
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_destination_profile")
+            .add_attribute("collection", nft_contract)
+            .add_attribute("channel_id", channel_id)
+            .add_attribute(
+                "profile",
+                profile.map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+            ))
+    }
+
+    fn execute_set_metadata_translator(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        translator: Option<MetadataTranslator>,
+    ) -> Result<Response<T>, ContractError> {
+        if !is_admin(deps.as_ref(), &env, &info.sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        let class_id = ClassId::new(class_id);
+        match translator.clone() {
+            Some(translator) => {
+                CLASS_METADATA_TRANSLATOR.save(deps.storage, class_id.clone(), &translator)?
+            }
+            None => CLASS_METADATA_TRANSLATOR.remove(deps.storage, class_id.clone()),
+        }
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_metadata_translator")
+            .add_attribute("class_id", class_id)
+            .add_attribute(
+                "translator",
+                translator.map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+            ))
+    }
+
+    fn execute_refresh_class_data(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        collection: String,
+    ) -> Result<Response<T>, ContractError> {
+        let nft_contract = deps.api.addr_validate(&collection)?;
+        // only admin or collection creator can call this method
+        let is_authorized = is_admin(deps.as_ref(), &env, &info.sender)?
+            || get_collection_owner(deps.as_ref(), &nft_contract)?
+                .map_or(false, |owner| owner == info.sender.as_str());
+        if !is_authorized {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        // vouchers keep class data from source chain, only home collections are refreshed
+        match load_class_id_for_nft_contract(deps.storage, &nft_contract)? {
+            Some(class_id) if class_id.to_string() == nft_contract.to_string() => (),
+            Some(class_id) => return Err(ContractError::NotHomeCollection(class_id.to_string())),
+            None => {
+                return Err(ContractError::NoClassIdForNftContract(
+                    nft_contract.to_string(),
+                ))
+            }
+        }
+        let class = self.save_home_class(&mut deps, &env, &nft_contract)?;
+
+        let class_data_string = class
+            .data
+            .map_or("none".to_string(), |data| format!("{data:?}"));
+        Ok(Response::default()
+            .add_attribute("method", "execute_refresh_class_data")
+            .add_attribute("class_id", class.id)
+            .add_attribute("class_data", class_data_string))
+    }
+
+    /// Creates class for a home collection with class data from `get_class_data` and stores it.
+    fn save_home_class(
+        &self,
+        deps: &mut DepsMut,
+        env: &Env,
+        nft_contract: &Addr,
+    ) -> Result<Class, ContractError> {
+        let class_data = self.get_class_data(deps, nft_contract)?;
+        let data = class_data.as_ref().map(to_json_binary).transpose()?;
+        let class = Class {
+            id: ClassId::new(nft_contract.to_string()),
+            // There is no collection-level uri nor data in the
+            // cw721 specification so we set those values to
+            // `None` for local, cw721 NFTs.
+            uri: None,
+            data,
+        };
+
+        // Merging and usage of this PR may change that:
+        // <https://github.com/CosmWasm/cw-nfts/pull/75>
+        CLASS_ID_TO_CLASS.save(deps.storage, class.id.clone(), &class)?;
+        CLASS_DATA_UPDATED_AT.save(deps.storage, class.id.clone(), &env.block.time)?;
+        Ok(class)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -181,7 +1119,7 @@ where
 
         // remove incoming channel entry and metadata
         INCOMING_CLASS_TOKEN_TO_CHANNEL
-            .remove(deps.storage, (child_class_id.clone(), token_id.clone()));
+            .remove(deps.storage, (child_class_id.clone(), token_id.clone()))?;
         IBC_RECEIVE_TOKEN_METADATA.remove(deps.storage, (child_class_id.clone(), token_id.clone()));
 
         // check NFT on child collection owned by recipient
@@ -265,7 +1203,7 @@ where
         // remove outgoing channel entry
         let token_id = TokenId::new(token_id);
         OUTGOING_CLASS_TOKEN_TO_CHANNEL
-            .remove(deps.storage, (home_class_id.clone(), token_id.clone()));
+            .remove(deps.storage, (home_class_id.clone(), token_id.clone()))?;
 
         // check NFT on home collection owned by ics721 contract
         let maybe_nft_info: Option<UniversalAllNftInfoResponse> = deps
@@ -363,7 +1301,7 @@ where
 
     fn receive_nft(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         nft_contract: &Addr,
         token_id: TokenId,
@@ -374,31 +1312,36 @@ where
         let msg: IbcOutgoingMsg = from_json(msg)?;
 
         let class = match load_class_id_for_nft_contract(deps.as_ref().storage, nft_contract)? {
+            // class data of home collections is refreshed depending on policy, vouchers keep class data from source chain
+            Some(class_id) if class_id.to_string() == nft_contract.to_string() => {
+                let is_stale = CLASS_DATA_REFRESH_POLICY
+                    .may_load(deps.storage)?
+                    .unwrap_or_default()
+                    .is_stale(
+                        CLASS_DATA_UPDATED_AT.may_load(deps.storage, class_id.clone())?,
+                        env.block.time,
+                    );
+                if is_stale {
+                    self.save_home_class(&mut deps, &env, nft_contract)?
+                } else {
+                    CLASS_ID_TO_CLASS.load(deps.storage, class_id)?
+                }
+            }
             Some(class_id) => CLASS_ID_TO_CLASS.load(deps.storage, class_id)?,
             // No class ID being present means that this is a local NFT
             // that has never been sent out of this contract.
             None => {
-                let class_data = self.get_class_data(&deps, nft_contract)?;
-                let data = class_data.as_ref().map(to_json_binary).transpose()?;
-                let class = Class {
-                    id: ClassId::new(nft_contract.to_string()),
-                    // There is no collection-level uri nor data in the
-                    // cw721 specification so we set those values to
-                    // `None` for local, cw721 NFTs.
-                    uri: None,
-                    data,
-                };
-
                 let class_id_info = ClassIdInfo {
-                    class_id: class.id.clone(),
+                    class_id: ClassId::new(nft_contract.to_string()),
                     address: nft_contract.clone(),
                 };
-                CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class.id, &class_id_info)?;
+                CLASS_ID_AND_NFT_CONTRACT_INFO.save(
+                    deps.storage,
+                    &class_id_info.class_id,
+                    &class_id_info,
+                )?;
 
-                // Merging and usage of this PR may change that:
-                // <https://github.com/CosmWasm/cw-nfts/pull/75>
-                CLASS_ID_TO_CLASS.save(deps.storage, class.id.clone(), &class)?;
-                class
+                self.save_home_class(&mut deps, &env, nft_contract)?
             }
         };
 
@@ -421,15 +1364,44 @@ where
             .may_load(deps.storage, (class.id.clone(), token_id.clone()))?
             .flatten()
         {
-            Some(metadata) => Some(metadata),
+            Some(snapshot) => {
+                self.select_token_metadata(deps.as_ref(), nft_contract, snapshot, info.extension)?
+            }
             // incase there is none in the storage, this is the 'home' chain, so metadata is retrieved from the cw721 contract
             None => info.extension.map(|ext| to_json_binary(&ext)).transpose()?,
         };
 
+        // collection owner may have set a profile for presenting vouchers on target chain
+        let class_data = match DESTINATION_PROFILES
+            .may_load(deps.storage, (class.id.clone(), msg.channel_id.clone()))?
+        {
+            Some(profile) => match class
+                .data
+                .clone()
+                .and_then(|data| from_json::<CollectionData>(data).ok())
+            {
+                Some(collection_data) => Some(to_json_binary(&CollectionData {
+                    destination_profile: Some(profile),
+                    ..collection_data
+                })?),
+                None => class.data.clone(),
+            },
+            None => class.data.clone(),
+        };
+        // SDK modules (e.g. nft-transfer) don't know collection data and NftExtension, so these are converted
+        let (class_data, token_metadata) =
+            match CHANNEL_INFOS.may_load(deps.storage, msg.channel_id.clone())? {
+                Some(channel) if is_sdk_module_port(&channel.counterparty_endpoint.port_id) => (
+                    self.encode_for_sdk_module(class_data, encode_class_data_for_sdk_module)?,
+                    self.encode_for_sdk_module(token_metadata, encode_token_data_for_sdk_module)?,
+                ),
+                _ => (class_data, token_metadata),
+            };
+
         let ibc_message = NonFungibleTokenPacketData {
             class_id: class.id.clone(),
             class_uri: class.uri,
-            class_data: class.data.clone(),
+            class_data,
 
             token_ids: vec![token_id.clone()],
             token_uris: info.token_uri.map(|uri| vec![uri]),
@@ -465,6 +1437,51 @@ where
             .add_message(ibc_message))
     }
 
+    /// Selects token metadata of a voucher sent on next hop, depending on token metadata policy.
+    /// Snapshot is used, in case voucher has no extension (e.g. custom data coming from nft-transfer module) or it is unchanged.
+    fn select_token_metadata(
+        &self,
+        deps: Deps,
+        nft_contract: &Addr,
+        snapshot: Binary,
+        extension: Option<NftExtension>,
+    ) -> StdResult<Option<Binary>> {
+        let Some(extension) = extension else {
+            return Ok(Some(snapshot));
+        };
+        if from_json::<NftExtension>(&snapshot).ok().as_ref() == Some(&extension) {
+            return Ok(Some(snapshot));
+        }
+        let is_live = match TOKEN_METADATA_POLICY
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+        {
+            TokenMetadataPolicy::Snapshot => false,
+            TokenMetadataPolicy::Live => true,
+            TokenMetadataPolicy::LiveIfTrustedUpdater(updaters) => {
+                get_collection_owner(deps, nft_contract)?
+                    .map_or(false, |creator| updaters.contains(&creator))
+            }
+        };
+        if is_live {
+            Ok(Some(to_json_binary(&extension)?))
+        } else {
+            Ok(Some(snapshot))
+        }
+    }
+
+    /// Data that can't be converted (e.g. custom data or data coming from a SDK module) is sent as is.
+    fn encode_for_sdk_module(
+        &self,
+        data: Option<Binary>,
+        encode: fn(&Binary) -> StdResult<Option<Binary>>,
+    ) -> StdResult<Option<Binary>> {
+        match data {
+            Some(data) => Ok(Some(encode(&data)?.unwrap_or(data))),
+            None => Ok(None),
+        }
+    }
+
     fn execute_pause(
         &self,
         deps: DepsMut,
@@ -502,9 +1519,19 @@ where
                 CallbackMsg::AddIncomingChannelEntries(entries) => {
                     self.callback_save_incoming_channel_entries(deps, entries)
                 }
+                CallbackMsg::AddClaimableVouchers(entries) => {
+                    self.callback_save_claimable_vouchers(deps, entries)
+                }
                 CallbackMsg::Conjunction { operands } => {
                     Ok(Response::default().add_messages(operands))
                 }
+                CallbackMsg::ExecuteWithGasLimit { msg, gas_limit } => {
+                    Ok(Response::default()
+                        .add_submessage(SubMsg::new(msg).with_gas_limit(gas_limit)))
+                }
+                CallbackMsg::CustomMsgs(msgs) => {
+                    Ok(Response::default().add_submessages(from_json::<Vec<SubMsg<T>>>(&msgs)?))
+                }
             }
         }
     }
@@ -514,13 +1541,14 @@ where
     /// a new collection is instantiated before minting the vouchers.
     fn callback_create_vouchers(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         receiver: String,
         create: VoucherCreation,
     ) -> Result<Response<T>, ContractError> {
         let VoucherCreation { class, tokens } = create;
 
+        let previous_class = CLASS_ID_TO_CLASS.may_load(deps.storage, class.id.clone())?;
         // Store mapping from classID to classURI. Notably, we don't check
         // if this has already been set. If a new NFT belonging to a class
         // ID we have already seen comes in with new metadata, we assume
@@ -539,7 +1567,19 @@ where
         };
 
         let (class_id_info, instantiate) =
-            self.create_instantiate_msg(deps, &env, class.clone())?;
+            self.create_instantiate_msg(deps.branch(), &env, class.clone())?;
+        // no instantiate message means nft contract already exists
+        let update_collection_info = if instantiate.is_empty() {
+            self.create_update_collection_info_msg(
+                deps.as_ref(),
+                &env,
+                &class_id_info,
+                previous_class,
+                &class,
+            )?
+        } else {
+            None
+        };
 
         let token_ids = format!("{:?}", tokens);
         let event = Event::new("ics721_receive_create_vouchers")
@@ -547,11 +1587,118 @@ where
             .add_attribute("nft_contract", class_id_info.address)
             .add_attribute("token_ids", token_ids);
 
-        Ok(Response::<T>::default()
+        let response = Response::<T>::default()
             .add_attribute("method", "callback_create_vouchers")
             .add_event(event)
             .add_submessages(instantiate)
-            .add_message(mint))
+            .add_message(mint);
+        match update_collection_info {
+            Some((message, update)) => {
+                queue_collection_info_update(deps.storage, update)?;
+                Ok(response.add_submessage(message))
+            }
+            None => Ok(response),
+        }
+    }
+
+    /// In case enabled and incoming collection data differs from the previous one, a message for updating the collection info
+    /// of the existing nft contract is created. Updating is best effort, so an error doesn't fail the transfer. Its result
+    /// is emitted by the reply.
+    fn create_update_collection_info_msg(
+        &self,
+        deps: Deps,
+        env: &Env,
+        class_id_info: &ClassIdInfo,
+        previous_class: Option<Class>,
+        class: &Class,
+    ) -> StdResult<Option<(SubMsg<T>, CollectionInfoUpdate)>> {
+        // bound nft contracts keep their own collection info
+        if !UPDATE_VOUCHER_COLLECTION_INFO
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            || CLASS_MAPPINGS.has(deps.storage, class_id_info.class_id.clone())
+        {
+            return Ok(None);
+        }
+        let Some(collection_data) = collection_data_from_class(class) else {
+            return Ok(None);
+        };
+        let changes = get_collection_info_changes(
+            previous_class
+                .as_ref()
+                .and_then(collection_data_from_class)
+                .as_ref(),
+            &collection_data,
+        );
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let cw721_admin = CW721_ADMIN.load(deps.storage)?.map(|a| a.to_string());
+        let message = SubMsg::<T>::reply_always(
+            WasmMsg::Execute {
+                contract_addr: class_id_info.address.to_string(),
+                msg: self.update_collection_info_msg(
+                    deps,
+                    env,
+                    &class_id_info.class_id,
+                    collection_data,
+                    cw721_admin,
+                )?,
+                funds: vec![],
+            },
+            UPDATE_COLLECTION_INFO_REPLY_ID,
+        );
+        let update = CollectionInfoUpdate {
+            class_id: class_id_info.class_id.clone(),
+            nft_contract: class_id_info.address.clone(),
+            changes: changes.into_iter().map(String::from).collect(),
+        };
+        Ok(Some((message, update)))
+    }
+
+    /// Default implementation using `cw721_metadata_onchain::msg::ExecuteMsg::UpdateCollectionInfo`
+    fn update_collection_info_msg(
+        &self,
+        deps: Deps,
+        env: &Env,
+        class_id: &ClassId,
+        collection_data: CollectionData,
+        cw721_admin: Option<String>,
+    ) -> StdResult<Binary> {
+        let ContractInfoResponse { creator, admin, .. } = deps
+            .querier
+            .query_wasm_contract_info(env.contract.address.to_string())?;
+        let (collection_data, profile_royalty_payee) =
+            apply_destination_profile(deps.api, collection_data);
+        let royalty_payee = get_royalty_payee(deps, class_id, profile_royalty_payee)?;
+        // same payment address as used on instantiation
+        let cw721_admin_or_ics721_admin_or_ics721_creator =
+            cw721_admin.or(admin).unwrap_or(creator);
+        let extension = collection_data
+            .extension
+            .map(|ext| cw721::msg::CollectionExtensionMsg {
+                description: Some(ext.description),
+                image: Some(ext.image),
+                external_link: ext.external_link,
+                explicit_content: ext.explicit_content,
+                start_trading_time: ext.start_trading_time,
+                royalty_info: ext.royalty_info.map(|r| RoyaltyInfoResponse {
+                    // r.payment_address cant be used, since it is from another chain
+                    payment_address: royalty_payee
+                        .unwrap_or(cw721_admin_or_ics721_admin_or_ics721_creator),
+                    share: r.share,
+                }),
+            });
+        to_json_binary(
+            &cw721_metadata_onchain::msg::ExecuteMsg::UpdateCollectionInfo {
+                collection_info: cw721::msg::CollectionInfoMsg {
+                    name: Some(collection_data.name),
+                    symbol: Some(collection_data.symbol),
+                    extension,
+                },
+            },
+        )
     }
 
     fn create_instantiate_msg(
@@ -565,48 +1712,101 @@ where
         if let Some(nft_contract) = maybe_class_id_info {
             Ok((nft_contract, vec![]))
         } else {
-            let class_id = ClassId::new(class.id.clone());
-            let cw721_code_id = CW721_CODE_ID.load(deps.storage)?;
             // for creating a predictable nft contract using, using instantiate2, we need: checksum, creator, and salt:
             // - using class id as salt for instantiating nft contract guarantees a) predictable address and b) uniqueness
-            // for this salt must be of length 32 bytes, so we use sha256 to hash class id
-            let mut hasher = Sha256::new();
-            hasher.update(class_id.as_bytes());
-            let salt = hasher.finalize().to_vec();
-
-            let nft_contract = get_instantiate2_address(
-                deps.as_ref(),
-                env.contract.address.as_str(),
-                &salt,
-                cw721_code_id,
-            )?;
+            let salt = get_voucher_salt(deps.storage, &class.id)?;
 
-            // Save classId <-> contract mappings.
-            let class_id_info = ClassIdInfo {
-                class_id: class_id.clone(),
-                address: nft_contract.clone(),
-            };
-            CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class.id, &class_id_info)?;
-
-            let cw721_admin = CW721_ADMIN.load(deps.storage)?.map(|a| a.to_string());
-            let message = SubMsg::<T>::reply_on_success(
-                WasmMsg::Instantiate2 {
-                    admin: cw721_admin.clone(),
-                    code_id: cw721_code_id,
-                    msg: self.init_msg(deps.as_ref(), env, &class, cw721_admin)?,
-                    funds: vec![],
-                    // Attempting to fit the class ID in the label field
-                    // can make this field too long which causes data
-                    // errors in the SDK.
-                    label: "ics-721 debt-voucher cw-721".to_string(),
-                    salt: salt.into(),
-                },
-                INSTANTIATE_CW721_REPLY_ID,
-            );
+            let (class_id_info, message) = self.create_instantiate2_msg(deps, env, &class, salt)?;
             Ok((class_id_info, vec![message]))
         }
     }
 
+    /// Saves the mapping for the class and creates the instantiate2 message of its voucher contract.
+    fn create_instantiate2_msg(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        class: &Class,
+        salt: Vec<u8>,
+    ) -> Result<(ClassIdInfo, SubMsg<T>), ContractError> {
+        let class_id = ClassId::new(class.id.clone());
+        let rule = get_voucher_code_id_rule(deps.storage, &class_id, class.data.as_ref())?;
+        let cw721_code_id = match &rule {
+            Some(rule) => rule.code_id,
+            None => CW721_CODE_ID.load(deps.storage)?,
+        };
+        let nft_contract = get_instantiate2_address(
+            deps.as_ref(),
+            env.contract.address.as_str(),
+            &salt,
+            cw721_code_id,
+        )?;
+
+        // Save classId <-> contract mappings.
+        let class_id_info = ClassIdInfo {
+            class_id: class_id.clone(),
+            address: nft_contract.clone(),
+        };
+        CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class.id, &class_id_info)?;
+
+        let cw721_admin = CW721_ADMIN.load(deps.storage)?.map(|a| a.to_string());
+        let message = SubMsg::<T>::reply_on_success(
+            WasmMsg::Instantiate2 {
+                admin: cw721_admin.clone(),
+                code_id: cw721_code_id,
+                msg: self.voucher_init_msg(
+                    deps.as_ref(),
+                    env,
+                    class,
+                    cw721_admin,
+                    &rule.map(|rule| rule.init_msg).unwrap_or_default(),
+                )?,
+                funds: vec![],
+                // Attempting to fit the class ID in the label field
+                // can make this field too long which causes data
+                // errors in the SDK.
+                label: "ics-721 debt-voucher cw-721".to_string(),
+                salt: salt.into(),
+            },
+            INSTANTIATE_CW721_REPLY_ID,
+        );
+        Ok((class_id_info, message))
+    }
+
+    /// Creates the instantiate message for a voucher contract, as selected by voucher code ID rules.
+    fn voucher_init_msg(
+        &self,
+        deps: Deps,
+        env: &Env,
+        class: &Class,
+        cw721_admin: Option<String>,
+        voucher_init_msg: &VoucherInitMsg,
+    ) -> StdResult<Binary> {
+        let init_msg = self.init_msg(deps, env, class, cw721_admin)?;
+        match voucher_init_msg {
+            VoucherInitMsg::Default => Ok(init_msg),
+            VoucherInitMsg::Extend(fields) => extend_init_msg(&init_msg, fields),
+        }
+    }
+
+    /// Default implementation using `cw721_metadata_onchain::msg::ExecuteMsg::UpdateCreatorOwnership`.
+    /// New owner has to accept ownership on the cw721 contract.
+    fn transfer_collection_ownership_msg(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        new_owner: &Addr,
+    ) -> StdResult<Binary> {
+        to_json_binary(
+            &cw721_metadata_onchain::msg::ExecuteMsg::UpdateCreatorOwnership(
+                cw_ownable::Action::TransferOwnership {
+                    new_owner: new_owner.to_string(),
+                    expiry: None,
+                },
+            ),
+        )
+    }
+
     /// Default implementation using `cw721_base::msg::InstantiateMsg`
     fn init_msg(
         &self,
@@ -630,17 +1830,17 @@ where
             name: class.id.clone().into(),
             symbol: class.id.clone().into(),
             collection_info_extension: None, // extension is set below, in case there's collection data
-            creator: Some(cw721_admin_or_ics721_admin_or_ics721_creator.clone()), // TODO maybe better using cw721 admin?
+            // ICS721 is creator, so it can update collection info and hand over creator ownership to collection owner
+            creator: Some(env.contract.address.to_string()),
             minter: Some(env.contract.address.to_string()),
             withdraw_address: Some(creator.clone()),
         };
 
         // use collection data for setting name and symbol
-        let collection_data = class
-            .data
-            .clone()
-            .and_then(|binary| from_json::<CollectionData>(binary).ok());
-        if let Some(collection_data) = collection_data {
+        if let Some(collection_data) = collection_data_from_class(class) {
+            let (collection_data, profile_royalty_payee) =
+                apply_destination_profile(deps.api, collection_data);
+            let royalty_payee = get_royalty_payee(deps, &class.id, profile_royalty_payee)?;
             instantiate_msg.name = collection_data.name;
             instantiate_msg.symbol = collection_data.symbol;
             let collection_info_extension_msg =
@@ -653,7 +1853,9 @@ where
                         explicit_content: ext.explicit_content,
                         start_trading_time: ext.start_trading_time,
                         royalty_info: ext.royalty_info.map(|r| RoyaltyInfoResponse {
-                            payment_address: cw721_admin_or_ics721_admin_or_ics721_creator, // r.payment_address cant be used, since it is from another chain
+                            // r.payment_address cant be used, since it is from another chain
+                            payment_address: royalty_payee
+                                .unwrap_or(cw721_admin_or_ics721_admin_or_ics721_creator),
                             share: r.share,
                         }),
                     });
@@ -713,6 +1915,27 @@ where
         let receiver = deps.api.addr_validate(&receiver)?;
         let nft_contract =
             load_nft_contract_for_class_id(deps.as_ref().storage, class_id.to_string())?;
+        let translator = CLASS_METADATA_TRANSLATOR.may_load(deps.storage, class_id.clone())?;
+
+        // bound nft contracts may already hold local NFTs with same token IDs
+        if CLASS_MAPPINGS.has(deps.storage, class_id.clone()) {
+            for token in &tokens {
+                let token_id: String = token.id.clone().into();
+                let owner: StdResult<cw721::msg::OwnerOfResponse> = deps.querier.query_wasm_smart(
+                    &nft_contract,
+                    &cw721_metadata_onchain::msg::QueryMsg::OwnerOf {
+                        token_id: token_id.clone(),
+                        include_expired: None,
+                    },
+                );
+                if owner.is_ok() {
+                    return Err(ContractError::TokenIdCollision {
+                        nft_contract: nft_contract.to_string(),
+                        token_id,
+                    });
+                }
+            }
+        }
 
         let mint = tokens
             .into_iter()
@@ -727,21 +1950,14 @@ where
                     &data,
                 )?;
 
-                // parse token data and check whether it is of type NftExtension
-                let extension: Option<NftExtensionMsg> = match data {
-                    Some(data) => from_json::<NftExtension>(data)
-                        .ok()
-                        .map(|ext| NftExtensionMsg {
-                            animation_url: ext.animation_url,
-                            attributes: ext.attributes,
-                            background_color: ext.background_color,
-                            description: ext.description,
-                            external_url: ext.external_url,
-                            image: ext.image,
-                            image_data: ext.image_data,
-                            youtube_url: ext.youtube_url,
-                            name: ext.name,
-                        }),
+                // translate token data into NftExtension, token data of unknown format is only kept in storage
+                let extension = match data {
+                    Some(data) => self.translate_token_data(
+                        deps.as_ref(),
+                        &class_id,
+                        translator.as_ref(),
+                        &data,
+                    )?,
                     None => None,
                 };
 
@@ -764,13 +1980,25 @@ where
             .add_messages(mint))
     }
 
+    /// Default implementation uses the translator set for the class, otherwise the format is detected.
+    /// Contracts may override this for supporting additional formats.
+    fn translate_token_data(
+        &self,
+        _deps: Deps,
+        _class_id: &ClassId,
+        translator: Option<&MetadataTranslator>,
+        data: &Binary,
+    ) -> StdResult<Option<NftExtensionMsg>> {
+        Ok(translate_token_data(translator, data))
+    }
+
     fn callback_redeem_outgoing_channel_entries(
         &self,
         deps: DepsMut,
         entries: Vec<(ClassId, TokenId)>,
     ) -> Result<Response<T>, ContractError> {
         for (class_id, token_id) in entries {
-            OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, (class_id, token_id));
+            OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, (class_id, token_id))?;
         }
         Ok(Response::default().add_attribute("method", "callback_redeem_outgoing_channel_entries"))
     }
@@ -786,9 +2014,20 @@ where
         Ok(Response::default().add_attribute("method", "callback_save_incoming_channel_entries"))
     }
 
-    fn migrate(
+    fn callback_save_claimable_vouchers(
         &self,
         deps: DepsMut,
+        entries: Vec<((ClassId, TokenId), ClaimableVoucher)>,
+    ) -> Result<Response<T>, ContractError> {
+        for (key, claimable) in entries {
+            CLAIMABLE_VOUCHERS.save(deps.storage, key, &claimable)?;
+        }
+        Ok(Response::default().add_attribute("method", "callback_save_claimable_vouchers"))
+    }
+
+    fn migrate(
+        &self,
+        mut deps: DepsMut,
         _env: Env,
         msg: MigrateMsg,
     ) -> Result<Response<T>, ContractError> {
@@ -800,6 +2039,12 @@ where
                 cw721_base_code_id,
                 cw721_admin,
                 contract_addr_length,
+                class_data_refresh_policy,
+                update_voucher_collection_info,
+                token_metadata_policy,
+                voucher_code_id_rules,
+                claim_mode,
+                callback_gas_limits,
             } => {
                 // disables incoming proxy if none is provided!
                 INCOMING_PROXY.save(
@@ -836,6 +2081,30 @@ where
                     CONTRACT_ADDR_LENGTH.remove(deps.storage);
                 }
 
+                if let Some(class_data_refresh_policy) = class_data_refresh_policy.clone() {
+                    CLASS_DATA_REFRESH_POLICY.save(deps.storage, &class_data_refresh_policy)?;
+                }
+                if let Some(update_voucher_collection_info) = update_voucher_collection_info {
+                    UPDATE_VOUCHER_COLLECTION_INFO
+                        .save(deps.storage, &update_voucher_collection_info)?;
+                }
+                if let Some(token_metadata_policy) = token_metadata_policy.clone() {
+                    token_metadata_policy.validate(deps.api)?;
+                    TOKEN_METADATA_POLICY.save(deps.storage, &token_metadata_policy)?;
+                }
+                if let Some(voucher_code_id_rules) = voucher_code_id_rules.clone() {
+                    for rule in &voucher_code_id_rules {
+                        rule.validate()?;
+                    }
+                    VOUCHER_CODE_ID_RULES.save(deps.storage, &voucher_code_id_rules)?;
+                }
+                if let Some(claim_mode) = claim_mode.clone() {
+                    CLAIM_MODE.save(deps.storage, &claim_mode)?;
+                }
+                if let Some(callback_gas_limits) = callback_gas_limits.clone() {
+                    CALLBACK_GAS_LIMITS.save(deps.storage, &callback_gas_limits)?;
+                }
+
                 let response = Response::default()
                     .add_attribute("method", "migrate")
                     .add_attribute("pauser", pauser.map_or_else(|| "none".to_string(), |or| or))
@@ -868,9 +2137,39 @@ where
                         "contract_addr_length",
                         contract_addr_length
                             .map_or_else(|| "none".to_string(), |or| or.to_string()),
+                    )
+                    .add_attribute(
+                        "class_data_refresh_policy",
+                        class_data_refresh_policy
+                            .map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+                    )
+                    .add_attribute(
+                        "update_voucher_collection_info",
+                        update_voucher_collection_info
+                            .map_or_else(|| "none".to_string(), |or| or.to_string()),
+                    )
+                    .add_attribute(
+                        "token_metadata_policy",
+                        token_metadata_policy
+                            .map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+                    )
+                    .add_attribute(
+                        "voucher_code_id_rules",
+                        voucher_code_id_rules
+                            .map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+                    )
+                    .add_attribute(
+                        "claim_mode",
+                        claim_mode.map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
+                    )
+                    .add_attribute(
+                        "callback_gas_limits",
+                        callback_gas_limits
+                            .map_or_else(|| "none".to_string(), |or| format!("{or:?}")),
                     );
 
-                self.migrate_legacy(deps, response)
+                let response = self.migrate_legacy(deps.branch(), response)?;
+                self.migrate_channel_indexes(deps, response)
             }
         }
     }
@@ -920,4 +2219,37 @@ where
             Ok(response)
         }
     }
+
+    // TODO once migrated, this complete block can be deleted
+    fn migrate_channel_indexes(
+        &self,
+        deps: DepsMut,
+        response: Response<T>,
+    ) -> Result<Response<T>, ContractError> {
+        let mut response = response;
+        for (name, class_token_to_channel) in [
+            ("outgoing", &OUTGOING_CLASS_TOKEN_TO_CHANNEL),
+            ("incoming", &INCOMING_CLASS_TOKEN_TO_CHANNEL),
+        ] {
+            // entries stored before the channel maps had indexes, have no index entries yet
+            let is_indexed = class_token_to_channel
+                .idx
+                .channel
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_some();
+            let is_empty = class_token_to_channel
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_none();
+            if is_indexed || is_empty || CHANNEL_INDEX_MIGRATIONS.has(deps.storage, name) {
+                continue;
+            }
+            // entries may be too many for a single tx, so they are indexed in pages by `MigrateChannelIndexes`
+            CHANNEL_INDEX_MIGRATIONS.save(deps.storage, name, &ChannelIndexMigration::default())?;
+            response =
+                response.add_attribute(format!("pending {name} channel index migration"), "true");
+        }
+        Ok(response)
+    }
 }
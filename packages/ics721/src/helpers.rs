@@ -1,19 +1,28 @@
 use cosmwasm_std::{
-    from_json, instantiate2_address, to_json_binary, Addr, Binary, CodeInfoResponse, Deps,
-    IbcPacket, StdResult, Storage, SubMsg, WasmMsg,
+    from_json, instantiate2_address, to_json_binary, Addr, Binary, CodeInfoResponse,
+    ContractInfoResponse, Deps, Env, IbcAcknowledgement, IbcPacket, StdResult, Storage, SubMsg,
+    WasmMsg,
 };
 use serde::Deserialize;
 
 use crate::{
-    ibc::ACK_CALLBACK_REPLY_ID,
-    state::{CONTRACT_ADDR_LENGTH, INCOMING_PROXY},
+    ibc::{ACK_CALLBACK_REPLY_ID, RECEIVE_CALLBACK_REPLY_ID},
+    ibc_helpers::ack_success,
+    msg::{CallbackMsg, ExecuteMsg},
+    state::{
+        AckCallback, CollectionInfoUpdate, ReceiveCallback, CALLBACK_GAS_LIMITS,
+        CONTRACT_ADDR_LENGTH, CW721_CODE_ID, INCOMING_PROXY, PENDING_ACK_CALLBACKS,
+        PENDING_COLLECTION_INFO_UPDATES, PENDING_VOUCHER_MIGRATIONS, VOUCHER_MIGRATION_TARGET,
+    },
     ContractError,
 };
 use ics721_types::{
     ibc_types::NonFungibleTokenPacketData,
     types::{
-        Ics721AckCallbackMsg, Ics721Callbacks, Ics721Memo, Ics721ReceiveCallbackMsg, Ics721Status,
-        ReceiverExecuteMsg,
+        IbcCallbackData, IbcCallbacksMemo, IbcDestinationCallbackMsg, IbcSourceCallbackMsg,
+        Ics721AckCallbackMsg, Ics721AckCallbackMsgV2, Ics721CallbackContext, Ics721CallbackVersion,
+        Ics721Callbacks, Ics721ErrorCode, Ics721Memo, Ics721ReceiveCallbackMsg,
+        Ics721ReceiveCallbackMsgV2, Ics721Status, ReceiveCallbackMode, ReceiverExecuteMsg,
     },
 };
 
@@ -29,46 +38,229 @@ fn parse_callback(memo: Option<String>) -> Option<Ics721Callbacks> {
     parse_memo::<Ics721Memo>(memo)?.callbacks
 }
 
-// Create a subMsg that execute the callback on the sender callback
-// we use a subMsg on error because we don't want to fail the whole tx
-// if the callback fails
-// if we were to fail the whole tx, the NFT would have been minted on
-// the other chain while the NFT on this chain would not have been
-// burned
-pub(crate) fn ack_callback_msg(
+/// Parse ADR-008 callbacks from the memo field, which unlike `Ics721Memo` is plain JSON
+fn parse_ibc_callbacks(memo: Option<&String>) -> Option<IbcCallbacksMemo> {
+    from_json::<IbcCallbacksMemo>(memo?.as_bytes()).ok()
+}
+
+// Create the callback on the sender callback, dispatched by `ack_callback_submessages`
+pub(crate) fn ack_callback(
     deps: Deps,
     status: Ics721Status,
+    error_code: Option<Ics721ErrorCode>,
+    ibc_packet: &IbcPacket,
     packet: NonFungibleTokenPacketData,
     nft_contract: String,
-) -> Option<SubMsg> {
+    relayer: &Addr,
+) -> Option<AckCallback> {
     // Get the callback object
     let callbacks = parse_callback(packet.memo.clone())?;
+    let ack_callback_data = callbacks.ack_callback_data?;
 
     // Validate the address
     let receiver = callbacks.ack_callback_addr.unwrap_or(packet.sender.clone());
-    let contract_addr = deps.api.addr_validate(receiver.as_str()).ok()?.to_string();
+    let contract = deps.api.addr_validate(receiver.as_str()).ok()?;
 
     // Create the message we send to the contract
     // The status is the status we want to send back to the contract
     // The msg is the msg we forward from the sender
-    let msg = to_json_binary(&ReceiverExecuteMsg::Ics721AckCallback(
-        Ics721AckCallbackMsg {
-            status,
-            nft_contract,
-            original_packet: packet,
-            msg: callbacks.ack_callback_data?,
-        },
-    ))
+    let msg = match callbacks.version.unwrap_or_default() {
+        Ics721CallbackVersion::V1 => to_json_binary(&ReceiverExecuteMsg::Ics721AckCallback(
+            Ics721AckCallbackMsg {
+                status: status.clone(),
+                nft_contract,
+                original_packet: packet,
+                msg: ack_callback_data,
+                error_code,
+            },
+        )),
+        // packet has been sent from this chain, so source is the local endpoint
+        Ics721CallbackVersion::V2 => {
+            let context = Ics721CallbackContext {
+                local_endpoint: ibc_packet.src.clone(),
+                counterparty_endpoint: ibc_packet.dest.clone(),
+                local_class_id: packet.class_id.clone(),
+                sequence: ibc_packet.sequence,
+                relayer: relayer.to_string(),
+            };
+            to_json_binary(&ReceiverExecuteMsg::Ics721AckCallbackV2(
+                Ics721AckCallbackMsgV2 {
+                    status: status.clone(),
+                    nft_contract,
+                    original_packet: packet,
+                    msg: ack_callback_data,
+                    error_code,
+                    context,
+                },
+            ))
+        }
+    }
     .ok()?;
 
-    Some(SubMsg::reply_on_error(
-        WasmMsg::Execute {
-            contract_addr,
-            msg,
+    let gas_limit = CALLBACK_GAS_LIMITS
+        .may_load(deps.storage)
+        .ok()?
+        .unwrap_or_default()
+        .ack(callbacks.ack_callback_gas_limit);
+    Some(AckCallback {
+        contract,
+        msg,
+        gas_limit,
+        packet: ibc_packet.clone(),
+        status,
+    })
+}
+
+/// Creates the ADR-008 like `src_callback` on ack or timeout, dispatched by `ack_callback_submessages`. Same as ibc-go,
+/// only the packet sender can be called back, otherwise there is no callback.
+pub(crate) fn src_callback(
+    deps: Deps,
+    packet: &NonFungibleTokenPacketData,
+    status: Ics721Status,
+    msg: IbcSourceCallbackMsg,
+) -> Option<AckCallback> {
+    let callback = parse_ibc_callbacks(packet.memo.as_ref())?.src_callback?;
+    if callback.address != packet.sender {
+        return None;
+    }
+    let contract = deps.api.addr_validate(&callback.address).ok()?;
+    let ibc_packet = match &msg {
+        IbcSourceCallbackMsg::Acknowledgement(ack) => ack.original_packet.clone(),
+        IbcSourceCallbackMsg::Timeout(timeout) => timeout.packet.clone(),
+    };
+    let msg = to_json_binary(&ReceiverExecuteMsg::IbcSourceCallback(msg)).ok()?;
+
+    let gas_limit = CALLBACK_GAS_LIMITS
+        .may_load(deps.storage)
+        .ok()?
+        .unwrap_or_default()
+        .ack(callback.gas_limit.map(|gas_limit| gas_limit.u64()));
+    Some(AckCallback {
+        contract,
+        msg,
+        gas_limit,
+        packet: ibc_packet,
+        status,
+    })
+}
+
+// Create subMsgs that execute the ack callbacks
+// we use subMsgs because we don't want to fail the whole tx
+// if a callback fails
+// if we were to fail the whole tx, the NFT would have been minted on
+// the other chain while the NFT on this chain would not have been
+// burned
+// callbacks are queued, so on reply a failed callback is stored for retry,
+// and with a gas limit, out of gas is caught by reply
+pub(crate) fn ack_callback_submessages<T>(
+    storage: &mut dyn Storage,
+    callbacks: impl IntoIterator<Item = AckCallback>,
+) -> StdResult<Vec<SubMsg<T>>> {
+    let mut pending = PENDING_ACK_CALLBACKS.may_load(storage)?.unwrap_or_default();
+    let mut submessages = vec![];
+    for callback in callbacks {
+        submessages.push(SubMsg {
+            gas_limit: callback.gas_limit,
+            ..SubMsg::reply_always(
+                WasmMsg::Execute {
+                    contract_addr: callback.contract.to_string(),
+                    msg: callback.msg.clone(),
+                    funds: vec![],
+                },
+                ACK_CALLBACK_REPLY_ID,
+            )
+        });
+        pending.push(callback);
+    }
+    if !submessages.is_empty() {
+        PENDING_ACK_CALLBACKS.save(storage, &pending)?;
+    }
+    Ok(submessages)
+}
+
+/// Completes `MigrateVoucherContracts` once its last page has been dispatched and all migrations have replied. Only in
+/// case no migration failed, new voucher contracts are instantiated with the new code ID. Returns whether code ID has
+/// been switched, or None in case migration is not complete yet.
+pub(crate) fn complete_voucher_migration(storage: &mut dyn Storage) -> StdResult<Option<bool>> {
+    let Some(target) = VOUCHER_MIGRATION_TARGET.may_load(storage)? else {
+        return Ok(None);
+    };
+    let pending = PENDING_VOUCHER_MIGRATIONS
+        .may_load(storage)?
+        .unwrap_or_default();
+    if !target.last_page || !pending.is_empty() {
+        return Ok(None);
+    }
+    VOUCHER_MIGRATION_TARGET.remove(storage);
+    if target.failed > 0 {
+        return Ok(Some(false));
+    }
+    CW721_CODE_ID.save(storage, &target.code_id)?;
+    Ok(Some(true))
+}
+
+/// Queues a collection info update, so its reply can emit the result.
+pub(crate) fn queue_collection_info_update(
+    storage: &mut dyn Storage,
+    update: CollectionInfoUpdate,
+) -> StdResult<()> {
+    let mut pending = PENDING_COLLECTION_INFO_UPDATES
+        .may_load(storage)?
+        .unwrap_or_default();
+    pending.push(update);
+    PENDING_COLLECTION_INFO_UPDATES.save(storage, &pending)
+}
+
+/// Gets the ADR-008 like `dest_callback` from the memo field, if any.
+pub(crate) fn get_dest_callback(packet: &NonFungibleTokenPacketData) -> Option<IbcCallbackData> {
+    parse_ibc_callbacks(packet.memo.as_ref())?.dest_callback
+}
+
+/// Creates the ADR-008 like `dest_callback` message, executed once NFTs have been received. Unlike ibc-go, where only
+/// out of gas fails the receive, any error of the callback fails the receive with an error ack.
+pub(crate) fn dest_callback_msg(
+    deps: Deps,
+    env: &Env,
+    ibc_packet: &IbcPacket,
+    packet: &NonFungibleTokenPacketData,
+) -> StdResult<Option<WasmMsg>> {
+    let Some(callback) = get_dest_callback(packet) else {
+        return Ok(None);
+    };
+    let Ok(contract_addr) = deps.api.addr_validate(&callback.address) else {
+        return Ok(None);
+    };
+    let msg = WasmMsg::Execute {
+        contract_addr: contract_addr.into_string(),
+        msg: to_json_binary(&ReceiverExecuteMsg::IbcDestinationCallback(
+            IbcDestinationCallbackMsg {
+                packet: ibc_packet.clone(),
+                ack: IbcAcknowledgement::new(ack_success()),
+            },
+        ))?,
+        funds: vec![],
+    };
+    let gas_limit = CALLBACK_GAS_LIMITS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .receive(callback.gas_limit.map(|gas_limit| gas_limit.u64()));
+    execute_with_gas_limit(env, msg, gas_limit).map(Some)
+}
+
+/// In case of a gas limit, msg is wrapped in `CallbackMsg::ExecuteWithGasLimit`, so it can be used as an operand of
+/// `CallbackMsg::Conjunction`.
+fn execute_with_gas_limit(env: &Env, msg: WasmMsg, gas_limit: Option<u64>) -> StdResult<WasmMsg> {
+    match gas_limit {
+        Some(gas_limit) => Ok(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::ExecuteWithGasLimit {
+                msg,
+                gas_limit,
+            }))?,
             funds: vec![],
-        },
-        ACK_CALLBACK_REPLY_ID,
-    ))
+        }),
+        None => Ok(msg),
+    }
 }
 
 /// If there is an incoming proxy, let proxy validate the packet, in case it fails, we fail the transfer
@@ -105,37 +297,117 @@ pub(crate) fn get_receive_callback(
     ))
 }
 
+/// Receive callback of an incoming packet, depending on its `ReceiveCallbackMode`.
+pub(crate) enum ReceiveCallbackMsg {
+    /// Executed as part of receiving NFTs, so a failing callback fails the transfer.
+    Required(WasmMsg),
+    /// Dispatched once NFTs have been received, so a failing callback is recorded but doesn't fail the transfer.
+    BestEffort(ReceiveCallback),
+}
+
+/// Dispatches a best-effort receive callback, replying always. The callback is kept in `PENDING_RECEIVE_CALLBACK`
+/// for the reply, so in case it fails, it is recorded.
+pub(crate) fn receive_callback_submessage<T>(callback: ReceiveCallback) -> SubMsg<T> {
+    SubMsg {
+        gas_limit: callback.gas_limit,
+        ..SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: callback.contract.into_string(),
+                msg: callback.msg,
+                funds: vec![],
+            },
+            RECEIVE_CALLBACK_REPLY_ID,
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_receive_callback_msg(
     deps: Deps,
+    env: &Env,
     packet: &NonFungibleTokenPacketData,
     receive_callback_data: Binary,
     receive_callback_addr: Option<String>,
     nft_contract: String,
-) -> Option<WasmMsg> {
+    context: Ics721CallbackContext,
+    is_redemption: bool,
+) -> Option<ReceiveCallbackMsg> {
     let callback_receiver = receive_callback_addr.unwrap_or(packet.receiver.clone());
-    let contract_addr = deps
-        .api
-        .addr_validate(callback_receiver.as_str())
-        .ok()?
-        .to_string();
+    let contract = deps.api.addr_validate(callback_receiver.as_str()).ok()?;
 
     // Create the message we send to the contract
     // The status is the status we want to send back to the contract
     // The msg is the msg we forward from the sender
-    let msg = to_json_binary(&ReceiverExecuteMsg::Ics721ReceiveCallback(
-        Ics721ReceiveCallbackMsg {
-            msg: receive_callback_data,
-            nft_contract,
-            original_packet: packet.clone(),
-        },
-    ))
+    let callbacks = parse_callback(packet.memo.clone());
+    let version = callbacks
+        .as_ref()
+        .and_then(|callbacks| callbacks.version.clone())
+        .unwrap_or_default();
+    let msg = match version {
+        Ics721CallbackVersion::V1 => to_json_binary(&ReceiverExecuteMsg::Ics721ReceiveCallback(
+            Ics721ReceiveCallbackMsg {
+                msg: receive_callback_data,
+                nft_contract,
+                original_packet: packet.clone(),
+            },
+        )),
+        Ics721CallbackVersion::V2 => {
+            let (minted_token_ids, redeemed_token_ids) = if is_redemption {
+                (vec![], packet.token_ids.clone())
+            } else {
+                (packet.token_ids.clone(), vec![])
+            };
+            to_json_binary(&ReceiverExecuteMsg::Ics721ReceiveCallbackV2(
+                Ics721ReceiveCallbackMsgV2 {
+                    msg: receive_callback_data,
+                    nft_contract,
+                    original_packet: packet.clone(),
+                    context,
+                    minted_token_ids,
+                    redeemed_token_ids,
+                },
+            ))
+        }
+    }
     .ok()?;
 
-    Some(WasmMsg::Execute {
-        contract_addr,
-        msg,
-        funds: vec![],
-    })
+    let (gas_limit, mode) = callbacks.map_or((None, ReceiveCallbackMode::default()), |callbacks| {
+        (
+            callbacks.receive_callback_gas_limit,
+            callbacks.receive_callback_mode.unwrap_or_default(),
+        )
+    });
+    let gas_limit = CALLBACK_GAS_LIMITS
+        .may_load(deps.storage)
+        .ok()?
+        .unwrap_or_default()
+        .receive(gas_limit);
+    match mode {
+        ReceiveCallbackMode::Required => execute_with_gas_limit(
+            env,
+            WasmMsg::Execute {
+                contract_addr: contract.into_string(),
+                msg,
+                funds: vec![],
+            },
+            gas_limit,
+        )
+        .ok()
+        .map(ReceiveCallbackMsg::Required),
+        ReceiveCallbackMode::BestEffort => Some(ReceiveCallbackMsg::BestEffort(ReceiveCallback {
+            contract,
+            msg,
+            gas_limit,
+        })),
+    }
+}
+
+/// Checks whether sender is the admin of this contract. In case contract has no admin, no one is admin.
+pub fn is_admin(deps: Deps, env: &Env, sender: &Addr) -> StdResult<bool> {
+    let ContractInfoResponse { admin, .. } = deps
+        .querier
+        .query_wasm_contract_info(env.contract.address.to_string())?;
+    Ok(admin.map_or(false, |admin| admin == sender.as_str()))
 }
 
 pub fn get_instantiate2_address(
@@ -1,21 +1,34 @@
 use cosmwasm_std::{
-    from_json, to_json_binary, DepsMut, Empty, Env, Event, IbcBasicResponse, IbcChannelCloseMsg,
-    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacket, IbcPacketAckMsg,
-    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Never, Reply, Response,
-    StdResult, SubMsgResult, WasmMsg,
+    from_json, to_json_binary, Addr, Deps, DepsMut, Empty, Env, Event, IbcBasicResponse,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacket,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Never, Reply,
+    Response, StdResult, SubMsg, SubMsgResult, WasmMsg,
 };
 use cw_utils::parse_reply_instantiate_data;
-use ics721_types::{ibc_types::NonFungibleTokenPacketData, types::Ics721Status};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    types::{
+        IbcAckCallbackMsg, IbcSourceCallbackMsg, IbcTimeoutCallbackMsg, Ics721ErrorCode,
+        Ics721Status,
+    },
+};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    helpers::ack_callback_msg,
-    ibc_helpers::{ack_fail, ack_success, try_get_ack_error, validate_order_and_version},
+    helpers::{
+        ack_callback, ack_callback_submessages, complete_voucher_migration,
+        receive_callback_submessage, src_callback,
+    },
+    ibc_helpers::{ack_fail_with_code, ack_success, try_get_ack_error, validate_order_and_version},
     ibc_packet_receive::receive_ibc_packet,
     query::{load_class_id_for_nft_contract, load_nft_contract_for_class_id},
     state::{
-        IBC_RECEIVE_TOKEN_METADATA, INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY,
-        OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY,
+        ChannelInfo, FailedAckCallback, FailedReceiveCallback, VoucherMigrationStatus,
+        CHANNEL_INFOS, FAILED_ACK_CALLBACKS, FAILED_RECEIVE_CALLBACKS, IBC_RECEIVE_TOKEN_METADATA,
+        INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY, NEXT_FAILED_ACK_CALLBACK_ID,
+        NEXT_FAILED_RECEIVE_CALLBACK_ID, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY,
+        PENDING_ACK_CALLBACKS, PENDING_COLLECTION_INFO_UPDATES, PENDING_RECEIVE_CALLBACK,
+        PENDING_VOUCHER_MIGRATIONS, VOUCHER_MIGRATIONS, VOUCHER_MIGRATION_TARGET,
     },
     ContractError,
 };
@@ -32,6 +45,12 @@ pub(crate) const INSTANTIATE_OUTGOING_PROXY_REPLY_ID: u64 = 2;
 pub(crate) const ACK_AND_DO_NOTHING_REPLY_ID: u64 = 3;
 /// Reply on callback
 pub(crate) const ACK_CALLBACK_REPLY_ID: u64 = 4;
+/// Reply on updating collection info of an existing voucher contract. Updating is best effort, so errors are ignored.
+pub(crate) const UPDATE_COLLECTION_INFO_REPLY_ID: u64 = 5;
+/// Reply on migrating a voucher contract, the result is stored for the pending voucher contract.
+pub(crate) const MIGRATE_VOUCHER_REPLY_ID: u64 = 6;
+/// Reply on best-effort receive callback, in case it fails it is recorded.
+pub(crate) const RECEIVE_CALLBACK_REPLY_ID: u64 = 7;
 /// The IBC version this contract expects to communicate with.
 pub const IBC_VERSION: &str = "ics721-1";
 
@@ -51,12 +70,23 @@ where
 
     fn ibc_channel_connect(
         &self,
-        _deps: DepsMut,
+        deps: DepsMut,
         _env: Env,
         msg: IbcChannelConnectMsg,
-    ) -> Result<IbcBasicResponse, ContractError> {
+    ) -> Result<IbcBasicResponse<T>, ContractError> {
         validate_order_and_version(msg.channel(), msg.counterparty_version())?;
 
+        let channel = msg.channel();
+        CHANNEL_INFOS.save(
+            deps.storage,
+            channel.endpoint.channel_id.clone(),
+            &ChannelInfo {
+                endpoint: channel.endpoint.clone(),
+                counterparty_endpoint: channel.counterparty_endpoint.clone(),
+                connection_id: Some(channel.connection_id.clone()),
+            },
+        )?;
+
         Ok(IbcBasicResponse::new()
             .add_attribute("method", "ibc_channel_connect")
             .add_attribute("channel", &msg.channel().endpoint.channel_id)
@@ -68,7 +98,7 @@ where
         _deps: DepsMut,
         _env: Env,
         msg: IbcChannelCloseMsg,
-    ) -> Result<IbcBasicResponse, ContractError> {
+    ) -> Result<IbcBasicResponse<T>, ContractError> {
         match msg {
             // Error any TX that would cause the channel to close that is
             // coming from the local chain.
@@ -98,30 +128,67 @@ where
         deps: DepsMut,
         env: Env,
         msg: IbcPacketReceiveMsg,
-    ) -> Result<IbcReceiveResponse, Never> {
+    ) -> Result<IbcReceiveResponse<T>, Never> {
         // Regardless of if our processing of this packet works we need to
         // commit an ACK to the chain. As such, we wrap all handling logic
         // in a seprate function and on error write out an error ack.
-        match receive_ibc_packet(deps, env, msg.packet) {
+        let result = self
+            .receive_custom_msgs(deps.as_ref(), &env, &msg.packet)
+            .and_then(|custom_msgs| {
+                receive_ibc_packet(deps, env, msg.packet, msg.relayer, custom_msgs)
+            });
+        match result {
             Ok(response) => Ok(response),
             Err(error) => Ok(IbcReceiveResponse::new()
                 .add_attribute("method", "ibc_packet_receive")
                 .add_attribute("error", error.to_string())
-                .set_ack(ack_fail(error.to_string()))),
+                .add_attribute("error_code", error.error_code().code().to_string())
+                .set_ack(ack_fail_with_code(error.to_string(), error.error_code()))),
         }
     }
 
+    /// Custom messages dispatched on receiving a packet, e.g. fees on voucher creation. They are dispatched as part
+    /// of the receive after the voucher messages, so a failing message results in an error ack, unless it is a
+    /// submessage replying on error. Default implementation adds none.
+    fn receive_custom_msgs(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _packet: &IbcPacket,
+    ) -> Result<Vec<SubMsg<T>>, ContractError> {
+        Ok(vec![])
+    }
+
     fn ibc_packet_ack(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         _env: Env,
         ack: IbcPacketAckMsg,
-    ) -> Result<IbcBasicResponse, ContractError> {
-        if let Some(error) = try_get_ack_error(&ack.acknowledgement) {
-            self.handle_packet_fail(deps, ack.original_packet, &error)
+    ) -> Result<IbcBasicResponse<T>, ContractError> {
+        let msg: NonFungibleTokenPacketData = from_json(&ack.original_packet.data)?;
+        let ack_error = try_get_ack_error(&ack.acknowledgement);
+        let src_callback = src_callback(
+            deps.as_ref(),
+            &msg,
+            ack_error.as_ref().map_or(Ics721Status::Success, |error| {
+                Ics721Status::Failed(error.error.clone())
+            }),
+            IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg {
+                acknowledgement: ack.acknowledgement.clone(),
+                original_packet: ack.original_packet.clone(),
+                relayer: ack.relayer.clone(),
+            }),
+        );
+        if let Some(error) = ack_error {
+            let response = self.handle_packet_fail(
+                deps.branch(),
+                ack.original_packet,
+                &error.error,
+                error.code,
+                &ack.relayer,
+            )?;
+            Ok(response.add_submessages(ack_callback_submessages(deps.storage, src_callback)?))
         } else {
-            let msg: NonFungibleTokenPacketData = from_json(&ack.original_packet.data)?;
-
             let nft_contract =
                 load_nft_contract_for_class_id(deps.storage, msg.class_id.to_string())?;
             // Burn all of the tokens being transfered out that were
@@ -137,7 +204,7 @@ where
                     });
                     if returning_to_source {
                         // This token's journey is complete, for now.
-                        INCOMING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key);
+                        INCOMING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key)?;
                         IBC_RECEIVE_TOKEN_METADATA
                             .remove(deps.storage, (msg.class_id.clone(), token.clone()));
 
@@ -153,15 +220,17 @@ where
                 },
             )?;
 
-            let callback = match ack_callback_msg(
+            let callback = ack_callback(
                 deps.as_ref(),
                 Ics721Status::Success,
+                None,
+                &ack.original_packet,
                 msg.clone(),
                 nft_contract.to_string(),
-            ) {
-                Some(msg) => vec![msg],
-                None => vec![],
-            };
+                &ack.relayer,
+            );
+            let callbacks =
+                ack_callback_submessages(deps.storage, callback.into_iter().chain(src_callback))?;
 
             let token_ids = format!("{:?}", msg.token_ids);
             let event = Event::new("ics721_ack_burn_vouchers")
@@ -178,18 +247,35 @@ where
                 .add_attribute("classId", msg.class_id)
                 .add_attribute("token_ids", token_ids)
                 .add_messages(burn_notices)
-                .add_submessages(callback)
+                .add_submessages(callbacks)
                 .add_event(event))
         }
     }
 
     fn ibc_packet_timeout(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         _env: Env,
         msg: IbcPacketTimeoutMsg,
-    ) -> Result<IbcBasicResponse, ContractError> {
-        self.handle_packet_fail(deps, msg.packet, "timeout")
+    ) -> Result<IbcBasicResponse<T>, ContractError> {
+        let data: NonFungibleTokenPacketData = from_json(&msg.packet.data)?;
+        let src_callback = src_callback(
+            deps.as_ref(),
+            &data,
+            Ics721Status::Failed("timeout".to_string()),
+            IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg {
+                packet: msg.packet.clone(),
+                relayer: msg.relayer.clone(),
+            }),
+        );
+        let response = self.handle_packet_fail(
+            deps.branch(),
+            msg.packet,
+            "timeout",
+            Some(Ics721ErrorCode::Timeout),
+            &msg.relayer,
+        )?;
+        Ok(response.add_submessages(ack_callback_submessages(deps.storage, src_callback)?))
     }
 
     /// Return the NFT locked in the ICS721 contract to sender; roll back.
@@ -198,7 +284,9 @@ where
         deps: DepsMut,
         packet: IbcPacket,
         error: &str,
-    ) -> Result<IbcBasicResponse, ContractError> {
+        error_code: Option<Ics721ErrorCode>,
+        relayer: &Addr,
+    ) -> Result<IbcBasicResponse<T>, ContractError> {
         let message: NonFungibleTokenPacketData = from_json(&packet.data)?;
         let nft_contract =
             load_nft_contract_for_class_id(deps.storage, message.class_id.to_string())?;
@@ -210,7 +298,7 @@ where
             .cloned()
             .map(|token_id| -> StdResult<_> {
                 OUTGOING_CLASS_TOKEN_TO_CHANNEL
-                    .remove(deps.storage, (message.class_id.clone(), token_id.clone()));
+                    .remove(deps.storage, (message.class_id.clone(), token_id.clone()))?;
                 Ok(WasmMsg::Execute {
                     contract_addr: nft_contract.to_string(),
                     msg: to_json_binary(&cw721_metadata_onchain::msg::ExecuteMsg::TransferNft {
@@ -222,15 +310,16 @@ where
             })
             .collect::<StdResult<Vec<_>>>()?;
 
-        let callback = match ack_callback_msg(
+        let callback = ack_callback(
             deps.as_ref(),
             Ics721Status::Failed(error.to_string()),
+            error_code,
+            &packet,
             message.clone(),
             nft_contract.to_string(),
-        ) {
-            Some(msg) => vec![msg],
-            None => vec![],
-        };
+            relayer,
+        );
+        let callback = ack_callback_submessages(deps.storage, callback)?;
 
         Ok(IbcBasicResponse::new()
             .add_messages(messages)
@@ -240,7 +329,11 @@ where
             .add_attribute("class_id", message.class_id)
             .add_attribute("channel_id", packet.src.channel_id)
             .add_attribute("address_refunded", message.sender)
-            .add_attribute("error", error))
+            .add_attribute("error", error)
+            .add_attribute(
+                "error_code",
+                error_code.map_or_else(|| "none".to_string(), |code| code.code().to_string()),
+            ))
     }
 
     fn reply(&self, deps: DepsMut, _env: Env, reply: Reply) -> Result<Response<T>, ContractError> {
@@ -289,18 +382,158 @@ where
             // reply - just need to commit an ack.
             ACK_AND_DO_NOTHING_REPLY_ID => {
                 match reply.result {
-                    // On success, set a successful ack. Nothing else to do.
-                    SubMsgResult::Ok(_) => Ok(Response::new().set_data(ack_success())),
+                    // On success, set a successful ack. NFTs have been received, so a best-effort receive callback
+                    // is dispatched now.
+                    SubMsgResult::Ok(_) => {
+                        let response = Response::new().set_data(ack_success());
+                        match PENDING_RECEIVE_CALLBACK.may_load(deps.storage)? {
+                            Some(callback) => {
+                                Ok(response.add_submessage(receive_callback_submessage(callback)))
+                            }
+                            None => Ok(response),
+                        }
+                    }
                     // On error we need to use set_data to override the data field
                     // from our caller, the IBC packet recv, and acknowledge our
                     // failure.  As per:
                     // https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#handling-the-reply
-                    SubMsgResult::Err(err) => Ok(Response::new().set_data(ack_fail(err))),
+                    // Submessage errors are opaque (e.g. redacted by wasmd), so
+                    // they can't be classified any further.
+                    SubMsgResult::Err(err) => {
+                        PENDING_RECEIVE_CALLBACK.remove(deps.storage);
+                        Ok(Response::new()
+                            .set_data(ack_fail_with_code(err, Ics721ErrorCode::ExecutionFailed)))
+                    }
                 }
             }
+            // failed ack callbacks, incl. out of gas in case of a gas limit, don't fail the ack.
+            // Instead they are stored, so they can be retried by `ExecuteMsg::RetryAckCallback`.
             ACK_CALLBACK_REPLY_ID => {
-                let err = reply.result.unwrap_err();
-                Ok(Response::new().add_attribute("error", err))
+                let mut pending = PENDING_ACK_CALLBACKS
+                    .may_load(deps.storage)?
+                    .unwrap_or_default();
+                let callback = (!pending.is_empty()).then(|| pending.remove(0));
+                PENDING_ACK_CALLBACKS.save(deps.storage, &pending)?;
+                match reply.result {
+                    SubMsgResult::Ok(_) => {
+                        Ok(Response::new().add_attribute("method", "ack_callback_reply"))
+                    }
+                    SubMsgResult::Err(err) => {
+                        let mut event = Event::new("ics721_ack_callback_failed");
+                        if let Some(callback) = callback {
+                            let id = NEXT_FAILED_ACK_CALLBACK_ID
+                                .may_load(deps.storage)?
+                                .unwrap_or_default();
+                            NEXT_FAILED_ACK_CALLBACK_ID.save(deps.storage, &(id + 1))?;
+                            FAILED_ACK_CALLBACKS.save(
+                                deps.storage,
+                                id,
+                                &FailedAckCallback {
+                                    callback,
+                                    error: err.clone(),
+                                },
+                            )?;
+                            event = event.add_attribute("id", id.to_string());
+                        }
+                        Ok(Response::new()
+                            .add_attribute("error", err.clone())
+                            .add_event(event.add_attribute("error", err)))
+                    }
+                }
+            }
+            // failed best-effort receive callbacks don't fail the transfer. Instead they are stored, so they can be
+            // queried by `QueryMsg::FailedReceiveCallbacks`.
+            RECEIVE_CALLBACK_REPLY_ID => {
+                let callback = PENDING_RECEIVE_CALLBACK.may_load(deps.storage)?;
+                PENDING_RECEIVE_CALLBACK.remove(deps.storage);
+                match reply.result {
+                    SubMsgResult::Ok(_) => {
+                        Ok(Response::new().add_attribute("method", "receive_callback_reply"))
+                    }
+                    SubMsgResult::Err(err) => {
+                        let mut event = Event::new("ics721_receive_callback_failed");
+                        if let Some(callback) = callback {
+                            let id = NEXT_FAILED_RECEIVE_CALLBACK_ID
+                                .may_load(deps.storage)?
+                                .unwrap_or_default();
+                            NEXT_FAILED_RECEIVE_CALLBACK_ID.save(deps.storage, &(id + 1))?;
+                            event = event
+                                .add_attribute("id", id.to_string())
+                                .add_attribute("contract", callback.contract.to_string());
+                            FAILED_RECEIVE_CALLBACKS.save(
+                                deps.storage,
+                                id,
+                                &FailedReceiveCallback {
+                                    callback,
+                                    error: err.clone(),
+                                },
+                            )?;
+                        }
+                        Ok(Response::new()
+                            .add_attribute("method", "receive_callback_reply")
+                            .add_event(event.add_attribute("error", err)))
+                    }
+                }
+            }
+            // updating collection info is best effort, so an error doesn't fail the transfer
+            UPDATE_COLLECTION_INFO_REPLY_ID => {
+                let mut pending = PENDING_COLLECTION_INFO_UPDATES
+                    .may_load(deps.storage)?
+                    .unwrap_or_default();
+                if pending.is_empty() {
+                    return Err(ContractError::UnrecognisedReplyId {});
+                }
+                let update = pending.remove(0);
+                PENDING_COLLECTION_INFO_UPDATES.save(deps.storage, &pending)?;
+                let (ty, error) = match reply.result {
+                    SubMsgResult::Ok(_) => ("ics721_update_collection_info", None),
+                    SubMsgResult::Err(err) => ("ics721_update_collection_info_failed", Some(err)),
+                };
+                let mut event = Event::new(ty)
+                    .add_attribute("class_id", update.class_id)
+                    .add_attribute("nft_contract", update.nft_contract)
+                    .add_attribute("changes", update.changes.join(","));
+                if let Some(err) = error {
+                    event = event.add_attribute("error", err);
+                }
+                Ok(Response::new()
+                    .add_attribute("method", "update_collection_info_reply")
+                    .add_event(event))
+            }
+            MIGRATE_VOUCHER_REPLY_ID => {
+                let mut pending = PENDING_VOUCHER_MIGRATIONS.load(deps.storage)?;
+                if pending.is_empty() {
+                    return Err(ContractError::UnrecognisedReplyId {});
+                }
+                let nft_contract = pending.remove(0);
+                PENDING_VOUCHER_MIGRATIONS.save(deps.storage, &pending)?;
+                let status = match reply.result {
+                    SubMsgResult::Ok(_) => VoucherMigrationStatus::Migrated,
+                    SubMsgResult::Err(err) => VoucherMigrationStatus::Failed(err),
+                };
+                let mut migration = VOUCHER_MIGRATIONS.load(deps.storage, nft_contract.clone())?;
+                migration.status = status.clone();
+                VOUCHER_MIGRATIONS.save(deps.storage, nft_contract.clone(), &migration)?;
+                if let VoucherMigrationStatus::Failed(_) = status {
+                    VOUCHER_MIGRATION_TARGET.update(
+                        deps.storage,
+                        |mut target| -> StdResult<_> {
+                            target.failed += 1;
+                            Ok(target)
+                        },
+                    )?;
+                }
+                let response = Response::new()
+                    .add_attribute("method", "migrate_voucher_reply")
+                    .add_attribute("nft_contract", nft_contract)
+                    .add_attribute("status", format!("{status:?}"));
+                // code id is switched on reply of the last migration, unless a migration failed
+                match complete_voucher_migration(deps.storage)? {
+                    Some(switched) => {
+                        Ok(response.add_attribute("code_id_switched", switched.to_string()))
+                    }
+                    None => Ok(response),
+                }
             }
             _ => Err(ContractError::UnrecognisedReplyId {}),
         }
@@ -1,6 +1,7 @@
 use cosmwasm_std::{
     from_json, to_json_binary, Binary, IbcAcknowledgement, IbcChannel, IbcEndpoint, IbcOrder,
 };
+use ics721_types::types::Ics721ErrorCode;
 use serde::{Deserialize, Serialize};
 
 use crate::{ibc::IBC_VERSION, ContractError};
@@ -32,25 +33,58 @@ pub(crate) fn get_endpoint_prefix(source: &IbcEndpoint) -> String {
 /// ICS20 which endodes its ACKs like this. This is compatible with
 /// the SDK ACK protobuf defined here:
 /// <https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147>
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Ics721Ack {
-    Result(Binary),
-    Error(String),
+///
+/// On top, error ACKs may hold a stable error `code` (see `Ics721ErrorCode`):
+///
+/// ```json
+/// {"error":"contract is paused pending governance intervention","code":1}
+/// ```
+#[derive(Serialize, Deserialize, Default)]
+pub struct Ics721Ack {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Binary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<u32>,
+}
+
+/// Error of an ACK as returned by `try_get_ack_error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ics721AckError {
+    pub error: String,
+    /// Error code, in case counterparty has provided one.
+    pub code: Option<Ics721ErrorCode>,
 }
 
 pub fn ack_success() -> Binary {
-    let res = Ics721Ack::Result(b"1".into());
+    let res = Ics721Ack {
+        result: Some(b"1".into()),
+        ..Default::default()
+    };
     to_json_binary(&res).unwrap()
 }
 
 pub fn ack_fail(err: String) -> Binary {
-    let res = Ics721Ack::Error(err);
+    let res = Ics721Ack {
+        error: Some(err),
+        ..Default::default()
+    };
+    to_json_binary(&res).unwrap()
+}
+
+pub fn ack_fail_with_code(err: String, code: Ics721ErrorCode) -> Binary {
+    let res = Ics721Ack {
+        error: Some(err),
+        code: Some(code.code()),
+        ..Default::default()
+    };
     to_json_binary(&res).unwrap()
 }
 
 /// Tries to get the error from an ACK. If an error exists, returns
-/// Some(error_message). Otherwise, returns `None`.
+/// Some(error). Otherwise, returns `None`. The error code is only
+/// provided by counterparties supporting error codes.
 ///
 /// NOTE(ekez): there is a special case here where the contents of the
 /// ACK we receive are set by the SDK, and not by our counterparty
@@ -70,13 +104,24 @@ pub fn ack_fail(err: String) -> Binary {
 /// ```json
 /// "eyJlcnJvciI6IkVtcHR5IGF0dHJpYnV0ZSB2YWx1ZS4gS2V5OiBjbGFzc19pZDogaW52YWxpZCBldmVudCJ9"
 /// ```
-pub fn try_get_ack_error(ack: &IbcAcknowledgement) -> Option<String> {
-    let ack: Ics721Ack =
-	// What we can not parse is an ACK fail.
-        from_json(&ack.data).unwrap_or_else(|_| Ics721Ack::Error(ack.data.to_base64()));
-    match ack {
-        Ics721Ack::Error(e) => Some(e),
-        _ => None,
+pub fn try_get_ack_error(ack: &IbcAcknowledgement) -> Option<Ics721AckError> {
+    match from_json::<Ics721Ack>(&ack.data) {
+        Ok(Ics721Ack {
+            error: Some(error),
+            code,
+            ..
+        }) => Some(Ics721AckError {
+            error,
+            code: code.map(Ics721ErrorCode::from_code),
+        }),
+        Ok(Ics721Ack {
+            result: Some(_), ..
+        }) => None,
+        // What we can not parse is an ACK fail.
+        _ => Some(Ics721AckError {
+            error: ack.data.to_base64(),
+            code: None,
+        }),
     }
 }
 
@@ -121,6 +166,63 @@ pub(crate) fn validate_order_and_version(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ack_json() {
+        assert_eq!(ack_success(), Binary::from(br#"{"result":"MQ=="}"#));
+        assert_eq!(
+            ack_fail("some failure".to_string()),
+            Binary::from(br#"{"error":"some failure"}"#)
+        );
+        assert_eq!(
+            ack_fail_with_code("paused".to_string(), Ics721ErrorCode::Paused),
+            Binary::from(br#"{"error":"paused","code":1}"#)
+        );
+    }
+
+    #[test]
+    fn test_try_get_ack_error() {
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(ack_success())),
+            None
+        );
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(ack_fail("legacy".to_string()))),
+            Some(Ics721AckError {
+                error: "legacy".to_string(),
+                code: None
+            })
+        );
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(ack_fail_with_code(
+                "paused".to_string(),
+                Ics721ErrorCode::Paused
+            ))),
+            Some(Ics721AckError {
+                error: "paused".to_string(),
+                code: Some(Ics721ErrorCode::Paused)
+            })
+        );
+        // unknown codes, e.g. from a newer counterparty
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(Binary::from(
+                br#"{"error":"future","code":4242}"#
+            ))),
+            Some(Ics721AckError {
+                error: "future".to_string(),
+                code: Some(Ics721ErrorCode::Unknown)
+            })
+        );
+        // not parseable
+        let ack = IbcAcknowledgement::new(Binary::from(b"foo"));
+        assert_eq!(
+            try_get_ack_error(&ack),
+            Some(Ics721AckError {
+                error: ack.data.to_base64(),
+                code: None
+            })
+        );
+    }
+
     #[test]
     fn test_pop_source_simple() {
         assert_eq!(
@@ -1,59 +1,233 @@
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Empty, Env, IbcPacket,
+    from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Env, IbcEndpoint, IbcPacket,
     IbcReceiveResponse, StdResult, SubMsg, WasmMsg,
 };
+use serde::{Deserialize, Serialize};
 use zip_optional::Zippable;
 
 use crate::{
-    helpers::{generate_receive_callback_msg, get_incoming_proxy_msg, get_receive_callback},
+    helpers::{
+        dest_callback_msg, generate_receive_callback_msg, get_incoming_proxy_msg,
+        get_receive_callback, ReceiveCallbackMsg,
+    },
     ibc::ACK_AND_DO_NOTHING_REPLY_ID,
     ibc_helpers::{get_endpoint_prefix, try_pop_source_prefix},
     msg::{CallbackMsg, ExecuteMsg},
     query::{
-        load_nft_contract_for_class_id, query_get_instantiate2_nft_contract,
-        query_nft_contract_for_class_id,
+        load_class_id_for_nft_contract, load_nft_contract_for_class_id,
+        query_get_instantiate2_nft_contract, query_nft_contract_for_class_id,
+    },
+    state::{
+        ChannelInfo, ClaimableVoucher, CHANNEL_INFOS, CLAIM_MODE, INCOMING_PROXY,
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PENDING_RECEIVE_CALLBACK, PO,
+        UNLINKED_NFT_CONTRACTS,
     },
-    state::{CW721_CODE_ID, OUTGOING_CLASS_TOKEN_TO_CHANNEL, PO},
     token_types::{VoucherCreation, VoucherRedemption},
+    translators::JsonValue,
+    utils::{derive_intermediate_sender, get_voucher_code_id},
     ContractError,
 };
 use ics721_types::{
     ibc_types::NonFungibleTokenPacketData,
     token_types::{Class, ClassId, Token, TokenId},
+    types::Ics721CallbackContext,
 };
 
-pub(crate) fn receive_ibc_packet(
+pub(crate) fn receive_ibc_packet<T>(
     deps: DepsMut,
     env: Env,
     packet: IbcPacket,
-) -> Result<IbcReceiveResponse, ContractError> {
+    relayer: Addr,
+    custom_msgs: Vec<SubMsg<T>>,
+) -> Result<IbcReceiveResponse<T>, ContractError>
+where
+    T: Serialize,
+{
     PO.error_if_paused(deps.storage)?;
     let data: NonFungibleTokenPacketData = from_json(&packet.data)?;
     data.validate()?;
 
-    // Check if NFT is local if not get the local class id
-    let maybe_local_class_id = try_pop_source_prefix(&packet.src, &data.class_id);
-    let callback = get_receive_callback(&data);
-    let local_class_id = if let Some(local_class_id) = maybe_local_class_id {
-        ClassId::new(local_class_id)
+    let ReceivePlan {
+        is_redemption,
+        nft_contract,
+        voucher_message,
+        channel_message,
+        callback_msg,
+        wasm_hook_msg,
+        in_custody,
+        ..
+    } = plan_receive(
+        deps.as_ref(),
+        &env,
+        &data,
+        &packet.src,
+        &packet.dest,
+        packet.sequence,
+        &relayer,
+    )?;
+    // channels connected before channel infos were stored, are registered on their first incoming packet
+    if !CHANNEL_INFOS.has(deps.storage, packet.dest.channel_id.clone()) {
+        CHANNEL_INFOS.save(
+            deps.storage,
+            packet.dest.channel_id.clone(),
+            &ChannelInfo {
+                endpoint: packet.dest.clone(),
+                counterparty_endpoint: packet.src.clone(),
+                connection_id: None,
+            },
+        )?;
+    }
+    // - one optional incoming proxy message
+    let incoming_proxy_msg =
+        get_incoming_proxy_msg(deps.as_ref().storage, packet.clone(), data.clone())?;
+    // - one optional required receive callback, while a best-effort receive callback is dispatched on reply, once
+    //   NFTs have been received
+    let (callback_msg, best_effort_callback) = match callback_msg {
+        Some(ReceiveCallbackMsg::Required(msg)) => (Some(msg), None),
+        Some(ReceiveCallbackMsg::BestEffort(callback)) => (None, Some(callback)),
+        None => (None, None),
+    };
+    // - one optional ADR-008 destination callback, skipped for vouchers held in custody
+    let dest_callback_msg = match in_custody {
+        true => None,
+        false => dest_callback_msg(deps.as_ref(), &env, &packet, &data)?,
+    };
+
+    // - one optional message dispatching custom messages (see `Ics721Ibc::receive_custom_msgs`)
+    let custom_msg = match custom_msgs.is_empty() {
+        true => None,
+        false => Some(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::CustomMsgs(
+                to_json_binary(&custom_msgs)?,
+            )))?,
+            funds: vec![],
+        }),
+    };
+
+    // informational only: the `wasm` memo contract is called by ICS721, regardless of sender and channel
+    let response = match wasm_hook_msg {
+        Some(_) => IbcReceiveResponse::default().add_attribute(
+            "wasm_hook_sender",
+            derive_intermediate_sender(&env, &packet.dest.channel_id, &data.sender)?,
+        ),
+        None => IbcReceiveResponse::default(),
+    };
+    let submessage = into_submessage(
+        env.contract.address,
+        voucher_message,
+        channel_message,
+        callback_msg,
+        incoming_proxy_msg,
+        wasm_hook_msg,
+        dest_callback_msg,
+        custom_msg,
+    )?;
+
+    let response = if let Some(memo) = data.memo {
+        response.add_attribute("ics721_memo", memo)
     } else {
-        let local_prefix = get_endpoint_prefix(&packet.dest);
-        ClassId::new(format!("{}{}", local_prefix, data.class_id))
+        response
     };
 
+    match best_effort_callback {
+        Some(callback) => PENDING_RECEIVE_CALLBACK.save(deps.storage, &callback)?,
+        None => PENDING_RECEIVE_CALLBACK.remove(deps.storage),
+    }
+
+    Ok(response
+        .add_submessage(submessage)
+        .add_attribute("method", "receive_ibc_packet")
+        .add_attribute("nft_contract", nft_contract.to_string())
+        .add_attribute("is_redemption", is_redemption.to_string())
+        .add_attribute("in_custody", in_custody.to_string())
+        .add_attribute("class_id", data.class_id)
+        .add_attribute("local_channel", packet.dest.channel_id)
+        .add_attribute("counterparty_channel", packet.src.channel_id))
+}
+
+/// Everything ICS721 is about to do for an incoming packet. It is computed without writing any state,
+/// so it is used for both, receiving a packet and simulating it (see `QueryMsg::SimulateReceive`).
+pub(crate) struct ReceivePlan {
+    pub local_class_id: ClassId,
+    pub is_redemption: bool,
+    /// Existing nft contract, or in case of creation the predicted (instantiate2) nft contract.
+    pub nft_contract: Addr,
+    pub voucher_message: WasmMsg,
+    pub channel_message: WasmMsg,
+    /// Receive callback, None for vouchers held in custody.
+    pub callback_msg: Option<ReceiveCallbackMsg>,
+    /// `wasm` memo message, None for vouchers held in custody.
+    pub wasm_hook_msg: Option<WasmMsg>,
+    /// Whether vouchers are minted into ICS721 custody (claim mode), instead of to the receiver. In this case
+    /// receive and destination callbacks, and the `wasm` memo are skipped.
+    pub in_custody: bool,
+}
+
+/// ibc-hooks like memo in plain JSON, e.g. `{"wasm":{"contract":"cosmos1...","msg":{"do_something":{}}}}`.
+#[derive(Deserialize)]
+struct WasmHookMemo {
+    wasm: WasmHook,
+}
+
+#[derive(Deserialize)]
+struct WasmHook {
+    contract: String,
+    msg: JsonValue,
+}
+
+/// Top-level keys of `ReceiverExecuteMsg`. Contracts trust these messages to be sent by ICS721, so they can't be
+/// forged by a `wasm` memo.
+const RESERVED_WASM_HOOK_MSGS: &[&str] = &[
+    "ics721_receive_callback",
+    "ics721_ack_callback",
+    "ics721_receive_callback_v2",
+    "ics721_ack_callback_v2",
+    "ibc_source_callback",
+    "ibc_destination_callback",
+    "ics721_receive_packet_msg",
+];
+
+/// Gets the local class id for an incoming class id: either the source prefix is removed (NFT is
+/// returning home) or the local endpoint prefix is added.
+pub(crate) fn get_local_class_id(
+    src: &IbcEndpoint,
+    dest: &IbcEndpoint,
+    class_id: &str,
+) -> (ClassId, bool) {
+    match try_pop_source_prefix(src, class_id) {
+        Some(local_class_id) => (ClassId::new(local_class_id), true),
+        None => {
+            let local_prefix = get_endpoint_prefix(dest);
+            (ClassId::new(format!("{}{}", local_prefix, class_id)), false)
+        }
+    }
+}
+
+/// Creates a plan for an already validated packet. Sequence and relayer are only passed to the receive callback.
+pub(crate) fn plan_receive(
+    deps: Deps,
+    env: &Env,
+    data: &NonFungibleTokenPacketData,
+    src: &IbcEndpoint,
+    dest: &IbcEndpoint,
+    sequence: u64,
+    relayer: &Addr,
+) -> Result<ReceivePlan, ContractError> {
+    // Check if NFT is local if not get the local class id
+    let (local_class_id, is_source_prefixed) = get_local_class_id(src, dest, &data.class_id);
+    let callback = get_receive_callback(data);
+
     // sub message holds 2 to 4 messages:
     // - one message for voucher creation or redemption, another message for updating incoming or outgoing channel
-    let (is_redemption, voucher_and_channel_messages) = create_voucher_and_channel_messages(
-        deps.as_ref(),
+    let (is_redemption, (voucher_message, channel_message)) = create_voucher_and_channel_messages(
+        deps,
         env.clone(),
         data.clone(),
-        maybe_local_class_id,
+        is_source_prefixed,
         local_class_id.clone(),
-        packet.clone(),
+        dest,
     )?;
-    // - one optional incoming proxy message
-    let incoming_proxy_msg =
-        get_incoming_proxy_msg(deps.as_ref().storage, packet.clone(), data.clone())?;
     // - one optional callback message
     // callback require the nft contract, get it using the class id from the action
     let nft_contract = if is_redemption {
@@ -71,10 +245,11 @@ pub(crate) fn receive_ibc_packet(
             Some(nft_contract) => Ok(nft_contract),
             None => {
                 // contract not yet instantiated, so we use instantiate2 to get the contract address
-                let cw721_code_id = CW721_CODE_ID.load(deps.storage)?;
+                let cw721_code_id =
+                    get_voucher_code_id(deps.storage, &local_class_id, data.class_data.as_ref())?;
                 query_get_instantiate2_nft_contract(
-                    deps.as_ref(),
-                    &env,
+                    deps,
+                    env,
                     local_class_id.clone(),
                     Some(cw721_code_id),
                 )
@@ -82,40 +257,114 @@ pub(crate) fn receive_ibc_packet(
         }
     }?;
 
-    let callback_msg =
-        create_callback_msg(deps.as_ref(), &data, nft_contract.to_string(), callback)?;
+    let in_custody = !is_redemption
+        && CLAIM_MODE
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .expires_at(env.block.time)
+            .is_some();
 
-    let submessage = into_submessage(
-        env.contract.address,
-        voucher_and_channel_messages.0,
-        voucher_and_channel_messages.1,
-        callback_msg,
-        incoming_proxy_msg,
-    )?;
+    let context = Ics721CallbackContext {
+        local_endpoint: dest.clone(),
+        counterparty_endpoint: src.clone(),
+        local_class_id: local_class_id.clone(),
+        sequence,
+        relayer: relayer.to_string(),
+    };
+    // receiver doesn't own vouchers held in custody, so there is nothing to call back
+    let callback_msg = match in_custody {
+        true => None,
+        false => create_callback_msg(
+            deps,
+            env,
+            data,
+            nft_contract.to_string(),
+            callback,
+            context,
+            is_redemption,
+        )?,
+    };
 
-    let response = if let Some(memo) = data.memo {
-        IbcReceiveResponse::default().add_attribute("ics721_memo", memo)
-    } else {
-        IbcReceiveResponse::default()
+    let wasm_hook_msg = match in_custody {
+        true => None,
+        false => create_wasm_hook_msg(deps, env, data, &nft_contract)?,
     };
 
-    Ok(response
-        .add_submessage(submessage)
-        .add_attribute("method", "receive_ibc_packet")
-        .add_attribute("nft_contract", nft_contract.to_string())
-        .add_attribute("is_redemption", is_redemption.to_string())
-        .add_attribute("class_id", data.class_id)
-        .add_attribute("local_channel", packet.dest.channel_id)
-        .add_attribute("counterparty_channel", packet.src.channel_id))
+    Ok(ReceivePlan {
+        local_class_id,
+        is_redemption,
+        nft_contract,
+        voucher_message,
+        channel_message,
+        callback_msg,
+        wasm_hook_msg,
+        in_custody,
+    })
+}
+
+/// Creates the message of a `wasm` memo. As in ibc-hooks the contract must be the receiver, so it already owns
+/// the NFTs on execution. Since the message is executed by ICS721, contracts trusting ICS721 can't be called, and
+/// messages sent by ICS721 (like callbacks) can't be forged.
+fn create_wasm_hook_msg(
+    deps: Deps,
+    env: &Env,
+    data: &NonFungibleTokenPacketData,
+    nft_contract: &Addr,
+) -> Result<Option<WasmMsg>, ContractError> {
+    let Some(WasmHookMemo { wasm }) = data
+        .memo
+        .as_ref()
+        .and_then(|memo| from_json::<WasmHookMemo>(memo.as_bytes()).ok())
+    else {
+        return Ok(None);
+    };
+    if wasm.contract != data.receiver {
+        return Err(ContractError::InvalidWasmHook(format!(
+            "contract {} is not the receiver",
+            wasm.contract
+        )));
+    }
+    let contract = deps.api.addr_validate(&wasm.contract)?;
+    let is_trusting_ics721 = contract == env.contract.address
+        || contract == *nft_contract
+        || load_class_id_for_nft_contract(deps.storage, &contract)?.is_some()
+        || UNLINKED_NFT_CONTRACTS.has(deps.storage, &contract)
+        || INCOMING_PROXY.may_load(deps.storage)?.flatten().as_ref() == Some(&contract)
+        || OUTGOING_PROXY.may_load(deps.storage)?.flatten().as_ref() == Some(&contract);
+    if is_trusting_ics721 {
+        return Err(ContractError::InvalidWasmHook(format!(
+            "contract {} can't be called",
+            contract
+        )));
+    }
+    let JsonValue::Object(msg) = &wasm.msg else {
+        return Err(ContractError::InvalidWasmHook(
+            "msg is not a JSON object".to_string(),
+        ));
+    };
+    if let Some(key) = msg
+        .keys()
+        .find(|key| RESERVED_WASM_HOOK_MSGS.contains(&key.as_str()))
+    {
+        return Err(ContractError::InvalidWasmHook(format!(
+            "msg {} is reserved",
+            key
+        )));
+    }
+    Ok(Some(WasmMsg::Execute {
+        contract_addr: contract.into_string(),
+        msg: to_json_binary(&wasm.msg)?,
+        funds: vec![],
+    }))
 }
 
 fn create_voucher_and_channel_messages(
     deps: Deps,
     env: Env,
     data: NonFungibleTokenPacketData,
-    maybe_local_class_id: Option<&str>,
+    is_source_prefixed: bool,
     local_class_id: ClassId,
-    packet: IbcPacket,
+    dest: &IbcEndpoint,
 ) -> Result<(bool, (WasmMsg, WasmMsg)), ContractError> {
     let token_count = data.token_ids.len();
     let redemption_or_create = data
@@ -130,14 +379,14 @@ fn create_voucher_and_channel_messages(
             ),
             |mut redemption_or_create, ((token_id, token_uri), token_data)| -> StdResult<_> {
                 // If class is not local, its something new
-                if maybe_local_class_id.is_some() {
+                if is_source_prefixed {
                     let key: (ClassId, TokenId) = (local_class_id.clone(), token_id.clone());
                     let outgoing_channel =
                         OUTGOING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?;
 
                     // Make sure the channel that used for outgoing transfer, is the same you use to transfer back
                     let returning_to_source = outgoing_channel.map_or(false, |outgoing_channel| {
-                        outgoing_channel == packet.dest.channel_id
+                        outgoing_channel == dest.channel_id
                     });
 
                     if returning_to_source {
@@ -214,12 +463,7 @@ fn create_voucher_and_channel_messages(
                 .tokens
                 .clone()
                 .into_iter()
-                .map(|token| {
-                    (
-                        (local_class_id.clone(), token.id),
-                        packet.dest.channel_id.clone(),
-                    )
-                })
+                .map(|token| ((local_class_id.clone(), token.id), dest.channel_id.clone()))
                 .collect();
             let add_incoming_channels_msg = WasmMsg::Execute {
                 contract_addr: env.contract.address.to_string(),
@@ -228,10 +472,55 @@ fn create_voucher_and_channel_messages(
                 ))?,
                 funds: vec![],
             };
-            (
-                creation.into_wasm_msg(env.contract.address.clone(), receiver.to_string())?,
-                add_incoming_channels_msg,
-            )
+            match CLAIM_MODE
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                .expires_at(env.block.time)
+            {
+                // vouchers are minted into custody of ICS721 and claimable by receiver
+                Some(expires_at) => {
+                    let add_claimable_vouchers: Vec<((ClassId, TokenId), ClaimableVoucher)> =
+                        creation
+                            .tokens
+                            .iter()
+                            .map(|token| {
+                                (
+                                    (local_class_id.clone(), token.id.clone()),
+                                    ClaimableVoucher {
+                                        receiver: receiver.clone(),
+                                        sender: data.sender.clone(),
+                                        expires_at,
+                                    },
+                                )
+                            })
+                            .collect();
+                    let add_claimable_vouchers_msg = WasmMsg::Execute {
+                        contract_addr: env.contract.address.to_string(),
+                        msg: to_json_binary(&ExecuteMsg::Callback(
+                            CallbackMsg::AddClaimableVouchers(add_claimable_vouchers),
+                        ))?,
+                        funds: vec![],
+                    };
+                    let channel_and_claimable_msg = WasmMsg::Execute {
+                        contract_addr: env.contract.address.to_string(),
+                        msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::Conjunction {
+                            operands: vec![add_incoming_channels_msg, add_claimable_vouchers_msg],
+                        }))?,
+                        funds: vec![],
+                    };
+                    (
+                        creation.into_wasm_msg(
+                            env.contract.address.clone(),
+                            env.contract.address.to_string(),
+                        )?,
+                        channel_and_claimable_msg,
+                    )
+                }
+                None => (
+                    creation.into_wasm_msg(env.contract.address.clone(), receiver.to_string())?,
+                    add_incoming_channels_msg,
+                ),
+            }
         }
     };
 
@@ -240,31 +529,41 @@ fn create_voucher_and_channel_messages(
 
 fn create_callback_msg(
     deps: Deps,
+    env: &Env,
     data: &NonFungibleTokenPacketData,
     nft_contract: String,
     callback: Option<(Binary, Option<String>)>,
-) -> Result<Option<WasmMsg>, ContractError> {
+    context: Ics721CallbackContext,
+    is_redemption: bool,
+) -> Result<Option<ReceiveCallbackMsg>, ContractError> {
     if let Some((receive_callback_data, receive_callback_addr)) = callback {
         Ok(generate_receive_callback_msg(
             deps,
+            env,
             data,
             receive_callback_data,
             receive_callback_addr,
             nft_contract.to_string(),
+            context,
+            is_redemption,
         ))
     } else {
         Ok(None)
     }
 }
 
-pub fn into_submessage(
+#[allow(clippy::too_many_arguments)]
+pub fn into_submessage<T>(
     contract: Addr,
     voucher_message: WasmMsg,
     channel_message: WasmMsg,
     callback_msg: Option<WasmMsg>,
     incoming_proxy_msg: Option<WasmMsg>,
-) -> StdResult<SubMsg<Empty>> {
-    let mut operands = Vec::with_capacity(4); // 4 is the max number of submessages we can have
+    wasm_hook_msg: Option<WasmMsg>,
+    dest_callback_msg: Option<WasmMsg>,
+    custom_msg: Option<WasmMsg>,
+) -> StdResult<SubMsg<T>> {
+    let mut operands = Vec::with_capacity(7); // 7 is the max number of submessages we can have
     if let Some(incoming_proxy_msg) = incoming_proxy_msg {
         operands.push(incoming_proxy_msg)
     }
@@ -278,6 +577,20 @@ pub fn into_submessage(
     // once all other submessages are done, we can update incoming or outgoing channel
     operands.push(channel_message);
 
+    // NFTs are fully received, before `wasm` memo is executed
+    if let Some(wasm_hook_msg) = wasm_hook_msg {
+        operands.push(wasm_hook_msg)
+    }
+
+    if let Some(dest_callback_msg) = dest_callback_msg {
+        operands.push(dest_callback_msg)
+    }
+
+    // custom messages are part of the receive, so in case they fail an error ack is sent
+    if let Some(custom_msg) = custom_msg {
+        operands.push(custom_msg)
+    }
+
     let message = WasmMsg::Execute {
         contract_addr: contract.into_string(),
         msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }))?,
@@ -0,0 +1,258 @@
+//! Interoperability with Cosmos SDK modules, like ibc-go's `nft-transfer` and x/nft.
+//! These modules don't use ICS721's `CollectionData` and `NftExtension`, but encode class and token data
+//! either as key/value data (e.g. `{"irismod:name": {"value": "..."}}`) or as protobuf `Any` in JSON encoding.
+
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{from_json, to_json_binary, Binary, StdResult};
+use cw721::{CollectionExtension, NftExtension};
+use ics721_types::token_types::Class;
+use serde::Serialize;
+
+use crate::{
+    state::CollectionData,
+    translators::{parse_any_json, parse_irismod, JsonValue, IRISMOD_PREFIX},
+};
+
+/// Ports of SDK modules transferring NFTs. Any other port is considered an ICS721 contract, since wasm port prefixes
+/// differ per chain (e.g. `wasm.` or `stars.`).
+pub const SDK_MODULE_PORTS: &[&str] = &["nft-transfer"];
+
+pub fn is_sdk_module_port(port_id: &str) -> bool {
+    SDK_MODULE_PORTS.contains(&port_id)
+}
+
+/// Returns collection data of a class. Class data is either `CollectionData` sent by ICS721
+/// or data of a SDK module, in this case name, symbol, creator and description are converted.
+pub fn collection_data_from_class(class: &Class) -> Option<CollectionData> {
+    let data = class.data.as_ref()?;
+    if let Ok(collection_data) = from_json::<CollectionData>(data) {
+        return Some(collection_data);
+    }
+    let mut object = parse_irismod(data).or_else(|| parse_any_json(data))?;
+    // collection data sent by ICS721 to a SDK module and sent back again
+    if let Some(JsonValue::String(data)) = object.get("data") {
+        if let Ok(collection_data) = from_json::<CollectionData>(data.as_bytes()) {
+            return Some(collection_data);
+        }
+    }
+    let mut take = |key: &str| object.remove(key).and_then(JsonValue::into_string);
+    let name = take("name")?;
+    let symbol = take("symbol").unwrap_or_else(|| name.clone());
+    let owner = take("creator");
+    let description = take("description");
+    // class uri is used as image, since collection info requires one
+    let extension = class.uri.clone().map(|uri| CollectionExtension {
+        description: description.unwrap_or_default(),
+        image: uri.clone(),
+        external_link: Some(uri),
+        explicit_content: None,
+        start_trading_time: None,
+        royalty_info: None,
+    });
+    Some(CollectionData {
+        owner,
+        contract_info: None,
+        num_tokens: None,
+        name,
+        symbol,
+        extension,
+        destination_profile: None,
+    })
+}
+
+#[derive(Serialize)]
+struct IrismodValue {
+    value: String,
+}
+
+fn to_irismod_binary(values: Vec<(&str, Option<String>)>) -> StdResult<Binary> {
+    let object: BTreeMap<String, IrismodValue> = values
+        .into_iter()
+        .filter_map(|(key, value)| {
+            value.map(|value| (format!("{IRISMOD_PREFIX}{key}"), IrismodValue { value }))
+        })
+        .collect();
+    to_json_binary(&object)
+}
+
+/// Converts class data into key/value data for SDK modules. Original data is kept in `irismod:data`,
+/// so it can be restored on the way back. Returns None, in case class data is not `CollectionData`.
+pub fn encode_class_data_for_sdk_module(data: &Binary) -> StdResult<Option<Binary>> {
+    let Ok(collection_data) = from_json::<CollectionData>(data) else {
+        return Ok(None);
+    };
+    let description = collection_data.extension.map(|ext| ext.description);
+    to_irismod_binary(vec![
+        ("name", Some(collection_data.name)),
+        ("symbol", Some(collection_data.symbol)),
+        ("description", description),
+        ("creator", collection_data.owner),
+        (
+            "data",
+            Some(String::from_utf8_lossy(data.as_slice()).to_string()),
+        ),
+    ])
+    .map(Some)
+}
+
+/// Converts token data into key/value data for SDK modules. Original data is kept in `irismod:token_data`,
+/// so it can be restored on the way back. Returns None, in case token data is not `NftExtension`.
+pub fn encode_token_data_for_sdk_module(data: &Binary) -> StdResult<Option<Binary>> {
+    let Ok(extension) = from_json::<NftExtension>(data) else {
+        return Ok(None);
+    };
+    to_irismod_binary(vec![
+        ("token_name", extension.name),
+        (
+            "token_data",
+            Some(String::from_utf8_lossy(data.as_slice()).to_string()),
+        ),
+    ])
+    .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Addr;
+    use ics721_types::token_types::ClassId;
+
+    use crate::translators::MetadataTranslator;
+
+    use super::*;
+
+    fn class(data: &str, uri: Option<&str>) -> Class {
+        Class {
+            id: ClassId::new("nft-transfer/channel-0/class"),
+            uri: uri.map(|uri| uri.to_string()),
+            data: Some(Binary::from(data.as_bytes())),
+        }
+    }
+
+    #[test]
+    fn test_is_sdk_module_port() {
+        assert!(is_sdk_module_port("nft-transfer"));
+        assert!(!is_sdk_module_port("wasm.stars1contract"));
+        // wasm ports with a custom prefix
+        assert!(!is_sdk_module_port("stars.stars1contract"));
+        assert!(!is_sdk_module_port("juno.juno1contract"));
+    }
+
+    #[test]
+    fn test_collection_data_from_irismod_class() {
+        let class = class(
+            r#"{"irismod:name":{"value":"name"},"irismod:symbol":{"value":"symbol"},"irismod:creator":{"value":"iaa1creator"},"irismod:description":{"value":"description"},"irismod:mint_restricted":{"value":true}}"#,
+            Some("ipfs://class"),
+        );
+        assert_eq!(
+            collection_data_from_class(&class),
+            Some(CollectionData {
+                owner: Some("iaa1creator".to_string()),
+                contract_info: None,
+                num_tokens: None,
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                extension: Some(CollectionExtension {
+                    description: "description".to_string(),
+                    image: "ipfs://class".to_string(),
+                    external_link: Some("ipfs://class".to_string()),
+                    explicit_content: None,
+                    start_trading_time: None,
+                    royalty_info: None,
+                }),
+                destination_profile: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_collection_data_from_any_json_class() {
+        let class = class(r#"{"@type":"/game.v1.Collection","name":"name"}"#, None);
+        assert_eq!(
+            collection_data_from_class(&class),
+            Some(CollectionData {
+                owner: None,
+                contract_info: None,
+                num_tokens: None,
+                name: "name".to_string(),
+                symbol: "name".to_string(),
+                extension: None,
+                destination_profile: None,
+            })
+        );
+
+        // without name, class ID is used by caller
+        let class = self::class(r#"{"@type":"/game.v1.Collection"}"#, None);
+        assert_eq!(collection_data_from_class(&class), None);
+    }
+
+    #[test]
+    fn test_class_data_round_trip() {
+        let collection_data = CollectionData {
+            owner: Some("owner".to_string()),
+            contract_info: None,
+            num_tokens: Some(1),
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            extension: Some(CollectionExtension {
+                description: "description".to_string(),
+                image: "https://ark.pass/image.png".to_string(),
+                external_link: None,
+                explicit_content: None,
+                start_trading_time: None,
+                royalty_info: Some(cw721::RoyaltyInfo {
+                    payment_address: Addr::unchecked("payment_address"),
+                    share: cosmwasm_std::Decimal::percent(5),
+                }),
+            }),
+            destination_profile: None,
+        };
+        let data = to_json_binary(&collection_data).unwrap();
+        let encoded = encode_class_data_for_sdk_module(&data).unwrap().unwrap();
+        let object = parse_irismod(&encoded).unwrap();
+        assert_eq!(
+            object.get("name"),
+            Some(&JsonValue::String("name".to_string()))
+        );
+        assert_eq!(
+            object.get("symbol"),
+            Some(&JsonValue::String("symbol".to_string()))
+        );
+        // original collection data is restored
+        let class = Class {
+            id: ClassId::new("wasm.address/channel-1/nft-transfer/channel-0/class"),
+            uri: None,
+            data: Some(encoded),
+        };
+        assert_eq!(collection_data_from_class(&class), Some(collection_data));
+
+        // unknown class data is not converted
+        let data = Binary::from(r#"{"irismod:name":{"value":"name"}}"#.as_bytes());
+        assert_eq!(encode_class_data_for_sdk_module(&data).unwrap(), None);
+    }
+
+    #[test]
+    fn test_token_data_round_trip() {
+        let extension = NftExtension {
+            name: Some("name".to_string()),
+            image: Some("https://ark.pass/image.png".to_string()),
+            attributes: Some(vec![cw721::Trait {
+                display_type: None,
+                trait_type: "color".to_string(),
+                value: "red".to_string(),
+            }]),
+            ..Default::default()
+        };
+        let data = to_json_binary(&extension).unwrap();
+        let encoded = encode_token_data_for_sdk_module(&data).unwrap().unwrap();
+        assert_eq!(
+            parse_irismod(&encoded).unwrap().get("token_name"),
+            Some(&JsonValue::String("name".to_string()))
+        );
+        // original extension is restored
+        assert_eq!(
+            MetadataTranslator::Irismod.translate(&encoded),
+            MetadataTranslator::Cw721.translate(&data)
+        );
+    }
+}
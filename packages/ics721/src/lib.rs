@@ -4,10 +4,12 @@ pub mod helpers;
 pub mod ibc;
 pub mod ibc_helpers;
 pub mod ibc_packet_receive;
+pub mod interop;
 pub mod msg;
 pub mod query;
 pub mod state;
 pub mod token_types;
+pub mod translators;
 pub mod utils;
 pub use crate::error::ContractError;
 pub use ics721_types::{
@@ -1,10 +1,22 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, WasmMsg};
-use cw721::receiver::Cw721ReceiveMsg;
+use cosmwasm_std::{Addr, Binary, IbcEndpoint, IbcTimeout, WasmMsg};
+use cw721::{receiver::Cw721ReceiveMsg, NftExtension};
 use cw_cii::ContractInstantiateInfo;
 
-use crate::token_types::{VoucherCreation, VoucherRedemption};
-use ics721_types::token_types::{Class, ClassId, ClassToken, Token, TokenId};
+use crate::{
+    state::{
+        CallbackGasLimits, ChannelInfo, ClaimMode, ClaimableVoucher, ClassDataRefreshPolicy,
+        CollectionData, DestinationProfile, FailedAckCallback, FailedReceiveCallback,
+        RoyaltyPayeeClaim, TokenMetadataPolicy, VoucherCodeIdRule, VoucherMigration,
+    },
+    token_types::{VoucherCreation, VoucherRedemption},
+    translators::MetadataTranslator,
+};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
+    types::Ics721ErrorCode,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -34,6 +46,20 @@ pub struct InstantiateMsg {
     pub cw721_admin: Option<String>,
     /// The optional contract address length being used for instantiate2. In case of None, default length is 32 (standard in cosmwasm).
     pub contract_addr_length: Option<u32>,
+    /// Whether class data of home collections is refreshed on send. In case of None, class data is never refreshed on send.
+    pub class_data_refresh_policy: Option<ClassDataRefreshPolicy>,
+    /// Whether collection info of existing voucher contracts is updated, in case incoming collection data has changed.
+    /// NOTE: this requires ICS721 to be allowed updating collection info (e.g. being creator of voucher contracts).
+    /// In case of None, collection info is not updated.
+    pub update_voucher_collection_info: Option<bool>,
+    /// Whether received or current token metadata of vouchers is sent on next hop. In case of None, received metadata is sent.
+    pub token_metadata_policy: Option<TokenMetadataPolicy>,
+    /// Rules for selecting code ID and instantiate message of voucher contracts. In case of None, `cw721_base_code_id` is used for all vouchers.
+    pub voucher_code_id_rules: Option<Vec<VoucherCodeIdRule>>,
+    /// Whether incoming vouchers are minted into ICS721 custody, claimable by the receiver. In case of None, vouchers are minted to the receiver.
+    pub claim_mode: Option<ClaimMode>,
+    /// Default and max gas limits of callbacks. In case of None, callback gas is unlimited.
+    pub callback_gas_limits: Option<CallbackGasLimits>,
 }
 
 #[cw_serde]
@@ -68,6 +94,108 @@ pub enum ExecuteMsg {
         class_id: String,
         collection: String,
     },
+
+    /// Refreshes class data of a home collection, so it is sent to other chains on next transfer.
+    /// Only callable by the collection creator (minter for cw721 v0.18 and lower) or admin.
+    RefreshClassData { collection: String },
+
+    /// Sets the translator for token data of incoming vouchers of a class. In case of None, the format is detected.
+    /// Only callable by admin.
+    SetMetadataTranslator {
+        class_id: String,
+        translator: Option<MetadataTranslator>,
+    },
+
+    /// Sets how vouchers of a home collection are presented on the target chain of a channel.
+    /// The profile is sent as part of the class data. In case of None, the profile is removed.
+    /// Only callable by the collection creator (minter for cw721 v0.18 and lower).
+    SetDestinationProfile {
+        collection: String,
+        channel_id: String,
+        profile: Option<DestinationProfile>,
+    },
+
+    /// Registers a local royalty payment address for vouchers of a class. In case sender is the same account as the
+    /// collection owner on source chain (e.g. `juno1XXX` and `stars1XXX`), payee is registered directly and the voucher
+    /// contract is updated. Otherwise a claim is stored, which needs to be approved by admin.
+    RegisterRoyaltyPayee { class_id: String, payee: String },
+
+    /// Approves a pending royalty payee claim. Claimer and payee must match the pending claim of the claimer, so a
+    /// claim changed in the meantime isn't approved. Only callable by admin.
+    ApproveRoyaltyPayee {
+        class_id: String,
+        claimer: String,
+        payee: String,
+    },
+
+    /// Transfers creator ownership of a voucher collection to sender. Requires a secp256k1 signature (ADR-036,
+    /// e.g. Keplr's `signArbitrary`) by the collection owner on source chain, signing the message created by
+    /// `utils::collection_ownership_claim_message`. The public key must derive the owner's address.
+    /// Sender needs to accept ownership on the voucher contract afterwards.
+    ClaimCollectionOwnership {
+        class_id: String,
+        pubkey: Binary,
+        signature: Binary,
+    },
+
+    /// Binds an incoming class to an existing local nft contract, so NFTs of that class are minted there instead of
+    /// a newly instantiated voucher contract. ICS721 must be minter of the nft contract. Only callable by admin.
+    RegisterClassMapping {
+        class_id: String,
+        nft_contract: String,
+    },
+
+    /// Removes the mapping of a voucher class to its nft contract, e.g. in case instantiation failed. Next transfer of
+    /// the class instantiates a new voucher contract, using a new salt. Fails in case vouchers of the class are
+    /// outstanding. Only callable by admin.
+    RemoveClassMapping { class_id: String },
+
+    /// Points a voucher class to a replacement nft contract, which must hold all outstanding vouchers of the class
+    /// and have ICS721 as minter. Only callable by admin.
+    RepointClassMapping {
+        class_id: String,
+        nft_contract: String,
+    },
+
+    /// Instantiates a fresh voucher contract for a class, using a new salt for instantiate2.
+    /// Fails in case vouchers of the class are outstanding. Only callable by admin.
+    ReinstantiateVoucherContract { class_id: String, salt: Binary },
+
+    /// Migrates a page of voucher contracts to a new code ID. Later pages must have the same code ID and msg as the
+    /// first page. Once all pages are migrated without failures, new code ID is used for new voucher contracts,
+    /// otherwise failed contracts can be migrated by starting over. Only contracts with ICS721 as admin are migrated,
+    /// results are stored per contract. Only callable by admin.
+    MigrateVoucherContracts {
+        new_code_id: u64,
+        msg: Binary,
+        /// Class ID to start after, as returned in `next_start_after` attribute of previous page. The attribute is
+        /// omitted on the last page.
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Indexes a page of channel entries, stored before the channel maps had a channel index. Pending after migrating
+    /// from such a version, until all entries are indexed. Meanwhile `OutgoingChannelsByChannel` and
+    /// `IncomingChannelsByChannel` fail, so this needs to be called until `pending` is false. Only callable by admin.
+    MigrateChannelIndexes { limit: Option<u32> },
+
+    /// Transfers vouchers held in ICS721 custody to the receiver. Only callable by the receiver.
+    Claim {
+        class_id: String,
+        token_ids: Vec<String>,
+    },
+
+    /// Returns expired, unclaimed vouchers to the sender on source chain, via the channel they have been received on.
+    /// Only callable by admin or the sender (same account as on source chain, e.g. `juno1XXX` and `stars1XXX`).
+    /// Not available in case an outgoing proxy is set, since returned vouchers would bypass it.
+    ReturnUnclaimedVouchers {
+        class_id: String,
+        token_ids: Vec<String>,
+        timeout: IbcTimeout,
+    },
+
+    /// Re-dispatches a failed ack callback. Only callable by the called back contract or admin.
+    RetryAckCallback { id: u64 },
 }
 
 #[cw_serde]
@@ -89,6 +217,8 @@ pub enum CallbackMsg {
     RedeemOutgoingChannelEntries(Vec<(ClassId, TokenId)>),
     /// Save all entries in incoming channel.
     AddIncomingChannelEntries(Vec<((ClassId, TokenId), String)>),
+    /// Save vouchers minted into ICS721 custody, claimable by the receiver.
+    AddClaimableVouchers(Vec<((ClassId, TokenId), ClaimableVoucher)>),
     /// Mints a NFT of collection class_id for receiver with the
     /// provided id and metadata. Only callable by this contract.
     Mint {
@@ -109,6 +239,14 @@ pub enum CallbackMsg {
     /// to respond with a single ACK when a message calls for the
     /// execution of both `CreateVouchers` and `RedeemVouchers`.
     Conjunction { operands: Vec<WasmMsg> },
+    /// Executes `msg` as a submessage with the given gas limit. An
+    /// out of gas error is not caught, so as an operand of
+    /// `Conjunction` it fails the whole conjunction.
+    ExecuteWithGasLimit { msg: WasmMsg, gas_limit: u64 },
+    /// Dispatches custom messages (`Vec<SubMsg<T>>` as JSON) of
+    /// `Ics721Ibc::receive_custom_msgs`. As an operand of
+    /// `Conjunction`, a failing message fails the whole conjunction.
+    CustomMsgs(Binary),
 }
 
 #[cw_serde]
@@ -127,7 +265,8 @@ pub enum QueryMsg {
     NftContract { class_id: String },
 
     /// Returns predictable NFT contract using instantiate2. If no
-    /// cw721_code_id is provided, default cw721_code_id from storage is used.
+    /// cw721_code_id is provided, code ID is resolved by voucher code ID rules,
+    /// otherwise default cw721_code_id from storage is used.
     #[returns(::cosmwasm_std::Addr)]
     GetInstantiate2NftContract {
         class_id: String,
@@ -143,6 +282,16 @@ pub enum QueryMsg {
     #[returns(Option<Token>)]
     TokenMetadata { class_id: String, token_id: String },
 
+    /// Same as `ClassMetadata`, but with class data decoded. In case
+    /// class data can't be decoded, raw data is returned.
+    #[returns(Option<DecodedClass>)]
+    DecodedClassMetadata { class_id: String },
+
+    /// Same as `TokenMetadata`, but with token data decoded. In case
+    /// token data can't be decoded, raw data is returned.
+    #[returns(Option<DecodedToken>)]
+    DecodedTokenMetadata { class_id: String, token_id: String },
+
     /// Gets the owner of the NFT identified by CLASS_ID and
     /// TOKEN_ID. Errors if no such NFT exists. Returns
     /// `cw721::OwnerOfResonse`.
@@ -177,6 +326,78 @@ pub enum QueryMsg {
     #[returns(Option<u32>)]
     ContractAddrLength {},
 
+    /// Gets the policy for refreshing class data of home collections on send.
+    #[returns(ClassDataRefreshPolicy)]
+    ClassDataRefreshPolicy {},
+
+    /// Gets whether collection info of existing voucher contracts is updated on incoming transfers.
+    #[returns(bool)]
+    UpdateVoucherCollectionInfo {},
+
+    /// Gets the policy for sending token metadata of vouchers on next hop.
+    #[returns(TokenMetadataPolicy)]
+    TokenMetadataPolicy {},
+
+    /// Gets the rules for selecting code ID and instantiate message of voucher contracts.
+    #[returns(Vec<VoucherCodeIdRule>)]
+    VoucherCodeIdRules {},
+
+    /// Gets whether incoming vouchers are minted into ICS721 custody.
+    #[returns(ClaimMode)]
+    ClaimMode {},
+
+    /// Gets default and max gas limits of callbacks.
+    #[returns(CallbackGasLimits)]
+    CallbackGasLimits {},
+
+    /// Gets a voucher held in ICS721 custody, claimable by the receiver.
+    #[returns(Option<ClaimableVoucher>)]
+    ClaimableVoucher { class_id: String, token_id: String },
+
+    /// Gets the translator for token data of incoming vouchers of a class.
+    #[returns(Option<MetadataTranslator>)]
+    MetadataTranslator { class_id: String },
+
+    /// Gets the profile of a home collection for presenting its vouchers on the target chain of a channel.
+    #[returns(Option<DestinationProfile>)]
+    DestinationProfile {
+        collection: String,
+        channel_id: String,
+    },
+
+    /// Gets the local royalty payment address registered for vouchers of a class.
+    #[returns(Option<Addr>)]
+    RoyaltyPayee { class_id: String },
+
+    /// Gets the royalty payee claim of a claimer, pending for approval by admin.
+    #[returns(Option<RoyaltyPayeeClaim>)]
+    RoyaltyPayeeClaim { class_id: String, claimer: String },
+
+    /// Gets the local nft contract a class is bound to by `RegisterClassMapping`.
+    #[returns(Option<Addr>)]
+    ClassMapping { class_id: String },
+
+    /// Gets results of `MigrateVoucherContracts` per voucher contract.
+    #[returns(Vec<(Addr, VoucherMigration)>)]
+    VoucherMigrations {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Gets failed ack callbacks, pending for `RetryAckCallback`.
+    #[returns(Vec<(u64, FailedAckCallback)>)]
+    FailedAckCallbacks {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Gets failed best-effort receive callbacks.
+    #[returns(Vec<(u64, FailedReceiveCallback)>)]
+    FailedReceiveCallbacks {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
     /// Gets a list of classID as key (from
     /// NonFungibleTokenPacketData) and cw721 contract as value
     /// (instantiated for that classID).
@@ -203,6 +424,156 @@ pub enum QueryMsg {
         start_after: Option<ClassToken>,
         limit: Option<u32>,
     },
+
+    /// Gets the local channel ID an NFT has been sent out on. Returns
+    /// None if the NFT is not escrowed by this contract.
+    #[returns(Option<String>)]
+    OutgoingChannel { class_id: String, token_id: String },
+
+    /// Gets the local channel ID an NFT has arrived at this contract.
+    /// Returns None if there is no voucher for the NFT.
+    #[returns(Option<String>)]
+    IncomingChannel { class_id: String, token_id: String },
+
+    /// Same as `OutgoingChannels`, but only for NFTs sent out on the
+    /// provided local channel. Fails while `MigrateChannelIndexes` is pending.
+    #[returns(Vec<((ClassId, TokenId), String)>)]
+    OutgoingChannelsByChannel {
+        channel_id: String,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Same as `IncomingChannels`, but only for NFTs arrived at the
+    /// provided local channel. Fails while `MigrateChannelIndexes` is pending.
+    #[returns(Vec<((ClassId, TokenId), String)>)]
+    IncomingChannelsByChannel {
+        channel_id: String,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Same as `OutgoingChannels`, but only for NFTs of the provided
+    /// class ID. `start_after` is a token ID.
+    #[returns(Vec<((ClassId, TokenId), String)>)]
+    OutgoingChannelsByClass {
+        class_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Same as `IncomingChannels`, but only for NFTs of the provided
+    /// class ID. `start_after` is a token ID.
+    #[returns(Vec<((ClassId, TokenId), String)>)]
+    IncomingChannelsByClass {
+        class_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Dry-runs receiving a packet with the provided packet data on the
+    /// provided endpoints (`dest` being this contract's endpoint), without
+    /// writing any state. Contracts called on receive (callbacks, `wasm`
+    /// memo, incoming proxy) are reported, but not executed. So checks done
+    /// by the nft contract (e.g. on mint or transfer) or by these contracts
+    /// are not simulated.
+    #[returns(SimulateReceiveResponse)]
+    SimulateReceive {
+        packet_data: NonFungibleTokenPacketData,
+        src: IbcEndpoint,
+        dest: IbcEndpoint,
+    },
+
+    /// Gets this contract's endpoint and the counterparty endpoint for a
+    /// local channel. Returns None if the channel is unknown.
+    #[returns(Option<ChannelInfo>)]
+    ChannelInfo { channel_id: String },
+
+    /// Gets the class ID the counterparty chain will see when sending an NFT
+    /// of `collection` over local `channel_id`.
+    #[returns(CounterpartyClassIdResponse)]
+    CounterpartyClassId {
+        collection: String,
+        channel_id: String,
+    },
+}
+
+#[cw_serde]
+pub struct DecodedClass {
+    pub id: ClassId,
+    pub uri: Option<String>,
+    pub data: Option<DecodedClassData>,
+}
+
+#[cw_serde]
+pub enum DecodedClassData {
+    /// Class data sent by ICS721 on the source chain.
+    CollectionData(CollectionData),
+    /// Class data in an unknown schema.
+    Raw(Binary),
+}
+
+#[cw_serde]
+pub struct DecodedToken {
+    pub id: TokenId,
+    pub uri: Option<String>,
+    pub data: Option<DecodedTokenData>,
+}
+
+#[cw_serde]
+pub enum DecodedTokenData {
+    /// Onchain metadata as used by cw721 (e.g. `cw721-metadata-onchain`).
+    NftExtension(NftExtension),
+    /// Token data in an unknown schema.
+    Raw(Binary),
+}
+
+#[cw_serde]
+pub struct SimulateReceiveResponse {
+    /// The error ICS721 would respond with in its error ack. In case of
+    /// None, the packet passes validation.
+    pub error: Option<String>,
+    pub error_code: Option<Ics721ErrorCode>,
+    /// Class ID used on this chain for the incoming class ID.
+    pub local_class_id: ClassId,
+    /// Whether NFTs are returning home (vouchers are redeemed) or new
+    /// vouchers are created. None in case of an error.
+    pub is_redemption: Option<bool>,
+    /// The nft contract receiving the NFTs. For a class ID not seen before,
+    /// this is the (predicted) contract instantiated on receive.
+    pub nft_contract: Option<Addr>,
+    /// Whether the nft contract gets instantiated on receive.
+    pub instantiates_nft_contract: bool,
+    /// Contract being called with a receive callback, if any. Receive
+    /// callbacks are skipped for vouchers held in custody.
+    pub receive_callback_addr: Option<String>,
+    /// Incoming proxy being called with the packet, if any.
+    pub incoming_proxy: Option<Addr>,
+    /// Whether vouchers are minted into ICS721 custody (claim mode), instead
+    /// of to the receiver.
+    pub in_custody: bool,
+    /// Contract being executed by a `wasm` memo, if any. Skipped for
+    /// vouchers held in custody.
+    pub wasm_hook_contract: Option<String>,
+    /// Contract being called with an ADR-008 destination callback, if any.
+    /// Skipped for vouchers held in custody.
+    pub dest_callback_addr: Option<String>,
+}
+
+#[cw_serde]
+pub struct CounterpartyClassIdResponse {
+    /// Class ID of the collection on this chain.
+    pub local_class_id: ClassId,
+    /// Class ID of the collection on the counterparty chain. In case of a
+    /// creation, the voucher contract can be retrieved by querying
+    /// `GetInstantiate2NftContract` on the counterparty ICS721 with this
+    /// class ID.
+    pub counterparty_class_id: ClassId,
+    /// Whether NFTs are returning home on the counterparty chain, or
+    /// vouchers are created there.
+    pub is_redemption: bool,
+    pub counterparty_port_id: String,
+    pub counterparty_channel_id: String,
 }
 
 #[cw_serde]
@@ -229,5 +600,18 @@ pub enum MigrateMsg {
         cw721_admin: Option<String>,
         /// The optional contract address length being used for instantiate2. In case of None, default length is 32 (standard in cosmwasm).
         contract_addr_length: Option<u32>,
+        /// Whether class data of home collections is refreshed on send. In case of None, the current policy is kept.
+        class_data_refresh_policy: Option<ClassDataRefreshPolicy>,
+        /// Whether collection info of existing voucher contracts is updated, in case incoming collection data has changed.
+        /// In case of None, the current setting is kept.
+        update_voucher_collection_info: Option<bool>,
+        /// Whether received or current token metadata of vouchers is sent on next hop. In case of None, the current policy is kept.
+        token_metadata_policy: Option<TokenMetadataPolicy>,
+        /// Rules for selecting code ID and instantiate message of voucher contracts. In case of None, the current rules are kept.
+        voucher_code_id_rules: Option<Vec<VoucherCodeIdRule>>,
+        /// Whether incoming vouchers are minted into ICS721 custody, claimable by the receiver. In case of None, the current mode is kept.
+        claim_mode: Option<ClaimMode>,
+        /// Default and max gas limits of callbacks. In case of None, the current limits are kept.
+        callback_gas_limits: Option<CallbackGasLimits>,
     },
 }
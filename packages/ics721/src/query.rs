@@ -1,21 +1,54 @@
-use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, Env, Order, StdError, StdResult, Storage};
-use cw_storage_plus::{Bound, Map};
-use sha2::{Digest, Sha256};
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Binary, Deps, Env, IbcEndpoint, Order, StdError, StdResult,
+    Storage, WasmMsg,
+};
+use cw721::NftExtension;
+use cw_storage_plus::{Bound, IndexedMap};
 
 use crate::{
-    helpers::get_instantiate2_address,
-    msg::QueryMsg,
+    helpers::{get_dest_callback, get_instantiate2_address, get_receive_callback},
+    ibc_packet_receive::{get_local_class_id, plan_receive, ReceivePlan},
+    msg::{
+        CounterpartyClassIdResponse, DecodedClass, DecodedClassData, DecodedToken,
+        DecodedTokenData, QueryMsg, SimulateReceiveResponse,
+    },
     state::{
-        UniversalAllNftInfoResponse, CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS,
-        CONTRACT_ADDR_LENGTH, CW721_ADMIN, CW721_CODE_ID, IBC_RECEIVE_TOKEN_METADATA,
-        INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL,
-        OUTGOING_PROXY, PO,
+        ClassTokenChannelIndexes, CollectionData, FailedAckCallback, FailedReceiveCallback,
+        UniversalAllNftInfoResponse, VoucherMigration, CALLBACK_GAS_LIMITS,
+        CHANNEL_INDEX_MIGRATIONS, CHANNEL_INFOS, CLAIMABLE_VOUCHERS, CLAIM_MODE,
+        CLASS_DATA_REFRESH_POLICY, CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS,
+        CLASS_MAPPINGS, CLASS_METADATA_TRANSLATOR, CONTRACT_ADDR_LENGTH, CW721_ADMIN,
+        CW721_CODE_ID, DESTINATION_PROFILES, FAILED_ACK_CALLBACKS, FAILED_RECEIVE_CALLBACKS,
+        IBC_RECEIVE_TOKEN_METADATA, INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY,
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO, ROYALTY_PAYEES, ROYALTY_PAYEE_CLAIMS,
+        TOKEN_METADATA_POLICY, UPDATE_VOUCHER_COLLECTION_INFO, VOUCHER_CODE_ID_RULES,
+        VOUCHER_MIGRATIONS,
     },
+    utils::{get_voucher_code_id, get_voucher_salt},
     ContractError,
 };
-use ics721_types::token_types::{Class, ClassId, ClassToken, Token, TokenId};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
+};
 
 pub trait Ics721Query {
+    /// Decodes class data for `QueryMsg::DecodedClassMetadata`. Override for supporting other schemas.
+    fn decode_class_data(&self, data: Binary) -> DecodedClassData {
+        match from_json::<CollectionData>(&data) {
+            Ok(collection_data) => DecodedClassData::CollectionData(collection_data),
+            Err(_) => DecodedClassData::Raw(data),
+        }
+    }
+
+    /// Decodes token data for `QueryMsg::DecodedTokenMetadata`. Override for supporting other schemas.
+    fn decode_token_data(&self, data: Binary) -> DecodedTokenData {
+        match from_json::<NftExtension>(&data) {
+            Ok(extension) => DecodedTokenData::NftExtension(extension),
+            Err(_) => DecodedTokenData::Raw(data),
+        }
+    }
+
     fn query(&self, deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
         match msg {
             QueryMsg::ClassId { contract } => Ok(to_json_binary(
@@ -39,6 +72,23 @@ pub trait Ics721Query {
             QueryMsg::TokenMetadata { class_id, token_id } => Ok(to_json_binary(
                 &query_token_metadata(deps, class_id, token_id)?,
             )?),
+            QueryMsg::DecodedClassMetadata { class_id } => {
+                let class = query_class_metadata(deps, class_id)?.map(|class| DecodedClass {
+                    id: class.id,
+                    uri: class.uri,
+                    data: class.data.map(|data| self.decode_class_data(data)),
+                });
+                Ok(to_json_binary(&class)?)
+            }
+            QueryMsg::DecodedTokenMetadata { class_id, token_id } => {
+                let token =
+                    query_token_metadata(deps, class_id, token_id)?.map(|token| DecodedToken {
+                        id: token.id,
+                        uri: token.uri,
+                        data: token.data.map(|data| self.decode_token_data(data)),
+                    });
+                Ok(to_json_binary(&token)?)
+            }
             QueryMsg::Owner { class_id, token_id } => {
                 Ok(to_json_binary(&query_owner(deps, class_id, token_id)?)?)
             }
@@ -51,6 +101,71 @@ pub trait Ics721Query {
             QueryMsg::ContractAddrLength {} => Ok(to_json_binary(
                 &CONTRACT_ADDR_LENGTH.may_load(deps.storage)?,
             )?),
+            QueryMsg::ClassDataRefreshPolicy {} => Ok(to_json_binary(
+                &CLASS_DATA_REFRESH_POLICY
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::UpdateVoucherCollectionInfo {} => Ok(to_json_binary(
+                &UPDATE_VOUCHER_COLLECTION_INFO
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::TokenMetadataPolicy {} => Ok(to_json_binary(
+                &TOKEN_METADATA_POLICY
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::VoucherCodeIdRules {} => Ok(to_json_binary(
+                &VOUCHER_CODE_ID_RULES
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::MetadataTranslator { class_id } => Ok(to_json_binary(
+                &CLASS_METADATA_TRANSLATOR.may_load(deps.storage, ClassId::new(class_id))?,
+            )?),
+            QueryMsg::DestinationProfile {
+                collection,
+                channel_id,
+            } => Ok(to_json_binary(&DESTINATION_PROFILES.may_load(
+                deps.storage,
+                (ClassId::new(collection), channel_id),
+            )?)?),
+            QueryMsg::RoyaltyPayee { class_id } => Ok(to_json_binary(
+                &ROYALTY_PAYEES.may_load(deps.storage, ClassId::new(class_id))?,
+            )?),
+            QueryMsg::RoyaltyPayeeClaim { class_id, claimer } => {
+                Ok(to_json_binary(&ROYALTY_PAYEE_CLAIMS.may_load(
+                    deps.storage,
+                    (ClassId::new(class_id), Addr::unchecked(claimer)),
+                )?)?)
+            }
+            QueryMsg::ClaimMode {} => Ok(to_json_binary(
+                &CLAIM_MODE.may_load(deps.storage)?.unwrap_or_default(),
+            )?),
+            QueryMsg::CallbackGasLimits {} => Ok(to_json_binary(
+                &CALLBACK_GAS_LIMITS
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::ClaimableVoucher { class_id, token_id } => {
+                Ok(to_json_binary(&CLAIMABLE_VOUCHERS.may_load(
+                    deps.storage,
+                    (ClassId::new(class_id), TokenId::new(token_id)),
+                )?)?)
+            }
+            QueryMsg::ClassMapping { class_id } => Ok(to_json_binary(
+                &CLASS_MAPPINGS.may_load(deps.storage, ClassId::new(class_id))?,
+            )?),
+            QueryMsg::VoucherMigrations { start_after, limit } => Ok(to_json_binary(
+                &query_voucher_migrations(deps, start_after, limit)?,
+            )?),
+            QueryMsg::FailedAckCallbacks { start_after, limit } => Ok(to_json_binary(
+                &query_failed_ack_callbacks(deps, start_after, limit)?,
+            )?),
+            QueryMsg::FailedReceiveCallbacks { start_after, limit } => Ok(to_json_binary(
+                &query_failed_receive_callbacks(deps, start_after, limit)?,
+            )?),
             QueryMsg::NftContracts { start_after, limit } => Ok(to_json_binary(
                 &query_nft_contracts(deps, start_after, limit)?,
             )?),
@@ -60,6 +175,78 @@ pub trait Ics721Query {
             QueryMsg::IncomingChannels { start_after, limit } => Ok(to_json_binary(
                 &query_channels(deps, &INCOMING_CLASS_TOKEN_TO_CHANNEL, start_after, limit)?,
             )?),
+            QueryMsg::OutgoingChannel { class_id, token_id } => Ok(to_json_binary(
+                &query_channel(deps, &OUTGOING_CLASS_TOKEN_TO_CHANNEL, class_id, token_id)?,
+            )?),
+            QueryMsg::IncomingChannel { class_id, token_id } => Ok(to_json_binary(
+                &query_channel(deps, &INCOMING_CLASS_TOKEN_TO_CHANNEL, class_id, token_id)?,
+            )?),
+            QueryMsg::OutgoingChannelsByChannel {
+                channel_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_channel(
+                deps,
+                "outgoing",
+                &OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+                channel_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::IncomingChannelsByChannel {
+                channel_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_channel(
+                deps,
+                "incoming",
+                &INCOMING_CLASS_TOKEN_TO_CHANNEL,
+                channel_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::OutgoingChannelsByClass {
+                class_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_class(
+                deps,
+                &OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+                class_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::IncomingChannelsByClass {
+                class_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_class(
+                deps,
+                &INCOMING_CLASS_TOKEN_TO_CHANNEL,
+                class_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::SimulateReceive {
+                packet_data,
+                src,
+                dest,
+            } => Ok(to_json_binary(&query_simulate_receive(
+                deps,
+                &env,
+                packet_data,
+                src,
+                dest,
+            )?)?),
+            QueryMsg::ChannelInfo { channel_id } => Ok(to_json_binary(
+                &CHANNEL_INFOS.may_load(deps.storage, channel_id)?,
+            )?),
+            QueryMsg::CounterpartyClassId {
+                collection,
+                channel_id,
+            } => Ok(to_json_binary(&query_counterparty_class_id(
+                deps, collection, channel_id,
+            )?)?),
         }
     }
 }
@@ -101,13 +288,16 @@ pub fn query_get_instantiate2_nft_contract(
     let cw721_code_id = if let Some(cw721_code_id) = cw721_code_id {
         cw721_code_id
     } else {
-        CW721_CODE_ID.load(deps.storage)?
+        // same as on voucher creation, class data is available once a class has been received
+        let class = CLASS_ID_TO_CLASS.may_load(deps.storage, class_id.clone())?;
+        get_voucher_code_id(
+            deps.storage,
+            &class_id,
+            class.as_ref().and_then(|class| class.data.as_ref()),
+        )?
     };
 
-    let mut hasher = Sha256::new();
-    hasher.update(class_id.as_bytes());
-    let salt = hasher.finalize().to_vec();
-
+    let salt = get_voucher_salt(deps.storage, &class_id)?;
     let nft_contract =
         get_instantiate2_address(deps, env.contract.address.as_str(), &salt, cw721_code_id)?;
     Ok(nft_contract)
@@ -196,23 +386,233 @@ pub fn query_nft_contracts(
     }
 }
 
+pub fn query_failed_ack_callbacks(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, FailedAckCallback)>> {
+    let start = start_after.map(Bound::exclusive);
+    let all = FAILED_ACK_CALLBACKS.range(deps.storage, start, None, Order::Ascending);
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+pub fn query_failed_receive_callbacks(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, FailedReceiveCallback)>> {
+    let start = start_after.map(Bound::exclusive);
+    let all = FAILED_RECEIVE_CALLBACKS.range(deps.storage, start, None, Order::Ascending);
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+pub fn query_voucher_migrations(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, VoucherMigration)>> {
+    let start = start_after
+        .map(|s| deps.api.addr_validate(&s))
+        .transpose()?
+        .map(Bound::exclusive);
+    let all = VOUCHER_MIGRATIONS.range(deps.storage, start, None, Order::Ascending);
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+pub fn query_simulate_receive(
+    deps: Deps,
+    env: &Env,
+    packet_data: NonFungibleTokenPacketData,
+    src: IbcEndpoint,
+    dest: IbcEndpoint,
+) -> StdResult<SimulateReceiveResponse> {
+    let (local_class_id, _) = get_local_class_id(&src, &dest, &packet_data.class_id);
+    let incoming_proxy = INCOMING_PROXY.may_load(deps.storage)?.flatten();
+    // same checks and order as in `receive_ibc_packet`
+    let plan = PO
+        .error_if_paused(deps.storage)
+        .map_err(ContractError::from)
+        .and_then(|_| packet_data.validate().map_err(ContractError::from))
+        // sequence and relayer are unknown before the packet is relayed
+        .and_then(|_| {
+            plan_receive(
+                deps,
+                env,
+                &packet_data,
+                &src,
+                &dest,
+                0,
+                &Addr::unchecked(""),
+            )
+        });
+    match plan {
+        Ok(ReceivePlan {
+            is_redemption,
+            nft_contract,
+            callback_msg,
+            wasm_hook_msg,
+            in_custody,
+            ..
+        }) => {
+            let instantiates_nft_contract = !is_redemption
+                && query_nft_contract_for_class_id(deps.storage, local_class_id.clone())?.is_none();
+            // callback message may be executed by ICS721 (e.g. with a gas limit), so the called back contract
+            // is taken from the memo
+            let receive_callback_addr = callback_msg
+                .and_then(|_| get_receive_callback(&packet_data))
+                .and_then(|(_, receive_callback_addr)| {
+                    deps.api
+                        .addr_validate(
+                            &receive_callback_addr.unwrap_or(packet_data.receiver.clone()),
+                        )
+                        .ok()
+                })
+                .map(|addr| addr.into_string());
+            let wasm_hook_contract = wasm_hook_msg.and_then(|msg| match msg {
+                WasmMsg::Execute { contract_addr, .. } => Some(contract_addr),
+                _ => None,
+            });
+            let dest_callback_addr = get_dest_callback(&packet_data)
+                .filter(|_| !in_custody)
+                .and_then(|callback| deps.api.addr_validate(&callback.address).ok())
+                .map(|addr| addr.into_string());
+            Ok(SimulateReceiveResponse {
+                error: None,
+                error_code: None,
+                local_class_id,
+                is_redemption: Some(is_redemption),
+                nft_contract: Some(nft_contract),
+                instantiates_nft_contract,
+                receive_callback_addr,
+                incoming_proxy,
+                in_custody,
+                wasm_hook_contract,
+                dest_callback_addr,
+            })
+        }
+        Err(error) => Ok(SimulateReceiveResponse {
+            error: Some(error.to_string()),
+            error_code: Some(error.error_code()),
+            local_class_id,
+            is_redemption: None,
+            nft_contract: None,
+            instantiates_nft_contract: false,
+            receive_callback_addr: None,
+            incoming_proxy: None,
+            in_custody: false,
+            wasm_hook_contract: None,
+            dest_callback_addr: None,
+        }),
+    }
+}
+
+pub fn query_counterparty_class_id(
+    deps: Deps,
+    collection: String,
+    channel_id: String,
+) -> Result<CounterpartyClassIdResponse, ContractError> {
+    let collection = deps.api.addr_validate(&collection)?;
+    let channel = CHANNEL_INFOS
+        .may_load(deps.storage, channel_id.clone())?
+        .ok_or(ContractError::UnknownChannel(channel_id))?;
+    // No class ID being present means that this is a local NFT
+    // that has never been sent out of this contract.
+    let local_class_id = load_class_id_for_nft_contract(deps.storage, &collection)?
+        .unwrap_or_else(|| ClassId::new(collection.to_string()));
+    // counterparty applies the same rules as we do on receive, with this contract's endpoint as source
+    let (counterparty_class_id, is_redemption) = get_local_class_id(
+        &channel.endpoint,
+        &channel.counterparty_endpoint,
+        &local_class_id,
+    );
+    Ok(CounterpartyClassIdResponse {
+        local_class_id,
+        counterparty_class_id,
+        is_redemption,
+        counterparty_port_id: channel.counterparty_endpoint.port_id,
+        counterparty_channel_id: channel.counterparty_endpoint.channel_id,
+    })
+}
+
+pub fn query_channel(
+    deps: Deps,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ClassTokenChannelIndexes>,
+    class_id: String,
+    token_id: String,
+) -> StdResult<Option<String>> {
+    class_token_to_channel.may_load(
+        deps.storage,
+        (ClassId::new(class_id), TokenId::new(token_id)),
+    )
+}
+
 fn query_channels(
     deps: Deps,
-    class_token_to_channel: &Map<(ClassId, TokenId), String>,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ClassTokenChannelIndexes>,
+    start_after: Option<ClassToken>,
+    limit: Option<u32>,
+) -> StdResult<Vec<((String, String), String)>> {
+    let start = start_after
+        .map(|class_token| Bound::exclusive((class_token.class_id, class_token.token_id)));
+    let all = class_token_to_channel.range(deps.storage, start, None, Order::Ascending);
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+/// Fails while the channel index migration of the map is pending (see `ExecuteMsg::MigrateChannelIndexes`), since
+/// entries stored before the index existed would be missing.
+fn query_channels_by_channel(
+    deps: Deps,
+    name: &str,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ClassTokenChannelIndexes>,
+    channel_id: String,
     start_after: Option<ClassToken>,
     limit: Option<u32>,
 ) -> StdResult<Vec<((String, String), String)>> {
-    let start_after = start_after.map(|class_token| {
-        (
-            ClassId::new(class_token.class_id),
-            TokenId::new(class_token.token_id),
-        )
-    });
-    cw_paginate_storage::paginate_map(
-        deps,
-        class_token_to_channel,
-        start_after,
-        limit,
+    if CHANNEL_INDEX_MIGRATIONS.has(deps.storage, name) {
+        return Err(StdError::generic_err(format!(
+            "{name} channel index migration pending, run MigrateChannelIndexes"
+        )));
+    }
+    let start = start_after
+        .map(|class_token| Bound::exclusive((class_token.class_id, class_token.token_id)));
+    let all = class_token_to_channel.idx.channel.prefix(channel_id).range(
+        deps.storage,
+        start,
+        None,
         Order::Ascending,
-    )
+    );
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+fn query_channels_by_class(
+    deps: Deps,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ClassTokenChannelIndexes>,
+    class_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<((String, String), String)>> {
+    let start = start_after.map(|token_id| Bound::exclusive(TokenId::new(token_id)));
+    let all = class_token_to_channel
+        .prefix(ClassId::new(class_id.clone()))
+        .range(deps.storage, start, None, Order::Ascending)
+        .map(|item| item.map(|(token_id, channel)| ((class_id.clone(), token_id), channel)));
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
 }
@@ -1,11 +1,21 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_schema::{cw_serde, schemars::JsonSchema};
-use cosmwasm_std::{Addr, Binary, ContractInfoResponse, Empty, Timestamp};
+use cosmwasm_std::{
+    from_json, Addr, Api, Binary, ContractInfoResponse, Empty, IbcEndpoint, IbcPacket, StdResult,
+    Timestamp,
+};
 use cw721::{DefaultOptionalCollectionExtension, DefaultOptionalNftExtension};
 use cw_pause_once::PauseOrchestrator;
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, UniqueIndex};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
 use serde::{Deserialize, Serialize};
 
-use ics721_types::token_types::{Class, ClassId, TokenId};
+use ics721_types::{
+    token_types::{Class, ClassId, TokenId},
+    types::Ics721Status,
+};
+
+use crate::translators::{parse_irismod, JsonValue, MetadataTranslator};
 
 /// The code ID we will use for instantiating new cw721s.
 pub const CW721_CODE_ID: Item<u64> = Item::new("a");
@@ -36,9 +46,35 @@ pub const CLASS_ID_TO_CLASS: Map<ClassId, Class> = Map::new("g");
 
 /// Maps (class ID, token ID) -> local channel ID. Used to determine
 /// the local channel that NFTs have been sent out on.
-pub const OUTGOING_CLASS_TOKEN_TO_CHANNEL: Map<(ClassId, TokenId), String> = Map::new("h");
+pub const OUTGOING_CLASS_TOKEN_TO_CHANNEL: IndexedMap<
+    (ClassId, TokenId),
+    String,
+    ClassTokenChannelIndexes,
+> = IndexedMap::new(
+    "h",
+    ClassTokenChannelIndexes {
+        channel: MultiIndex::new(
+            |_pk, channel| channel.clone(),
+            "h",
+            "outgoing_channel__channel",
+        ),
+    },
+);
 /// Same as above, but for NFTs arriving at this contract.
-pub const INCOMING_CLASS_TOKEN_TO_CHANNEL: Map<(ClassId, TokenId), String> = Map::new("i");
+pub const INCOMING_CLASS_TOKEN_TO_CHANNEL: IndexedMap<
+    (ClassId, TokenId),
+    String,
+    ClassTokenChannelIndexes,
+> = IndexedMap::new(
+    "i",
+    ClassTokenChannelIndexes {
+        channel: MultiIndex::new(
+            |_pk, channel| channel.clone(),
+            "i",
+            "incoming_channel__channel",
+        ),
+    },
+);
 
 /// IMPORTANT: collections can either come from (a) smart contracts or (b) nft module.
 /// This map is the truth of source. Only for smart contracts and in case of `receive_nft`
@@ -58,6 +94,83 @@ pub const CW721_ADMIN: Item<Option<Addr>> = Item::new("l");
 /// Bug: https://github.com/CosmWasm/cosmwasm/issues/2155
 pub const CONTRACT_ADDR_LENGTH: Item<u32> = Item::new("n");
 
+/// Maps local channel ID -> channel info. Used to determine the
+/// counterparty endpoint of a local channel.
+pub const CHANNEL_INFOS: Map<String, ChannelInfo> = Map::new("o");
+
+/// Defines when class data of home collections is refreshed on send. In case of None, it is never refreshed.
+pub const CLASS_DATA_REFRESH_POLICY: Item<ClassDataRefreshPolicy> = Item::new("p");
+/// Maps class ID -> block time class data of a home collection has been created or refreshed.
+pub const CLASS_DATA_UPDATED_AT: Map<ClassId, Timestamp> = Map::new("q");
+
+/// Whether collection info of existing voucher contracts is updated, in case incoming collection data has changed.
+pub const UPDATE_VOUCHER_COLLECTION_INFO: Item<bool> = Item::new("r");
+
+/// Defines which token metadata of vouchers is sent on next hop. In case of None, the received snapshot is sent.
+pub const TOKEN_METADATA_POLICY: Item<TokenMetadataPolicy> = Item::new("s");
+
+/// Maps class ID -> translator for token data of incoming vouchers. In case of None, the format is detected.
+pub const CLASS_METADATA_TRANSLATOR: Map<ClassId, MetadataTranslator> = Map::new("t");
+
+/// Maps (class ID, channel ID) -> profile set by the owner of a home collection for presenting its vouchers on target chain.
+pub const DESTINATION_PROFILES: Map<(ClassId, String), DestinationProfile> = Map::new("u");
+
+/// Maps class ID -> local royalty payment address for vouchers, registered by the collection owner on source chain.
+pub const ROYALTY_PAYEES: Map<ClassId, Addr> = Map::new("v");
+/// Maps (class ID, claimer) -> royalty payee claim, pending for approval by admin. Claims are kept per claimer, so a
+/// claim can't be replaced by others.
+pub const ROYALTY_PAYEE_CLAIMS: Map<(ClassId, Addr), RoyaltyPayeeClaim> = Map::new("w");
+
+/// Maps class ID -> existing local nft contract, bound by `RegisterClassMapping` instead of instantiating a voucher contract.
+pub const CLASS_MAPPINGS: Map<ClassId, Addr> = Map::new("x");
+
+/// Rules for selecting code ID and instantiate message of voucher contracts. First matching rule applies,
+/// in case no rule matches `CW721_CODE_ID` is used.
+pub const VOUCHER_CODE_ID_RULES: Item<Vec<VoucherCodeIdRule>> = Item::new("y");
+
+/// Maps voucher contract -> result of its last migration by `MigrateVoucherContracts`.
+pub const VOUCHER_MIGRATIONS: Map<Addr, VoucherMigration> = Map::new("z");
+/// Voucher contracts with a dispatched migrate submessage, in order of their replies.
+pub const PENDING_VOUCHER_MIGRATIONS: Item<Vec<Addr>> = Item::new("za");
+/// Target of a paged `MigrateVoucherContracts`, saved on its first page and removed once all pages are migrated.
+pub const VOUCHER_MIGRATION_TARGET: Item<VoucherMigrationTarget> = Item::new("zo");
+
+/// Whether incoming vouchers are minted into ICS721 custody, claimable by the receiver. In case of None, vouchers are
+/// minted to the receiver.
+pub const CLAIM_MODE: Item<ClaimMode> = Item::new("zb");
+/// Maps (class ID, token ID) -> voucher held in ICS721 custody, until it is claimed by the receiver or returned to the sender.
+pub const CLAIMABLE_VOUCHERS: Map<(ClassId, TokenId), ClaimableVoucher> = Map::new("zc");
+/// Default and max gas limits of callback submessages.
+pub const CALLBACK_GAS_LIMITS: Item<CallbackGasLimits> = Item::new("zd");
+/// Ack callbacks with a dispatched submessage, in order of their replies.
+pub const PENDING_ACK_CALLBACKS: Item<Vec<AckCallback>> = Item::new("ze");
+/// Maps ID -> failed ack callback, until it is retried by `ExecuteMsg::RetryAckCallback`.
+pub const FAILED_ACK_CALLBACKS: Map<u64, FailedAckCallback> = Map::new("zf");
+/// ID of the next failed ack callback.
+pub const NEXT_FAILED_ACK_CALLBACK_ID: Item<u64> = Item::new("zg");
+/// Best-effort receive callback of the packet being received. It is dispatched once NFTs have been received, and
+/// removed by its reply (or on failed receive), so it is recorded in case the callback fails.
+pub const PENDING_RECEIVE_CALLBACK: Item<ReceiveCallback> = Item::new("zh");
+/// Maps ID -> failed best-effort receive callback.
+pub const FAILED_RECEIVE_CALLBACKS: Map<u64, FailedReceiveCallback> = Map::new("zi");
+/// ID of the next failed best-effort receive callback.
+pub const NEXT_FAILED_RECEIVE_CALLBACK_ID: Item<u64> = Item::new("zj");
+/// Maps channel map name ("outgoing" or "incoming") -> pending migration of entries stored before the map had a channel
+/// index. Entries are indexed in pages by `ExecuteMsg::MigrateChannelIndexes`.
+pub const CHANNEL_INDEX_MIGRATIONS: Map<&str, ChannelIndexMigration> = Map::new("zk");
+
+/// Collection info updates of nft contracts, in order of dispatch. Each is popped by its reply, emitting its result.
+pub const PENDING_COLLECTION_INFO_UPDATES: Item<Vec<CollectionInfoUpdate>> = Item::new("zl");
+
+/// Maps class ID -> number of removed voucher mappings. Used in the instantiate2 salt, so a voucher contract
+/// instantiated after removal doesn't collide with the previous one.
+pub const VOUCHER_SALT_NONCES: Map<ClassId, u64> = Map::new("zm");
+
+/// Nft contracts unlinked from their class by `RemoveClassMapping`, `RepointClassMapping` or
+/// `ReinstantiateVoucherContract`. ICS721 may still be their minter and creator, so same as mapped nft contracts they
+/// can't be called by a `wasm` memo.
+pub const UNLINKED_NFT_CONTRACTS: Map<&Addr, Empty> = Map::new("zn");
+
 #[derive(Deserialize, Debug)]
 pub struct UniversalAllNftInfoResponse {
     pub access: UniversalOwnerOfResponse,
@@ -98,6 +211,23 @@ pub struct CollectionData {
     pub name: String,
     pub symbol: String,
     pub extension: DefaultOptionalCollectionExtension,
+    /// Presentation of vouchers on target chain, set by the collection owner on source chain.
+    /// It is only sent to the channel it has been set for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_profile: Option<DestinationProfile>,
+}
+
+/// Defines how vouchers of a home collection are presented on target chain. Unset props fall back to collection data.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[schemars(crate = "cosmwasm_schema::schemars")]
+#[serde(crate = "cosmwasm_schema::serde")]
+pub struct DestinationProfile {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub image: Option<String>,
+    /// Royalty payment address on target chain. In case it is not a valid address there, ICS721 uses its default.
+    pub royalty_payee: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -118,6 +248,273 @@ pub struct ClassIdInfo {
     pub address: Addr,
 }
 
+#[cw_serde]
+pub struct ChannelInfo {
+    /// This contract's endpoint.
+    pub endpoint: IbcEndpoint,
+    pub counterparty_endpoint: IbcEndpoint,
+    /// Connection the channel has been opened on. In case of None, the channel has
+    /// been connected before channels were stored and got registered on its first
+    /// incoming packet.
+    pub connection_id: Option<String>,
+}
+
+/// Class data of home collections is created on initial send. This policy defines whether it is
+/// refreshed on subsequent sends, so changes in collection info (e.g. name or royalties) are sent
+/// to other chains. Class data can always be refreshed explicitly by `ExecuteMsg::RefreshClassData`.
+#[cw_serde]
+#[derive(Default)]
+pub enum ClassDataRefreshPolicy {
+    /// Class data is never refreshed on send.
+    #[default]
+    Never,
+    /// Class data is refreshed on each send.
+    Always,
+    /// Class data is refreshed on send, in case it is older than the given seconds.
+    AfterSeconds(u64),
+}
+
+impl ClassDataRefreshPolicy {
+    pub fn is_stale(&self, updated_at: Option<Timestamp>, now: Timestamp) -> bool {
+        match self {
+            ClassDataRefreshPolicy::Never => false,
+            ClassDataRefreshPolicy::Always => true,
+            // class data created before refreshing was supported, is always stale
+            ClassDataRefreshPolicy::AfterSeconds(seconds) => {
+                updated_at.map_or(true, |updated_at| now >= updated_at.plus_seconds(*seconds))
+            }
+        }
+    }
+}
+
+/// Token metadata received via IBC is stored as a snapshot in `IBC_RECEIVE_TOKEN_METADATA`. This policy defines
+/// whether the snapshot or the current on-chain metadata of a voucher is sent, in case it is transferred onward.
+/// The snapshot is kept in any case.
+#[cw_serde]
+#[derive(Default)]
+pub enum TokenMetadataPolicy {
+    /// Metadata received via IBC is sent.
+    #[default]
+    Snapshot,
+    /// Current extension of the voucher is sent, in case it differs from the snapshot.
+    Live,
+    /// Current extension of the voucher is sent, in case it differs from the snapshot and the voucher contract's
+    /// creator (who is allowed to update NFT metadata) is one of the given trusted updaters.
+    LiveIfTrustedUpdater(Vec<String>),
+}
+
+impl TokenMetadataPolicy {
+    pub fn validate(&self, api: &dyn Api) -> StdResult<()> {
+        if let TokenMetadataPolicy::LiveIfTrustedUpdater(updaters) = self {
+            for updater in updaters {
+                api.addr_validate(updater)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Selects code ID and instantiate message for voucher contracts of matching classes,
+/// e.g. soulbound-aware contracts for a channel or cw721-expiration for rentals.
+#[cw_serde]
+pub struct VoucherCodeIdRule {
+    pub matcher: VoucherMatcher,
+    pub code_id: u64,
+    pub init_msg: VoucherInitMsg,
+}
+
+impl VoucherCodeIdRule {
+    pub fn validate(&self) -> StdResult<()> {
+        if let VoucherInitMsg::Extend(fields) = &self.init_msg {
+            from_json::<BTreeMap<String, JsonValue>>(fields)?;
+        }
+        Ok(())
+    }
+}
+
+#[cw_serde]
+pub enum VoucherMatcher {
+    /// Classes received on a local channel (e.g. `channel-1`), as given in voucher class ID `{port}/{channel}/{class}`.
+    Channel(String),
+    /// Class IDs starting with prefix.
+    ClassIdPrefix(String),
+    /// Class data holding a top level key (e.g. `soulbound`), either as JSON or as key/value data of SDK modules.
+    ClassDataKey(String),
+}
+
+impl VoucherMatcher {
+    pub fn matches(&self, class_id: &ClassId, class_data: Option<&Binary>) -> bool {
+        match self {
+            VoucherMatcher::Channel(channel_id) => {
+                class_id.split('/').nth(1) == Some(channel_id.as_str())
+            }
+            VoucherMatcher::ClassIdPrefix(prefix) => class_id.starts_with(prefix.as_str()),
+            VoucherMatcher::ClassDataKey(key) => class_data.map_or(false, |data| {
+                parse_irismod(data)
+                    .or_else(|| from_json::<BTreeMap<String, JsonValue>>(data).ok())
+                    .map_or(false, |object| object.contains_key(key))
+            }),
+        }
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum VoucherInitMsg {
+    /// Instantiate message created by `init_msg`.
+    #[default]
+    Default,
+    /// Instantiate message created by `init_msg`, extended by top level fields of given JSON object,
+    /// e.g. `{"expiration_days": 30}` for cw721-expiration.
+    Extend(Binary),
+}
+
+#[cw_serde]
+pub struct VoucherMigration {
+    pub class_id: ClassId,
+    pub code_id: u64,
+    pub status: VoucherMigrationStatus,
+}
+
+#[cw_serde]
+pub struct VoucherMigrationTarget {
+    pub code_id: u64,
+    pub msg: Binary,
+    /// Number of failed migrations on all pages so far.
+    pub failed: u64,
+    /// Whether the last page has been dispatched.
+    pub last_page: bool,
+}
+
+#[cw_serde]
+pub enum VoucherMigrationStatus {
+    /// Migrate submessage has been dispatched.
+    Pending,
+    Migrated,
+    Failed(String),
+    /// Not migrated, e.g. ICS721 is not admin of the contract or contract already has the new code ID.
+    Skipped(String),
+}
+
+/// Minting a voucher fails in case receiver is refused by the voucher contract, and the NFT bounces back to source chain.
+/// In claim mode vouchers are minted into ICS721 custody instead, and released by `ExecuteMsg::Claim`.
+#[cw_serde]
+#[derive(Default)]
+pub enum ClaimMode {
+    /// Vouchers are minted to the receiver.
+    #[default]
+    Disabled,
+    /// Vouchers are minted into ICS721 custody and claimable by the receiver. Unclaimed vouchers expire after the
+    /// given seconds, and then may be returned to the sender by `ExecuteMsg::ReturnUnclaimedVouchers`. Since the
+    /// receiver doesn't own the vouchers yet, receive callbacks, ADR-008 destination callbacks and `wasm` memos are skipped.
+    Enabled { expiry: u64 },
+}
+
+impl ClaimMode {
+    /// Expiry for vouchers received now, None in case claim mode is disabled.
+    pub fn expires_at(&self, now: Timestamp) -> Option<Timestamp> {
+        match self {
+            ClaimMode::Disabled => None,
+            ClaimMode::Enabled { expiry } => Some(now.plus_seconds(*expiry)),
+        }
+    }
+}
+
+/// Gas limits of callbacks, so a callback contract can't burn the relayer's whole gas. In case of None, gas is
+/// unlimited.
+#[cw_serde]
+#[derive(Default)]
+pub struct CallbackGasLimits {
+    /// Gas limit of ack callbacks, in case memo provides none.
+    pub default_ack: Option<u64>,
+    /// Gas limit of receive callbacks, in case memo provides none.
+    pub default_receive: Option<u64>,
+    /// Memo-provided and default gas limits are capped by max.
+    pub max: Option<u64>,
+}
+
+impl CallbackGasLimits {
+    pub fn ack(&self, memo_gas_limit: Option<u64>) -> Option<u64> {
+        self.cap(memo_gas_limit.or(self.default_ack))
+    }
+
+    pub fn receive(&self, memo_gas_limit: Option<u64>) -> Option<u64> {
+        self.cap(memo_gas_limit.or(self.default_receive))
+    }
+
+    fn cap(&self, gas_limit: Option<u64>) -> Option<u64> {
+        match (gas_limit, self.max) {
+            (Some(gas_limit), Some(max)) => Some(gas_limit.min(max)),
+            (gas_limit, max) => gas_limit.or(max),
+        }
+    }
+}
+
+/// Ack callback (ICS721 or ADR-008) of a packet sent by this chain.
+#[cw_serde]
+pub struct AckCallback {
+    /// Contract being called back.
+    pub contract: Addr,
+    /// Callback message, e.g. `ReceiverExecuteMsg::Ics721AckCallback`.
+    pub msg: Binary,
+    pub gas_limit: Option<u64>,
+    pub packet: IbcPacket,
+    pub status: Ics721Status,
+}
+
+#[cw_serde]
+pub struct FailedAckCallback {
+    pub callback: AckCallback,
+    pub error: String,
+}
+
+/// Best-effort receive callback of a packet received by this chain.
+#[cw_serde]
+pub struct ReceiveCallback {
+    /// Contract being called back.
+    pub contract: Addr,
+    /// Callback message, e.g. `ReceiverExecuteMsg::Ics721ReceiveCallback`.
+    pub msg: Binary,
+    /// Gas limit of the callback submessage, if any.
+    pub gas_limit: Option<u64>,
+}
+
+#[cw_serde]
+pub struct FailedReceiveCallback {
+    pub callback: ReceiveCallback,
+    pub error: String,
+}
+
+#[cw_serde]
+pub struct CollectionInfoUpdate {
+    pub class_id: ClassId,
+    pub nft_contract: Addr,
+    /// Changed props, e.g. "name" or "royalty_info".
+    pub changes: Vec<String>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ChannelIndexMigration {
+    /// Last indexed entry, in case of None no entry has been indexed yet.
+    pub start_after: Option<(ClassId, TokenId)>,
+}
+
+#[cw_serde]
+pub struct ClaimableVoucher {
+    /// Local address that may claim the voucher.
+    pub receiver: Addr,
+    /// Sender on source chain, unclaimed vouchers are returned to.
+    pub sender: String,
+    pub expires_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct RoyaltyPayeeClaim {
+    pub claimer: Addr,
+    pub payee: Addr,
+}
+
 pub struct ClassIdInfoIndexes<'a> {
     pub class_id: UniqueIndex<'a, ClassId, ClassIdInfo>,
     pub address: UniqueIndex<'a, Addr, ClassIdInfo>,
@@ -130,6 +527,19 @@ impl<'a> IndexList<ClassIdInfo> for ClassIdInfoIndexes<'a> {
     }
 }
 
+/// Entries are indexed by channel only. Entries of a class are queried by prefix, since class ID is the first part of
+/// the primary key.
+pub struct ClassTokenChannelIndexes<'a> {
+    pub channel: MultiIndex<'a, String, String, (ClassId, TokenId)>,
+}
+
+impl<'a> IndexList<String> for ClassTokenChannelIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<String>> + '_> {
+        let v: Vec<&dyn Index<String>> = vec![&self.channel];
+        Box::new(v.into_iter())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::{from_json, to_json_binary};
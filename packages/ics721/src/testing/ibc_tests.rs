@@ -3,27 +3,44 @@ use core::panic;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     attr, from_json,
-    testing::{mock_dependencies, mock_env, mock_info},
-    to_json_binary, to_json_vec, Addr, Attribute, Binary, DepsMut, Empty, Env, IbcAcknowledgement,
-    IbcChannel, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder, IbcPacket,
-    IbcPacketReceiveMsg, IbcTimeout, Reply, Response, StdResult, SubMsgResponse, SubMsgResult,
-    Timestamp, WasmMsg,
+    testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR},
+    to_json_binary, to_json_vec, Addr, Attribute, Binary, ContractInfoResponse, ContractResult,
+    CosmosMsg, Deps, DepsMut, Empty, Env, Event, IbcAcknowledgement, IbcChannel,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcTimeout, Reply, ReplyOn, Response, StdResult,
+    SubMsg, SubMsgResponse, SubMsgResult, SystemError, SystemResult, Timestamp, WasmMsg, WasmQuery,
 };
 
 use crate::{
     execute::Ics721Execute,
-    ibc::{Ics721Ibc, ACK_AND_DO_NOTHING_REPLY_ID, IBC_VERSION, INSTANTIATE_CW721_REPLY_ID},
-    ibc_helpers::{ack_fail, ack_success, try_get_ack_error},
-    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, QueryMsg},
+    ibc::{
+        Ics721Ibc, ACK_AND_DO_NOTHING_REPLY_ID, ACK_CALLBACK_REPLY_ID, IBC_VERSION,
+        INSTANTIATE_CW721_REPLY_ID, RECEIVE_CALLBACK_REPLY_ID,
+    },
+    ibc_helpers::{ack_fail_with_code, ack_success, try_get_ack_error},
+    msg::{
+        CallbackMsg, CounterpartyClassIdResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+        SimulateReceiveResponse,
+    },
     query::Ics721Query,
-    state::{ClassIdInfo, CollectionData, CLASS_ID_AND_NFT_CONTRACT_INFO, CW721_CODE_ID, PO},
+    state::{
+        AckCallback, CallbackGasLimits, ChannelInfo, ClaimMode, ClaimableVoucher, ClassIdInfo,
+        CollectionData, FailedAckCallback, FailedReceiveCallback, ReceiveCallback,
+        CALLBACK_GAS_LIMITS, CLAIM_MODE, CLASS_ID_AND_NFT_CONTRACT_INFO, CW721_CODE_ID,
+        INCOMING_PROXY, PENDING_RECEIVE_CALLBACK, PO,
+    },
     utils::get_collection_data,
     ContractError,
 };
 use ics721_types::{
     ibc_types::NonFungibleTokenPacketData,
     token_types::{ClassId, TokenId},
-    types::{Ics721Callbacks, ReceiverExecuteMsg},
+    types::{
+        IbcAckCallbackMsg, IbcDestinationCallbackMsg, IbcSourceCallbackMsg, IbcTimeoutCallbackMsg,
+        Ics721AckCallbackMsgV2, Ics721CallbackContext, Ics721CallbackVersion, Ics721Callbacks,
+        Ics721ErrorCode, Ics721Memo, Ics721ReceiveCallbackMsg, Ics721ReceiveCallbackMsgV2,
+        Ics721Status, ReceiveCallbackMode, ReceiverExecuteMsg,
+    },
 };
 
 const CONTRACT_PORT: &str = "wasm.address1";
@@ -48,6 +65,38 @@ impl Ics721Execute<Empty> for Ics721Contract {
 impl Ics721Ibc<Empty> for Ics721Contract {}
 impl Ics721Query for Ics721Contract {}
 
+/// Chain specific message, e.g. Stargaze's fair burn.
+#[cw_serde]
+pub enum FairBurnMsg {
+    FundFairburnPool { receiver: String },
+}
+
+/// Flavour emitting a custom message for each received packet.
+#[derive(Default)]
+pub struct Ics721ContractFairBurn {}
+impl Ics721Execute<FairBurnMsg> for Ics721ContractFairBurn {
+    type ClassData = CollectionData;
+
+    fn get_class_data(&self, deps: &DepsMut, sender: &Addr) -> StdResult<Option<Self::ClassData>> {
+        get_collection_data(deps, sender).map(Option::Some)
+    }
+}
+impl Ics721Ibc<FairBurnMsg> for Ics721ContractFairBurn {
+    fn receive_custom_msgs(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        packet: &IbcPacket,
+    ) -> Result<Vec<SubMsg<FairBurnMsg>>, ContractError> {
+        let data: NonFungibleTokenPacketData = from_json(&packet.data)?;
+        Ok(vec![SubMsg::new(CosmosMsg::Custom(
+            FairBurnMsg::FundFairburnPool {
+                receiver: data.receiver,
+            },
+        ))])
+    }
+}
+
 fn mock_channel(channel_id: &str) -> IbcChannel {
     IbcChannel::new(
         IbcEndpoint {
@@ -112,6 +161,12 @@ fn do_instantiate(deps: DepsMut, env: Env, sender: &str) -> StdResult<Response>
         pauser: None,
         cw721_admin: None,
         contract_addr_length: None,
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
     };
     Ics721Contract::default().instantiate(deps, env, mock_info(sender, &[]), msg)
 }
@@ -206,7 +261,13 @@ fn test_stateless_reply() {
     let res = Ics721Contract::default()
         .reply(deps.as_mut(), mock_env(), rep)
         .unwrap();
-    assert_eq!(res.data, Some(ack_fail("some failure".to_string())));
+    assert_eq!(
+        res.data,
+        Some(ack_fail_with_code(
+            "some failure".to_string(),
+            Ics721ErrorCode::ExecutionFailed
+        ))
+    );
 }
 
 #[test]
@@ -237,6 +298,89 @@ fn test_ibc_channel_open() {
     add_channel(deps.as_mut(), env, "channel-1");
 }
 
+#[test]
+fn test_query_counterparty_class_id() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    do_instantiate(deps.as_mut(), env.clone(), ADDR1).unwrap();
+    add_channel(deps.as_mut(), env.clone(), "channel-1");
+
+    let channel_info: Option<ChannelInfo> = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::ChannelInfo {
+                    channel_id: "channel-1".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    let channel = mock_channel("channel-1");
+    assert_eq!(
+        channel_info,
+        Some(ChannelInfo {
+            endpoint: channel.endpoint.clone(),
+            counterparty_endpoint: channel.counterparty_endpoint.clone(),
+            connection_id: Some(CONNECTION_ID.to_string()),
+        })
+    );
+
+    // voucher received on channel-1
+    let voucher_class_id = ClassId::new("wasm.address1/channel-1/remote_collection");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &voucher_class_id,
+            &ClassIdInfo {
+                class_id: voucher_class_id.clone(),
+                address: Addr::unchecked("voucher_collection"),
+            },
+        )
+        .unwrap();
+
+    let query_counterparty_class_id = |collection: &str, channel_id: &str| {
+        Ics721Contract::default().query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::CounterpartyClassId {
+                collection: collection.to_string(),
+                channel_id: channel_id.to_string(),
+            },
+        )
+    };
+
+    // home collection, never sent before
+    let response: CounterpartyClassIdResponse =
+        from_json(query_counterparty_class_id("home_collection", "channel-1").unwrap()).unwrap();
+    assert_eq!(
+        response,
+        CounterpartyClassIdResponse {
+            local_class_id: ClassId::new("home_collection"),
+            counterparty_class_id: ClassId::new("stars.address1/channel-15/home_collection"),
+            is_redemption: false,
+            counterparty_port_id: REMOTE_PORT.to_string(),
+            counterparty_channel_id: "channel-15".to_string(),
+        }
+    );
+
+    // voucher returns home
+    let response: CounterpartyClassIdResponse =
+        from_json(query_counterparty_class_id("voucher_collection", "channel-1").unwrap()).unwrap();
+    assert_eq!(
+        response.counterparty_class_id,
+        ClassId::new("remote_collection")
+    );
+    assert!(response.is_redemption);
+
+    // unknown channel
+    assert_eq!(
+        query_counterparty_class_id("home_collection", "channel-2").unwrap_err(),
+        ContractError::UnknownChannel("channel-2".to_string())
+    );
+}
+
 #[test]
 #[should_panic(expected = "OrderedChannel")]
 fn test_ibc_channel_open_ordered_channel() {
@@ -526,6 +670,417 @@ fn test_ibc_packet_receive() {
     }
 }
 
+#[test]
+fn test_ibc_packet_receive_claim_mode() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    CLAIM_MODE
+        .save(&mut deps.storage, &ClaimMode::Enabled { expiry: 100 })
+        .unwrap();
+    let dest_class_id = format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &ClassId::new(dest_class_id.clone()),
+            &ClassIdInfo {
+                class_id: ClassId::new(dest_class_id.clone()),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let data = to_json_binary(&build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    ))
+    .unwrap();
+    let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+    let env = mock_env();
+    let response = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), packet)
+        .unwrap();
+    let callback = |msg: &WasmMsg| match msg {
+        WasmMsg::Execute { msg, .. } => match from_json::<ExecuteMsg>(msg).unwrap() {
+            ExecuteMsg::Callback(callback_msg) => callback_msg,
+            _ => panic!("unexpected execute msg"),
+        },
+        _ => panic!("unexpected wasm msg"),
+    };
+    let operands = match &response.messages[0].msg {
+        cosmwasm_std::CosmosMsg::Wasm(msg) => match callback(msg) {
+            CallbackMsg::Conjunction { operands } => operands,
+            _ => panic!("unexpected callback msg"),
+        },
+        _ => panic!("unexpected cosmos msg"),
+    };
+
+    // voucher is minted into custody of ICS721
+    match callback(&operands[0]) {
+        CallbackMsg::CreateVouchers { receiver, .. } => {
+            assert_eq!(receiver, env.contract.address.to_string())
+        }
+        _ => panic!("unexpected callback msg"),
+    }
+    // and claimable by receiver, once incoming channel is saved
+    let operands = match callback(&operands[1]) {
+        CallbackMsg::Conjunction { operands } => operands,
+        _ => panic!("unexpected callback msg"),
+    };
+    assert!(matches!(
+        callback(&operands[0]),
+        CallbackMsg::AddIncomingChannelEntries(_)
+    ));
+    assert_eq!(
+        callback(&operands[1]),
+        CallbackMsg::AddClaimableVouchers(vec![(
+            (ClassId::new(dest_class_id), TokenId::new("1")),
+            ClaimableVoucher {
+                receiver: Addr::unchecked("blue"),
+                sender: "violet".to_string(),
+                expires_at: env.block.time.plus_seconds(100),
+            }
+        )])
+    );
+}
+
+#[test]
+fn test_ibc_packet_receive_claim_mode_skips_callbacks() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    CLAIM_MODE
+        .save(&mut deps.storage, &ClaimMode::Enabled { expiry: 100 })
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &dest_class_id,
+            &ClassIdInfo {
+                class_id: dest_class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let receive_callback_memo = to_json_binary(&Ics721Memo {
+        callbacks: Some(Ics721Callbacks {
+            ack_callback_data: None,
+            ack_callback_addr: None,
+            receive_callback_data: Some(to_json_binary("receive").unwrap()),
+            receive_callback_addr: None,
+            version: None,
+            ack_callback_gas_limit: None,
+            receive_callback_gas_limit: None,
+            receive_callback_mode: None,
+        }),
+    })
+    .unwrap()
+    .to_base64();
+    let dest_callback_memo = r#"{"dest_callback":{"address":"callback"}}"#.to_string();
+    for memo in [receive_callback_memo, dest_callback_memo] {
+        let mut data = build_ics_packet(
+            "id",
+            None,
+            None,
+            vec!["1"],
+            None,
+            None,
+            "violet",
+            "blue",
+            None,
+        );
+        data.memo = Some(memo);
+        let packet = IbcPacketReceiveMsg::new(
+            mock_packet(to_json_binary(&data).unwrap()),
+            Addr::unchecked(RELAYER_ADDR),
+        );
+        let response = Ics721Contract::default()
+            .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+            .unwrap();
+        assert!(response.attributes.contains(&attr("in_custody", "true")));
+        let operands = match response.messages[0].msg.clone() {
+            cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+                match from_json::<ExecuteMsg>(msg).unwrap() {
+                    ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                    _ => panic!("unexpected execute msg"),
+                }
+            }
+            _ => panic!("unexpected cosmos msg"),
+        };
+        // receiver doesn't own the voucher yet, so only voucher and channel messages are executed
+        assert_eq!(operands.len(), 2);
+        for operand in operands {
+            match operand {
+                WasmMsg::Execute { contract_addr, .. } => {
+                    assert_eq!(contract_addr, MOCK_CONTRACT_ADDR)
+                }
+                _ => panic!("unexpected wasm msg"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ibc_packet_receive_custom_msg() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &dest_class_id,
+            &ClassIdInfo {
+                class_id: dest_class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let data = to_json_binary(&build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    ))
+    .unwrap();
+    let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+    let response = Ics721ContractFairBurn::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    // custom message is dispatched as part of the receive, after the voucher messages
+    assert_eq!(response.messages.len(), 1);
+    assert_eq!(response.messages[0].id, ACK_AND_DO_NOTHING_REPLY_ID);
+    let operands = match response.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(operands.len(), 3);
+    let custom_msgs = match operands[2].clone() {
+        WasmMsg::Execute {
+            contract_addr, msg, ..
+        } => {
+            assert_eq!(contract_addr, MOCK_CONTRACT_ADDR);
+            from_json::<ExecuteMsg>(msg).unwrap()
+        }
+        _ => panic!("unexpected wasm msg"),
+    };
+    let response = Ics721ContractFairBurn::default()
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            custom_msgs,
+        )
+        .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(CosmosMsg::Custom(
+            FairBurnMsg::FundFairburnPool {
+                receiver: "blue".to_string()
+            }
+        ))]
+    );
+
+    // a failing custom message fails the receive, and an error ack is sent
+    let response = Ics721ContractFairBurn::default()
+        .reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ACK_AND_DO_NOTHING_REPLY_ID,
+                result: SubMsgResult::Err("insufficient funds".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        response.data,
+        Some(ack_fail_with_code(
+            "insufficient funds".to_string(),
+            Ics721ErrorCode::ExecutionFailed
+        ))
+    );
+
+    // on error, an error ack is written without custom messages
+    let packet = IbcPacketReceiveMsg::new(
+        mock_packet(Binary::from(b"invalid".to_vec())),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let response = Ics721ContractFairBurn::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    assert!(response.messages.is_empty());
+    assert!(try_get_ack_error(&IbcAcknowledgement::new(response.acknowledgement)).is_some());
+}
+
+#[test]
+fn test_simulate_receive() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &ClassId::new(dest_class_id.clone()),
+            &ClassIdInfo {
+                class_id: ClassId::new(dest_class_id.clone()),
+                address: Addr::unchecked("cosmos2contract"),
+            },
+        )
+        .unwrap();
+    INCOMING_PROXY
+        .save(&mut deps.storage, &Some(Addr::unchecked("incoming_proxy")))
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let ibc_packet = mock_packet(Binary::default());
+    let packet_data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    );
+    let simulate =
+        |deps: Deps, packet_data: NonFungibleTokenPacketData| -> SimulateReceiveResponse {
+            from_json(
+                Ics721Contract::default()
+                    .query(
+                        deps,
+                        mock_env(),
+                        QueryMsg::SimulateReceive {
+                            packet_data,
+                            src: ibc_packet.src.clone(),
+                            dest: ibc_packet.dest.clone(),
+                        },
+                    )
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+
+    // voucher creation on existing nft contract
+    assert_eq!(
+        simulate(deps.as_ref(), packet_data.clone()),
+        SimulateReceiveResponse {
+            error: None,
+            error_code: None,
+            local_class_id: ClassId::new(dest_class_id.clone()),
+            is_redemption: Some(false),
+            nft_contract: Some(Addr::unchecked("cosmos2contract")),
+            instantiates_nft_contract: false,
+            receive_callback_addr: None,
+            incoming_proxy: Some(Addr::unchecked("incoming_proxy")),
+            in_custody: false,
+            wasm_hook_contract: None,
+            dest_callback_addr: None,
+        }
+    );
+
+    // side effects after receiving NFTs: dest callback and `wasm` memo are reported
+    let mut memo_packet_data = packet_data.clone();
+    memo_packet_data.memo = Some(
+        r#"{"wasm":{"contract":"blue","msg":{"deposit":{}}},"dest_callback":{"address":"callback"}}"#
+            .to_string(),
+    );
+    let response = simulate(deps.as_ref(), memo_packet_data);
+    assert_eq!(response.error, None);
+    assert_eq!(response.wasm_hook_contract, Some("blue".to_string()));
+    assert_eq!(response.dest_callback_addr, Some("callback".to_string()));
+    assert!(!response.in_custody);
+
+    // receive callback is reported, also in case it is executed by ICS721 with a gas limit
+    CALLBACK_GAS_LIMITS
+        .save(
+            &mut deps.storage,
+            &CallbackGasLimits {
+                default_ack: None,
+                default_receive: Some(200000),
+                max: None,
+            },
+        )
+        .unwrap();
+    for (receive_callback_addr, expected) in [(Some("callback"), "callback"), (None, "blue")] {
+        let mut memo_packet_data = packet_data.clone();
+        memo_packet_data.memo = Some(
+            to_json_binary(&Ics721Memo {
+                callbacks: Some(Ics721Callbacks {
+                    ack_callback_data: None,
+                    ack_callback_addr: None,
+                    receive_callback_data: Some(to_json_binary("receive").unwrap()),
+                    receive_callback_addr: receive_callback_addr.map(str::to_string),
+                    version: None,
+                    ack_callback_gas_limit: None,
+                    receive_callback_gas_limit: None,
+                    receive_callback_mode: None,
+                }),
+            })
+            .unwrap()
+            .to_base64(),
+        );
+        let response = simulate(deps.as_ref(), memo_packet_data);
+        assert_eq!(response.error, None);
+        assert_eq!(response.receive_callback_addr, Some(expected.to_string()));
+    }
+    CALLBACK_GAS_LIMITS.remove(&mut deps.storage);
+
+    // in claim mode, vouchers are held in custody
+    CLAIM_MODE
+        .save(&mut deps.storage, &ClaimMode::Enabled { expiry: 3600 })
+        .unwrap();
+    let response = simulate(deps.as_ref(), packet_data.clone());
+    assert!(response.in_custody);
+    // and dest callback is skipped
+    let mut memo_packet_data = packet_data.clone();
+    memo_packet_data.memo = Some(r#"{"dest_callback":{"address":"callback"}}"#.to_string());
+    let response = simulate(deps.as_ref(), memo_packet_data);
+    assert!(response.in_custody);
+    assert_eq!(response.dest_callback_addr, None);
+    CLAIM_MODE.remove(&mut deps.storage);
+
+    // invalid packet, nothing would be done
+    let mut invalid_packet_data = packet_data;
+    invalid_packet_data.token_ids = vec![];
+    let response = simulate(deps.as_ref(), invalid_packet_data);
+    assert!(response.error.is_some());
+    assert_eq!(response.error_code, Some(Ics721ErrorCode::InvalidPacket));
+    assert_eq!(response.local_class_id, ClassId::new(dest_class_id));
+    assert_eq!(response.is_redemption, None);
+    assert_eq!(response.nft_contract, None);
+    assert_eq!(response.incoming_proxy, None);
+}
+
 #[test]
 fn test_ibc_packet_receive_invalid_packet_data() {
     // the actual message used here is unimportant. this just
@@ -545,11 +1100,12 @@ fn test_ibc_packet_receive_invalid_packet_data() {
     let res = Ics721Contract::default().ibc_packet_receive(deps.as_mut(), env, packet);
 
     assert!(res.is_ok());
-    let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement));
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement)).unwrap();
 
-    assert!(error
-        .unwrap()
-        .starts_with("Error parsing into type ics721_types::ibc_types::NonFungibleTokenPacketData"))
+    assert!(error.error.starts_with(
+        "Error parsing into type ics721_types::ibc_types::NonFungibleTokenPacketData"
+    ));
+    assert_eq!(error.code, Some(Ics721ErrorCode::InvalidPacket))
 }
 
 #[test]
@@ -624,7 +1180,7 @@ fn test_ibc_packet_receive_missmatched_lengths() {
     let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement));
 
     assert_eq!(
-        error,
+        error.map(|e| e.error),
         Some(
             ContractError::Ics721Error(ics721_types::error::Ics721Error::TokenInfoLenMissmatch {})
                 .to_string()
@@ -659,7 +1215,7 @@ fn test_ibc_packet_receive_missmatched_lengths() {
     let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement));
 
     assert_eq!(
-        error,
+        error.map(|e| e.error),
         Some(
             ContractError::Ics721Error(ics721_types::error::Ics721Error::TokenInfoLenMissmatch {})
                 .to_string()
@@ -721,11 +1277,12 @@ fn test_no_receive_when_paused() {
     let res = Ics721Contract::default().ibc_packet_receive(deps.as_mut(), env, packet);
 
     assert!(res.is_ok());
-    let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement));
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.unwrap().acknowledgement)).unwrap();
 
     assert!(error
-        .unwrap()
-        .starts_with("contract is paused pending governance intervention"))
+        .error
+        .starts_with("contract is paused pending governance intervention"));
+    assert_eq!(error.code, Some(Ics721ErrorCode::Paused))
 }
 
 #[test]
@@ -769,6 +1326,10 @@ fn test_different_memo_ignored() {
                     ack_callback_addr: None,
                     receive_callback_data: Some(dest_callback),
                     receive_callback_addr: None,
+                    version: None,
+                    ack_callback_gas_limit: None,
+                    receive_callback_gas_limit: None,
+                    receive_callback_mode: None,
                 }),
                 extra: None,
             })
@@ -803,6 +1364,967 @@ fn test_different_memo_ignored() {
     };
 }
 
+#[test]
+fn test_callbacks_v2() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    for class_id in [dest_class_id.clone(), ClassId::new("id")] {
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                &mut deps.storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked("nft"),
+                },
+            )
+            .unwrap();
+    }
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let memo = to_json_binary(&Ics721Memo {
+        callbacks: Some(Ics721Callbacks {
+            ack_callback_data: Some(to_json_binary("ack").unwrap()),
+            ack_callback_addr: None,
+            receive_callback_data: Some(to_json_binary("receive").unwrap()),
+            receive_callback_addr: None,
+            version: Some(Ics721CallbackVersion::V2),
+            ack_callback_gas_limit: None,
+            receive_callback_gas_limit: None,
+            receive_callback_mode: None,
+        }),
+    })
+    .unwrap();
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    );
+    data.memo = Some(memo.to_base64());
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+
+    // receive callback holds route of incoming packet
+    let packet = IbcPacketReceiveMsg::new(ibc_packet.clone(), Addr::unchecked(RELAYER_ADDR));
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    let callback = match operands[1].clone() {
+        WasmMsg::Execute { msg, .. } => from_json::<ReceiverExecuteMsg>(msg).unwrap(),
+        _ => panic!("unexpected wasm msg"),
+    };
+    let context = Ics721CallbackContext {
+        local_endpoint: ibc_packet.dest.clone(),
+        counterparty_endpoint: ibc_packet.src.clone(),
+        local_class_id: dest_class_id,
+        sequence: 42,
+        relayer: RELAYER_ADDR.to_string(),
+    };
+    assert_eq!(
+        callback,
+        ReceiverExecuteMsg::Ics721ReceiveCallbackV2(Ics721ReceiveCallbackMsgV2 {
+            nft_contract: "nft".to_string(),
+            original_packet: data.clone(),
+            msg: to_json_binary("receive").unwrap(),
+            context,
+            minted_token_ids: vec![TokenId::new("1")],
+            redeemed_token_ids: vec![],
+        })
+    );
+
+    // ack callback holds route of outgoing packet
+    let ack = IbcPacketAckMsg::new(
+        IbcAcknowledgement::new(ack_success()),
+        ibc_packet.clone(),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_ack(deps.as_mut(), mock_env(), ack)
+        .unwrap();
+    let callback = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            from_json::<ReceiverExecuteMsg>(msg).unwrap()
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(
+        callback,
+        ReceiverExecuteMsg::Ics721AckCallbackV2(Ics721AckCallbackMsgV2 {
+            status: Ics721Status::Success,
+            nft_contract: "nft".to_string(),
+            original_packet: data,
+            msg: to_json_binary("ack").unwrap(),
+            error_code: None,
+            context: Ics721CallbackContext {
+                local_endpoint: ibc_packet.src,
+                counterparty_endpoint: ibc_packet.dest,
+                local_class_id: ClassId::new("id"),
+                sequence: 42,
+                relayer: RELAYER_ADDR.to_string(),
+            },
+        })
+    );
+}
+
+#[test]
+fn test_ibc_callbacks_adr8() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    for class_id in [dest_class_id, ClassId::new("id")] {
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                &mut deps.storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked("nft"),
+                },
+            )
+            .unwrap();
+    }
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "source",
+        "blue",
+        None,
+    );
+    // plain JSON memo, as defined by ADR-008
+    data.memo = Some(
+        r#"{"src_callback":{"address":"source","gas_limit":"50000"},"dest_callback":{"address":"dest","gas_limit":"100000"}}"#
+            .to_string(),
+    );
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+
+    // destination callback is executed last with gas limit
+    let packet = IbcPacketReceiveMsg::new(ibc_packet.clone(), Addr::unchecked(RELAYER_ADDR));
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(operands.len(), 3);
+    assert_eq!(res.messages[0].id, ACK_AND_DO_NOTHING_REPLY_ID);
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+    assert_eq!(
+        operands[2],
+        WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::ExecuteWithGasLimit {
+                msg: WasmMsg::Execute {
+                    contract_addr: "dest".to_string(),
+                    msg: to_json_binary(&ReceiverExecuteMsg::IbcDestinationCallback(
+                        IbcDestinationCallbackMsg {
+                            packet: ibc_packet.clone(),
+                            ack: IbcAcknowledgement::new(ack_success()),
+                        }
+                    ))
+                    .unwrap(),
+                    funds: vec![],
+                },
+                gas_limit: 100000,
+            }))
+            .unwrap(),
+            funds: vec![],
+        }
+    );
+
+    // source callback on ack
+    let acknowledgement = IbcAcknowledgement::new(ack_success());
+    let ack = IbcPacketAckMsg::new(
+        acknowledgement.clone(),
+        ibc_packet.clone(),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_ack(deps.as_mut(), mock_env(), ack)
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "source".to_string(),
+                msg: to_json_binary(&ReceiverExecuteMsg::IbcSourceCallback(
+                    IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg {
+                        acknowledgement,
+                        original_packet: ibc_packet.clone(),
+                        relayer: Addr::unchecked(RELAYER_ADDR),
+                    })
+                ))
+                .unwrap(),
+                funds: vec![],
+            },
+            ACK_CALLBACK_REPLY_ID,
+        )
+        .with_gas_limit(50000)]
+    );
+
+    // source callback on timeout, after NFT has been returned to sender
+    let timeout = IbcPacketTimeoutMsg::new(ibc_packet.clone(), Addr::unchecked(RELAYER_ADDR));
+    let res = Ics721Contract::default()
+        .ibc_packet_timeout(deps.as_mut(), mock_env(), timeout)
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "source".to_string(),
+                msg: to_json_binary(&ReceiverExecuteMsg::IbcSourceCallback(
+                    IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg {
+                        packet: ibc_packet,
+                        relayer: Addr::unchecked(RELAYER_ADDR),
+                    })
+                ))
+                .unwrap(),
+                funds: vec![],
+            },
+            ACK_CALLBACK_REPLY_ID,
+        )
+        .with_gas_limit(50000)
+    );
+
+    // unlike ibc-go, any error of the destination callback (not only out of gas) fails the receive with an error ack
+    let res = Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ACK_AND_DO_NOTHING_REPLY_ID,
+                result: SubMsgResult::Err("dest callback failed".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.data,
+        Some(ack_fail_with_code(
+            "dest callback failed".to_string(),
+            Ics721ErrorCode::ExecutionFailed
+        ))
+    );
+
+    // no source callback, in case callback address isn't the packet sender
+    data.sender = "violet".to_string();
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+    let ack = IbcPacketAckMsg::new(
+        IbcAcknowledgement::new(ack_success()),
+        ibc_packet,
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_ack(deps.as_mut(), mock_env(), ack)
+        .unwrap();
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn test_callback_gas_limits() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    for class_id in [dest_class_id, ClassId::new("id")] {
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                &mut deps.storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked("nft"),
+                },
+            )
+            .unwrap();
+    }
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    CALLBACK_GAS_LIMITS
+        .save(
+            &mut deps.storage,
+            &CallbackGasLimits {
+                default_ack: Some(100000),
+                default_receive: Some(200000),
+                max: Some(300000),
+            },
+        )
+        .unwrap();
+
+    // receive callback gas limit is capped by max, ack callback uses default
+    let memo = to_json_binary(&Ics721Memo {
+        callbacks: Some(Ics721Callbacks {
+            ack_callback_data: Some(to_json_binary("ack").unwrap()),
+            ack_callback_addr: None,
+            receive_callback_data: Some(to_json_binary("receive").unwrap()),
+            receive_callback_addr: None,
+            version: None,
+            ack_callback_gas_limit: None,
+            receive_callback_gas_limit: Some(1000000),
+            receive_callback_mode: None,
+        }),
+    })
+    .unwrap();
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    );
+    data.memo = Some(memo.to_base64());
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+
+    let packet = IbcPacketReceiveMsg::new(ibc_packet.clone(), Addr::unchecked(RELAYER_ADDR));
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    let gas_limit = match operands[1].clone() {
+        WasmMsg::Execute { msg, .. } => match from_json::<ExecuteMsg>(msg).unwrap() {
+            ExecuteMsg::Callback(CallbackMsg::ExecuteWithGasLimit { gas_limit, .. }) => gas_limit,
+            _ => panic!("unexpected execute msg"),
+        },
+        _ => panic!("unexpected wasm msg"),
+    };
+    assert_eq!(gas_limit, 300000);
+
+    let ack = IbcPacketAckMsg::new(
+        IbcAcknowledgement::new(ack_success()),
+        ibc_packet,
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_ack(deps.as_mut(), mock_env(), ack)
+        .unwrap();
+    assert_eq!(res.messages[0].gas_limit, Some(100000));
+    assert_eq!(res.messages[0].id, ACK_CALLBACK_REPLY_ID);
+
+    // out of gas is recorded by reply
+    let res = Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ACK_CALLBACK_REPLY_ID,
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("ics721_ack_callback_failed")
+            .add_attribute("id", "0")
+            .add_attribute("error", "out of gas")]
+    );
+}
+
+#[test]
+fn test_retry_failed_ack_callback() {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => SystemResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADDR1.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &ClassId::new("id"),
+            &ClassIdInfo {
+                class_id: ClassId::new("id"),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+
+    let memo = to_json_binary(&Ics721Memo {
+        callbacks: Some(Ics721Callbacks {
+            ack_callback_data: Some(to_json_binary("ack").unwrap()),
+            ack_callback_addr: None,
+            receive_callback_data: None,
+            receive_callback_addr: None,
+            version: None,
+            ack_callback_gas_limit: None,
+            receive_callback_gas_limit: None,
+            receive_callback_mode: None,
+        }),
+    })
+    .unwrap();
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    );
+    data.memo = Some(memo.to_base64());
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+    let ack = IbcPacketAckMsg::new(
+        IbcAcknowledgement::new(ack_success()),
+        ibc_packet.clone(),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_ack(deps.as_mut(), mock_env(), ack)
+        .unwrap();
+    let callback_msg = match res.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => msg,
+        _ => panic!("unexpected cosmos msg"),
+    };
+
+    // failed callback is stored on reply
+    Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ACK_CALLBACK_REPLY_ID,
+                result: SubMsgResult::Err("error".to_string()),
+            },
+        )
+        .unwrap();
+    let failed: Vec<(u64, FailedAckCallback)> = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::FailedAckCallbacks {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        failed,
+        vec![(
+            0,
+            FailedAckCallback {
+                callback: AckCallback {
+                    contract: Addr::unchecked("violet"),
+                    msg: callback_msg.clone(),
+                    gas_limit: None,
+                    packet: ibc_packet,
+                    status: Ics721Status::Success,
+                },
+                error: "error".to_string(),
+            }
+        )]
+    );
+
+    // only callable by called back contract or admin
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            ExecuteMsg::RetryAckCallback { id: 0 },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("violet", &[]),
+            ExecuteMsg::RetryAckCallback { id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "violet".to_string(),
+            msg: callback_msg,
+            funds: vec![],
+        })]
+    );
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADDR1, &[]),
+            ExecuteMsg::RetryAckCallback { id: 0 },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::NoFailedAckCallback(0));
+}
+
+#[test]
+fn test_best_effort_receive_callback() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &dest_class_id,
+            &ClassIdInfo {
+                class_id: dest_class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    let memo = to_json_binary(&Ics721Memo {
+        callbacks: Some(Ics721Callbacks {
+            ack_callback_data: None,
+            ack_callback_addr: None,
+            receive_callback_data: Some(to_json_binary("receive").unwrap()),
+            receive_callback_addr: None,
+            version: None,
+            ack_callback_gas_limit: None,
+            receive_callback_gas_limit: None,
+            receive_callback_mode: Some(ReceiveCallbackMode::BestEffort),
+        }),
+    })
+    .unwrap();
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "blue",
+        None,
+    );
+    data.memo = Some(memo.to_base64());
+    let packet = IbcPacketReceiveMsg::new(
+        mock_packet(to_json_binary(&data).unwrap()),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    // callback isn't part of receiving NFTs
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(operands.len(), 2);
+    let callback_msg = to_json_binary(&ReceiverExecuteMsg::Ics721ReceiveCallback(
+        Ics721ReceiveCallbackMsg {
+            nft_contract: "nft".to_string(),
+            original_packet: data,
+            msg: to_json_binary("receive").unwrap(),
+        },
+    ))
+    .unwrap();
+    let callback = ReceiveCallback {
+        contract: Addr::unchecked("blue"),
+        msg: callback_msg.clone(),
+        gas_limit: None,
+    };
+    assert_eq!(
+        PENDING_RECEIVE_CALLBACK.load(&deps.storage).unwrap(),
+        callback
+    );
+
+    let reply = |deps: DepsMut, id: u64, result: SubMsgResult| {
+        Ics721Contract::default()
+            .reply(deps, mock_env(), Reply { id, result })
+            .unwrap()
+    };
+    let success = || {
+        SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        })
+    };
+
+    // once NFTs have been received, callback is dispatched as submessage replying always
+    let res = reply(deps.as_mut(), ACK_AND_DO_NOTHING_REPLY_ID, success());
+    assert_eq!(res.data, Some(ack_success()));
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "blue".to_string(),
+                msg: callback_msg.clone(),
+                funds: vec![],
+            },
+            RECEIVE_CALLBACK_REPLY_ID,
+        )]
+    );
+
+    // failure is recorded
+    let res = reply(
+        deps.as_mut(),
+        RECEIVE_CALLBACK_REPLY_ID,
+        SubMsgResult::Err("error".to_string()),
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("ics721_receive_callback_failed")
+            .add_attribute("id", "0")
+            .add_attribute("contract", "blue")
+            .add_attribute("error", "error")]
+    );
+    assert_eq!(
+        PENDING_RECEIVE_CALLBACK.may_load(&deps.storage).unwrap(),
+        None
+    );
+    let failed: Vec<(u64, FailedReceiveCallback)> = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::FailedReceiveCallbacks {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        failed,
+        vec![(
+            0,
+            FailedReceiveCallback {
+                callback: callback.clone(),
+                error: "error".to_string(),
+            }
+        )]
+    );
+
+    // successful callback isn't kept
+    PENDING_RECEIVE_CALLBACK
+        .save(&mut deps.storage, &callback)
+        .unwrap();
+    let res = reply(deps.as_mut(), RECEIVE_CALLBACK_REPLY_ID, success());
+    assert!(res.events.is_empty());
+    assert_eq!(
+        PENDING_RECEIVE_CALLBACK.may_load(&deps.storage).unwrap(),
+        None
+    );
+
+    // in case receiving NFTs fails, callback isn't dispatched
+    PENDING_RECEIVE_CALLBACK
+        .save(&mut deps.storage, &callback)
+        .unwrap();
+    let res = reply(
+        deps.as_mut(),
+        ACK_AND_DO_NOTHING_REPLY_ID,
+        SubMsgResult::Err("error".to_string()),
+    );
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        PENDING_RECEIVE_CALLBACK.may_load(&deps.storage).unwrap(),
+        None
+    );
+
+    // without best-effort callback, nothing is dispatched
+    let res = reply(deps.as_mut(), ACK_AND_DO_NOTHING_REPLY_ID, success());
+    assert_eq!(res.data, Some(ack_success()));
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn test_ibc_packet_receive_wasm_hook() {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let dest_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &dest_class_id,
+            &ClassIdInfo {
+                class_id: dest_class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    // intermediate sender is derived with prefix of contract address
+    let mut env = mock_env();
+    env.contract.address =
+        Addr::unchecked("cosmos1enee9aw9d9cju6cdvv3zlh6h4ldwz5q3wh32v36tfyxmsy3rtnjspnstv8");
+
+    let receive = |deps: DepsMut, receiver: &str, memo: &str| {
+        let mut data = build_ics_packet(
+            "id",
+            None,
+            None,
+            vec!["1"],
+            None,
+            None,
+            "violet",
+            receiver,
+            None,
+        );
+        data.memo = Some(memo.to_string());
+        let packet = IbcPacketReceiveMsg::new(
+            mock_packet(to_json_binary(&data).unwrap()),
+            Addr::unchecked(RELAYER_ADDR),
+        );
+        Ics721Contract::default()
+            .ibc_packet_receive(deps, env.clone(), packet)
+            .unwrap()
+    };
+    let memo = r#"{"wasm":{"contract":"hook_contract","msg":{"do_something":{"value":1}}}}"#;
+
+    // contract is executed after NFT has been received
+    let res = receive(deps.as_mut(), "hook_contract", memo);
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(operands.len(), 3);
+    assert_eq!(
+        operands[2],
+        WasmMsg::Execute {
+            contract_addr: "hook_contract".to_string(),
+            msg: Binary::from(br#"{"do_something":{"value":1}}"#.to_vec()),
+            funds: vec![],
+        }
+    );
+    assert!(res.attributes.contains(&attr(
+        "wasm_hook_sender",
+        "cosmos19rt0lm2uyrhccx8sj437mjfgpvaqws8vnlg0c5rtt0t0mcew6xhslh0jkk"
+    )));
+
+    // as in ibc-hooks, contract must be receiver
+    let res = receive(deps.as_mut(), "blue", memo);
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+    assert_eq!(
+        error.error,
+        ContractError::InvalidWasmHook("contract hook_contract is not the receiver".to_string())
+            .to_string()
+    );
+
+    // contracts trusting ICS721 can't be called
+    let res = receive(
+        deps.as_mut(),
+        "nft",
+        r#"{"wasm":{"contract":"nft","msg":{"burn":{"token_id":"1"}}}}"#,
+    );
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+    assert_eq!(
+        error.error,
+        ContractError::InvalidWasmHook("contract nft can't be called".to_string()).to_string()
+    );
+
+    // callbacks sent by ICS721 can't be forged
+    let forged_callback = to_json_binary(&ReceiverExecuteMsg::Ics721ReceiveCallback(
+        Ics721ReceiveCallbackMsg {
+            nft_contract: "nft".to_string(),
+            original_packet: build_ics_packet(
+                "id",
+                None,
+                None,
+                vec!["1"],
+                None,
+                None,
+                "violet",
+                "hook_contract",
+                None,
+            ),
+            msg: to_json_binary("receive").unwrap(),
+        },
+    ))
+    .unwrap();
+    let res = receive(
+        deps.as_mut(),
+        "hook_contract",
+        &format!(
+            r#"{{"wasm":{{"contract":"hook_contract","msg":{}}}}}"#,
+            String::from_utf8(forged_callback.to_vec()).unwrap()
+        ),
+    );
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+    assert_eq!(
+        error.error,
+        ContractError::InvalidWasmHook("msg ics721_receive_callback is reserved".to_string())
+            .to_string()
+    );
+    let res = receive(
+        deps.as_mut(),
+        "hook_contract",
+        r#"{"wasm":{"contract":"hook_contract","msg":{"ibc_destination_callback":{}}}}"#,
+    );
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+    assert_eq!(
+        error.error,
+        ContractError::InvalidWasmHook("msg ibc_destination_callback is reserved".to_string())
+            .to_string()
+    );
+
+    // in claim mode, receiver doesn't own the voucher yet and memo is skipped
+    CLAIM_MODE
+        .save(&mut deps.storage, &ClaimMode::Enabled { expiry: 100 })
+        .unwrap();
+    let res = receive(deps.as_mut(), "hook_contract", memo);
+    let operands = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(operands.len(), 2);
+    assert!(!res
+        .attributes
+        .iter()
+        .any(|attribute| attribute.key == "wasm_hook_sender"));
+}
+
+#[test]
+fn test_ibc_packet_receive_wasm_hook_unlinked_voucher() {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => SystemResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADDR1.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    for (class_id, nft_contract) in [("id", "nft"), ("old", "old_voucher")] {
+        let class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, class_id));
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                &mut deps.storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked(nft_contract),
+                },
+            )
+            .unwrap();
+    }
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+
+    // voucher contract is unlinked, but ICS721 is still its minter
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADDR1, &[]),
+            ExecuteMsg::RemoveClassMapping {
+                class_id: format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "old"),
+            },
+        )
+        .unwrap();
+
+    // so it can't be called by a `wasm` memo
+    let mut data = build_ics_packet(
+        "id",
+        None,
+        None,
+        vec!["1"],
+        None,
+        None,
+        "violet",
+        "old_voucher",
+        None,
+    );
+    data.memo = Some(
+        r#"{"wasm":{"contract":"old_voucher","msg":{"mint":{"token_id":"2","owner":"violet"}}}}"#
+            .to_string(),
+    );
+    let packet = IbcPacketReceiveMsg::new(
+        mock_packet(to_json_binary(&data).unwrap()),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet)
+        .unwrap();
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+    assert_eq!(
+        error.error,
+        ContractError::InvalidWasmHook("contract old_voucher can't be called".to_string())
+            .to_string()
+    );
+}
+
 #[test]
 fn test_ibc_packet_not_json_memo() {
     let data = NonFungibleTokenPacketData {
@@ -2,10 +2,10 @@ use anyhow::Result;
 use bech32::{decode, encode, Hrp};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json, instantiate2_address, to_json_binary, Addr, Api, Binary, CanonicalAddr, Decimal,
-    Deps, DepsMut, Empty, Env, GovMsg, IbcTimeout, IbcTimeoutBlock, MemoryStorage, MessageInfo,
-    RecoverPubkeyError, Reply, Response, StdError, StdResult, Storage, Timestamp,
-    VerificationError, WasmMsg,
+    from_json, instantiate2_address, testing::MockApi, to_json_binary, Addr, Api, Binary,
+    CanonicalAddr, Decimal, Deps, DepsMut, Empty, Env, GovMsg, HexBinary, IbcTimeout,
+    IbcTimeoutBlock, MemoryStorage, MessageInfo, RecoverPubkeyError, Reply, Response, StdError,
+    StdResult, Storage, Timestamp, VerificationError, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw721::{
@@ -21,6 +21,7 @@ use cw_multi_test::{
     AddressGenerator, App, AppBuilder, BankKeeper, Contract, ContractWrapper, DistributionKeeper,
     Executor, FailingModule, IbcAcceptingModule, Router, StakeKeeper, StargateFailing, WasmKeeper,
 };
+use cw_ownable::Ownership;
 use cw_pause_once::PauseError;
 use sha2::{digest::Update, Digest, Sha256};
 
@@ -212,11 +213,11 @@ impl Api for MockApiBech32 {
 
     fn secp256k1_verify(
         &self,
-        _message_hash: &[u8],
-        _signature: &[u8],
-        _public_key: &[u8],
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
     ) -> Result<bool, VerificationError> {
-        unimplemented!()
+        MockApi::default().secp256k1_verify(message_hash, signature, public_key)
     }
 
     fn secp256k1_recover_pubkey(
@@ -362,6 +363,12 @@ impl Test {
                     pauser: admin.clone(),
                     cw721_admin: admin.clone(),
                     contract_addr_length: None,
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 },
                 &[],
                 "ics721-base",
@@ -690,6 +697,7 @@ fn test_do_instantiate_and_mint_weird_data() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -899,6 +907,7 @@ fn test_do_instantiate_and_mint() {
                                     symbol: "protocol".to_string(),
                                     extension: collection_extension.clone(),
                                     num_tokens: Some(1),
+                                    destination_profile: None,
                                 })
                                 .unwrap(),
                             ),
@@ -1213,6 +1222,7 @@ fn test_do_instantiate_and_mint() {
                                             .addr_make(COLLECTION_OWNER_TARGET_CHAIN)
                                             .to_string(),
                                     ),
+                                    destination_profile: None,
                                 })
                                 .unwrap(),
                             ),
@@ -1663,6 +1673,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -1785,6 +1796,7 @@ fn test_do_instantiate_and_mint_permissions() {
                                     start_trading_time: Some(Timestamp::from_seconds(42)),
                                 }),
                                 num_tokens: Some(1),
+                                destination_profile: None,
                             })
                             .unwrap(),
                         ),
@@ -2037,6 +2049,7 @@ fn test_receive_nft() {
                 start_trading_time: Some(Timestamp::from_seconds(42)),
             }),
             num_tokens: Some(1),
+            destination_profile: None,
         })
         .unwrap();
         let class_data_attribute = event
@@ -2114,6 +2127,7 @@ fn test_receive_nft() {
             symbol: "symbol".to_string(),
             extension: None,
             num_tokens: Some(1),
+            destination_profile: None,
         })
         .unwrap();
         assert_eq!(
@@ -2369,6 +2383,12 @@ fn test_pause() {
                     cw721_base_code_id: None,
                     cw721_admin: None,
                     contract_addr_length: None,
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 })
                 .unwrap(),
             }
@@ -2425,6 +2445,12 @@ fn test_migration() {
                     cw721_base_code_id: Some(12345678),
                     cw721_admin: Some(admin.to_string()),
                     contract_addr_length: Some(20),
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: None,
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
                 })
                 .unwrap(),
             }
@@ -2449,6 +2475,12 @@ fn test_migration() {
         cw721_base_code_id: None,
         cw721_admin: Some("".to_string()),
         contract_addr_length: None,
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
     };
     test.app
         .execute(
@@ -2471,3 +2503,242 @@ fn test_migration() {
     assert_eq!(test.query_cw721_admin(), None);
     assert_eq!(test.query_contract_addr_length(), None);
 }
+
+#[test]
+fn test_claim_collection_ownership_of_voucher() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    // signature below is bound to ICS721 address and chain ID
+    assert_eq!(
+        test.ics721.to_string(),
+        "stars1uzyszmsnca8euusre35wuqj4el3hyj8jty84kwln7du5stwwxynswqjyza"
+    );
+    let class_id = "wasm.address1/channel-1/address2";
+    let source_owner = "juno1tdakrlgp6hd4c0kswxdczdkgqsvlssj2tzsxzq";
+    let create_vouchers = |test: &mut Test, name: &str, token_id: &str| {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: Class {
+                            id: ClassId::new(class_id),
+                            uri: None,
+                            data: Some(
+                                to_json_binary(&CollectionData {
+                                    owner: Some(source_owner.to_string()),
+                                    contract_info: Default::default(),
+                                    name: name.to_string(),
+                                    symbol: "symbol".to_string(),
+                                    extension: None,
+                                    num_tokens: Some(1),
+                                    destination_profile: None,
+                                })
+                                .unwrap(),
+                            ),
+                        },
+                        tokens: vec![Token {
+                            id: TokenId::new(token_id),
+                            uri: None,
+                            data: None,
+                        }],
+                    },
+                }),
+                &[],
+            )
+            .unwrap()
+    };
+    create_vouchers(&mut test, "name", "1");
+    let nft_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::NftContract {
+                class_id: class_id.to_string(),
+            },
+        )
+        .unwrap();
+    // ICS721 is creator of voucher contract
+    let creator: Ownership<Addr> = test
+        .app
+        .wrap()
+        .query_wasm_smart(nft_contract.clone(), &Cw721QueryMsg::GetCreatorOwnership {})
+        .unwrap();
+    assert_eq!(creator.owner, Some(test.ics721.clone()));
+
+    // ADR-036 signature of claim message by source owner
+    let claimant = test.app.api().addr_make("claimant");
+    test.app
+        .execute_contract(
+            claimant.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ClaimCollectionOwnership {
+                class_id: class_id.to_string(),
+                pubkey: HexBinary::from_hex(
+                    "02a8cc7af8b9ac1e16ac05d8620d766d44b90b75aa193fa26640f003b6547e0aa8",
+                )
+                .unwrap()
+                .into(),
+                signature: HexBinary::from_hex("97a1d3b3be358699bceef239ac694327779040e648ec153dcf717de2bc16be0f0e6a68f1e16d1e41f3c3be545f884e77ddf2c03158819545133e0fdff6c6fece").unwrap().into(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // claimant needs to accept ownership
+    let creator: Ownership<Addr> = test
+        .app
+        .wrap()
+        .query_wasm_smart(nft_contract.clone(), &Cw721QueryMsg::GetCreatorOwnership {})
+        .unwrap();
+    assert_eq!(creator.owner, Some(test.ics721.clone()));
+    assert_eq!(creator.pending_owner, Some(claimant.clone()));
+    test.app
+        .execute_contract(
+            claimant.clone(),
+            nft_contract.clone(),
+            &cw721_metadata_onchain::msg::ExecuteMsg::UpdateCreatorOwnership(
+                cw_ownable::Action::AcceptOwnership,
+            ),
+            &[],
+        )
+        .unwrap();
+
+    // once ICS721 isn't creator anymore, updating collection info fails without failing the transfer
+    test.app
+        .execute(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            WasmMsg::Migrate {
+                contract_addr: test.ics721.to_string(),
+                new_code_id: test.ics721_id,
+                msg: to_json_binary(&MigrateMsg::WithUpdate {
+                    pauser: None,
+                    incoming_proxy: None,
+                    outgoing_proxy: None,
+                    cw721_base_code_id: None,
+                    cw721_admin: None,
+                    contract_addr_length: None,
+                    class_data_refresh_policy: None,
+                    update_voucher_collection_info: Some(true),
+                    token_metadata_policy: None,
+                    voucher_code_id_rules: None,
+                    claim_mode: None,
+                    callback_gas_limits: None,
+                })
+                .unwrap(),
+            }
+            .into(),
+        )
+        .unwrap();
+    let res = create_vouchers(&mut test, "new name", "2");
+    let event = res
+        .events
+        .iter()
+        .find(|event| event.ty == "wasm-ics721_update_collection_info_failed")
+        .unwrap();
+    assert!(event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "nft_contract" && attr.value == nft_contract.as_str()));
+    let collection_info: CollectionInfoAndExtensionResponse<DefaultOptionalCollectionExtension> =
+        test.app
+            .wrap()
+            .query_wasm_smart(
+                nft_contract.clone(),
+                &Cw721QueryMsg::GetCollectionInfoAndExtension {},
+            )
+            .unwrap();
+    assert_eq!(collection_info.name, "name");
+    let creator: Ownership<Addr> = test
+        .app
+        .wrap()
+        .query_wasm_smart(nft_contract, &Cw721QueryMsg::GetCreatorOwnership {})
+        .unwrap();
+    assert_eq!(creator.owner, Some(claimant));
+}
+
+#[test]
+fn test_receive_after_removing_class_mapping() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let class_id = "wasm.address1/channel-1/address2";
+    let create_vouchers = |test: &mut Test, token_id: &str| {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: Class {
+                            id: ClassId::new(class_id),
+                            uri: None,
+                            data: None,
+                        },
+                        tokens: vec![Token {
+                            id: TokenId::new(token_id),
+                            uri: None,
+                            data: None,
+                        }],
+                    },
+                }),
+                &[],
+            )
+            .unwrap();
+        test.app
+            .wrap()
+            .query_wasm_smart::<Addr>(
+                test.ics721.clone(),
+                &QueryMsg::NftContract {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let predicted_nft_contract = |test: &Test| {
+        test.app
+            .wrap()
+            .query_wasm_smart::<Addr>(
+                test.ics721.clone(),
+                &QueryMsg::GetInstantiate2NftContract {
+                    class_id: class_id.to_string(),
+                    cw721_code_id: None,
+                },
+            )
+            .unwrap()
+    };
+    let nft_contract = create_vouchers(&mut test, "1");
+
+    // no outstanding vouchers, since vouchers haven't been transferred via a channel
+    test.app
+        .execute_contract(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            test.ics721.clone(),
+            &ExecuteMsg::RemoveClassMapping {
+                class_id: class_id.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // next receive instantiates a new voucher contract, using a new salt
+    let new_predicted_nft_contract = predicted_nft_contract(&test);
+    assert_ne!(new_predicted_nft_contract, nft_contract);
+    let new_nft_contract = create_vouchers(&mut test, "2");
+    assert_eq!(new_nft_contract, new_predicted_nft_contract);
+}
@@ -1,14 +1,18 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json,
+    attr, from_json,
     testing::{mock_dependencies, mock_env, mock_info, MockQuerier, MOCK_CONTRACT_ADDR},
-    to_json_binary, Addr, ContractResult, CosmosMsg, Decimal, DepsMut, Empty, IbcMsg, IbcTimeout,
-    Order, QuerierResult, Response, StdResult, SubMsg, Timestamp, WasmMsg, WasmQuery,
+    to_json_binary, Addr, Binary, ContractResult, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env,
+    HexBinary, IbcEndpoint, IbcMsg, IbcTimeout, Order, QuerierResult, Reply, Response, StdError,
+    StdResult, SubMsg, SubMsgResponse, SubMsgResult, Timestamp, WasmMsg, WasmQuery,
 };
 use cw721::{
     msg::{
-        AllNftInfoResponse, CollectionInfoAndExtensionResponse, NftExtensionMsg, NftInfoResponse,
-        NumTokensResponse,
+        AllNftInfoResponse, CollectionExtensionMsg, CollectionInfoAndExtensionResponse,
+        CollectionInfoMsg, NftExtensionMsg, NftInfoResponse, NumTokensResponse,
+        RoyaltyInfoResponse,
     },
     CollectionExtension, DefaultOptionalCollectionExtension, DefaultOptionalNftExtension,
     NftExtension, RoyaltyInfo,
@@ -21,22 +25,38 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     execute::Ics721Execute,
-    ibc::{Ics721Ibc, INSTANTIATE_INCOMING_PROXY_REPLY_ID, INSTANTIATE_OUTGOING_PROXY_REPLY_ID},
-    msg::{InstantiateMsg, MigrateMsg},
+    ibc::{
+        Ics721Ibc, INSTANTIATE_INCOMING_PROXY_REPLY_ID, INSTANTIATE_OUTGOING_PROXY_REPLY_ID,
+        MIGRATE_VOUCHER_REPLY_ID, UPDATE_COLLECTION_INFO_REPLY_ID,
+    },
+    interop::encode_class_data_for_sdk_module,
+    msg::{
+        CallbackMsg, DecodedClass, DecodedClassData, DecodedToken, DecodedTokenData, ExecuteMsg,
+        InstantiateMsg, MigrateMsg, QueryMsg as Ics721QueryMsg,
+    },
     query::{
         query_class_id_for_nft_contract, query_nft_contract_for_class_id, query_nft_contracts,
         Ics721Query,
     },
     state::{
-        ClassIdInfo, CollectionData, CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS,
-        CONTRACT_ADDR_LENGTH, CW721_ADMIN, CW721_CODE_ID, IBC_RECEIVE_TOKEN_METADATA,
-        INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO,
+        ChannelInfo, ClaimMode, ClaimableVoucher, ClassDataRefreshPolicy, ClassIdInfo,
+        CollectionData, DestinationProfile, RoyaltyPayeeClaim, TokenMetadataPolicy,
+        VoucherCodeIdRule, VoucherInitMsg, VoucherMatcher, VoucherMigrationStatus, CHANNEL_INFOS,
+        CLASS_DATA_REFRESH_POLICY, CLASS_DATA_UPDATED_AT, CLASS_ID_AND_NFT_CONTRACT_INFO,
+        CLASS_ID_TO_CLASS, CLASS_MAPPINGS, CLASS_METADATA_TRANSLATOR, CONTRACT_ADDR_LENGTH,
+        CW721_ADMIN, CW721_CODE_ID, IBC_RECEIVE_TOKEN_METADATA, INCOMING_CLASS_TOKEN_TO_CHANNEL,
+        INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO, ROYALTY_PAYEES,
+        ROYALTY_PAYEE_CLAIMS, TOKEN_METADATA_POLICY, UPDATE_VOUCHER_COLLECTION_INFO,
+        VOUCHER_MIGRATIONS,
     },
-    utils::get_collection_data,
+    token_types::VoucherCreation,
+    translators::MetadataTranslator,
+    utils::{get_collection_data, get_voucher_code_id},
+    ContractError,
 };
 use ics721_types::{
     ibc_types::{IbcOutgoingMsg, NonFungibleTokenPacketData},
-    token_types::{ClassId, Token, TokenId},
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
 };
 
 const NFT_CONTRACT_1: &str = "nft1";
@@ -446,6 +466,7 @@ fn test_receive_nft() {
                         start_trading_time: Some(Timestamp::from_seconds(42)),
                     }),
                     num_tokens: Some(1),
+                    destination_profile: None,
                 };
                 assert_eq!(class_data, expected_class_data);
                 assert_eq!(
@@ -561,6 +582,7 @@ fn test_receive_nft() {
                         start_trading_time: Some(Timestamp::from_seconds(42)),
                     }),
                     num_tokens: Some(1),
+                    destination_profile: None,
                 };
                 assert_eq!(class_data, expected_class_data);
                 assert_eq!(
@@ -652,6 +674,7 @@ fn test_receive_nft() {
                     symbol: "symbol".to_string(),
                     extension: None,
                     num_tokens: Some(1),
+                    destination_profile: None,
                 };
                 assert_eq!(class_data, expected_class_data);
                 assert_eq!(
@@ -737,6 +760,7 @@ fn test_receive_nft() {
                     symbol: "symbol".to_string(),
                     extension: None,
                     num_tokens: Some(1),
+                    destination_profile: None,
                 };
                 assert_eq!(class_data, expected_class_data);
                 assert_eq!(
@@ -822,6 +846,7 @@ fn test_receive_nft() {
                     symbol: "symbol".to_string(),
                     extension: None,
                     num_tokens: Some(1),
+                    destination_profile: None,
                 };
                 assert_eq!(class_data, expected_class_data);
                 assert_eq!(
@@ -1076,6 +1101,85 @@ fn test_callback_mint() {
             _ => panic!("unexpected message type"),
         }
     }
+    // test case: unknown token data is translated by translator set for class
+    {
+        let mut querier = MockQuerier::default();
+        querier.update_wasm(mock_querier);
+
+        let mut deps = mock_dependencies();
+        deps.querier = querier;
+        let env = mock_env();
+        let class_id_info = ClassIdInfo {
+            class_id: ClassId::new(NFT_CONTRACT_1),
+            address: Addr::unchecked(NFT_CONTRACT_1),
+        };
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &ClassId::new(NFT_CONTRACT_1),
+                &class_id_info,
+            )
+            .unwrap();
+
+        // only admin can set translator
+        let err = Ics721Contract::default()
+            .execute(
+                deps.as_mut(),
+                env,
+                mock_info(OWNER_ADDR, &[]),
+                ExecuteMsg::SetMetadataTranslator {
+                    class_id: NFT_CONTRACT_1.to_string(),
+                    translator: Some(MetadataTranslator::FlatJson),
+                },
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        CLASS_METADATA_TRANSLATOR
+            .save(
+                deps.as_mut().storage,
+                ClassId::new(NFT_CONTRACT_1),
+                &MetadataTranslator::FlatJson,
+            )
+            .unwrap();
+
+        let token = Token {
+            id: TokenId::new("1"),
+            uri: None,
+            data: Some(
+                to_json_binary(&UnknownMetadata {
+                    unknown: "unknown".to_string(),
+                })
+                .unwrap(),
+            ),
+        };
+        let res: cosmwasm_std::Response<_> = Ics721Contract::default()
+            .callback_mint(
+                deps.as_mut(),
+                ClassId::new(NFT_CONTRACT_1),
+                vec![token],
+                "receiver".to_string(),
+            )
+            .unwrap();
+        match res.messages[0].msg.clone() {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_json(msg).unwrap() {
+                cw721_metadata_onchain::msg::ExecuteMsg::Mint { extension, .. } => {
+                    assert_eq!(
+                        extension,
+                        Some(NftExtensionMsg {
+                            attributes: Some(vec![cw721::Trait {
+                                display_type: None,
+                                trait_type: "unknown".to_string(),
+                                value: "unknown".to_string(),
+                            }]),
+                            ..Default::default()
+                        })
+                    );
+                }
+                _ => panic!("unexpected message type"),
+            },
+            _ => panic!("unexpected message type"),
+        }
+    }
 }
 
 #[test]
@@ -1137,10 +1241,265 @@ fn test_receive_sets_uri() {
                     start_trading_time: Some(Timestamp::from_seconds(42)),
                 }),
                 num_tokens: Some(1),
+                destination_profile: None,
+            })
+            .unwrap()
+        ),
+    );
+}
+
+#[test]
+fn test_refresh_class_data() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let mut env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // returns class data sent in packet
+    let sent_class_data =
+        |deps: DepsMut, env: Env| send_nft(deps, env, "callum", "channel-1").class_data;
+    // class data is outdated, e.g. collection has been renamed in the meantime
+    fn outdate_class_data(deps: DepsMut) {
+        CLASS_ID_TO_CLASS
+            .update(deps.storage, ClassId::new(NFT_CONTRACT_1), |class| {
+                Ok::<_, StdError>(Class {
+                    data: None,
+                    ..class.unwrap()
+                })
             })
+            .unwrap();
+    }
+
+    // initial send creates class data
+    let class_data = sent_class_data(deps.as_mut(), env.clone());
+    assert!(class_data.is_some());
+    assert_eq!(
+        CLASS_DATA_UPDATED_AT
+            .load(&deps.storage, ClassId::new(NFT_CONTRACT_1))
+            .unwrap(),
+        env.block.time
+    );
+
+    // default policy never refreshes
+    outdate_class_data(deps.as_mut());
+    assert_eq!(sent_class_data(deps.as_mut(), env.clone()), None);
+
+    // refresh on each send
+    CLASS_DATA_REFRESH_POLICY
+        .save(deps.as_mut().storage, &ClassDataRefreshPolicy::Always)
+        .unwrap();
+    assert_eq!(sent_class_data(deps.as_mut(), env.clone()), class_data);
+
+    // refresh after 100 seconds
+    CLASS_DATA_REFRESH_POLICY
+        .save(
+            deps.as_mut().storage,
+            &ClassDataRefreshPolicy::AfterSeconds(100),
+        )
+        .unwrap();
+    outdate_class_data(deps.as_mut());
+    env.block.time = env.block.time.plus_seconds(99);
+    assert_eq!(sent_class_data(deps.as_mut(), env.clone()), None);
+    env.block.time = env.block.time.plus_seconds(1);
+    assert_eq!(sent_class_data(deps.as_mut(), env.clone()), class_data);
+
+    // explicit refresh only by collection creator or admin
+    CLASS_DATA_REFRESH_POLICY
+        .save(deps.as_mut().storage, &ClassDataRefreshPolicy::Never)
+        .unwrap();
+    outdate_class_data(deps.as_mut());
+    let refresh_msg = ExecuteMsg::RefreshClassData {
+        collection: NFT_CONTRACT_1.to_string(),
+    };
+    let err = Ics721Contract {}
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("unauthorized", &[]),
+            refresh_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    Ics721Contract {}
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            refresh_msg,
+        )
+        .unwrap();
+    assert_eq!(
+        CLASS_ID_TO_CLASS
+            .load(&deps.storage, ClassId::new(NFT_CONTRACT_1))
             .unwrap()
+            .data,
+        class_data
+    );
+
+    // vouchers keep class data from source chain
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &ClassId::new(CLASS_ID_2),
+            &ClassIdInfo {
+                class_id: ClassId::new(CLASS_ID_2),
+                address: Addr::unchecked(NFT_CONTRACT_2),
+            },
+        )
+        .unwrap();
+    let err = Ics721Contract {}
+        .execute(
+            deps.as_mut(),
+            env,
+            mock_info(OWNER_ADDR, &[]),
+            ExecuteMsg::RefreshClassData {
+                collection: NFT_CONTRACT_2.to_string(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotHomeCollection(CLASS_ID_2.to_string())
+    );
+}
+
+#[test]
+fn test_query_decoded_metadata() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+
+    let collection_data = CollectionData {
+        owner: Some(OWNER_ADDR.to_string()),
+        contract_info: None,
+        num_tokens: Some(1),
+        name: "name".to_string(),
+        symbol: "symbol".to_string(),
+        extension: None,
+        destination_profile: None,
+    };
+    let nft_extension = NftExtension {
+        image: Some("https://ark.pass/image.png".to_string()),
+        ..Default::default()
+    };
+    let unknown_data = to_json_binary(&UnknownMetadata {
+        unknown: "unknown".to_string(),
+    })
+    .unwrap();
+    for (class_id, nft_contract, class_data) in [
+        (
+            CLASS_ID_1,
+            NFT_CONTRACT_1,
+            to_json_binary(&collection_data).unwrap(),
         ),
+        (CLASS_ID_2, NFT_CONTRACT_2, unknown_data.clone()),
+    ] {
+        let class_id = ClassId::new(class_id);
+        CLASS_ID_TO_CLASS
+            .save(
+                deps.as_mut().storage,
+                class_id.clone(),
+                &Class {
+                    id: class_id.clone(),
+                    uri: None,
+                    data: Some(class_data),
+                },
+            )
+            .unwrap();
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked(nft_contract),
+                },
+            )
+            .unwrap();
+    }
+    IBC_RECEIVE_TOKEN_METADATA
+        .save(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_1), TokenId::new("1")),
+            &Some(to_json_binary(&nft_extension).unwrap()),
+        )
+        .unwrap();
+    IBC_RECEIVE_TOKEN_METADATA
+        .save(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_2), TokenId::new("1")),
+            &Some(unknown_data.clone()),
+        )
+        .unwrap();
+
+    let query_class = |class_id: &str| -> Option<DecodedClass> {
+        from_json(
+            Ics721Contract {}
+                .query(
+                    deps.as_ref(),
+                    env.clone(),
+                    Ics721QueryMsg::DecodedClassMetadata {
+                        class_id: class_id.to_string(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+    let query_token = |class_id: &str| -> Option<DecodedToken> {
+        from_json(
+            Ics721Contract {}
+                .query(
+                    deps.as_ref(),
+                    env.clone(),
+                    Ics721QueryMsg::DecodedTokenMetadata {
+                        class_id: class_id.to_string(),
+                        token_id: "1".to_string(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // known schemas
+    assert_eq!(
+        query_class(CLASS_ID_1).unwrap().data,
+        Some(DecodedClassData::CollectionData(collection_data))
     );
+    let token = query_token(CLASS_ID_1).unwrap();
+    assert_eq!(
+        token.uri,
+        Some("https://moonphase.is/image.svg".to_string())
+    );
+    assert_eq!(
+        token.data,
+        Some(DecodedTokenData::NftExtension(nft_extension))
+    );
+
+    // unknown schemas fall back to raw data
+    assert_eq!(
+        query_class(CLASS_ID_2).unwrap().data,
+        Some(DecodedClassData::Raw(unknown_data.clone()))
+    );
+    assert_eq!(
+        query_token(CLASS_ID_2).unwrap().data,
+        Some(DecodedTokenData::Raw(unknown_data))
+    );
+
+    // unknown class
+    assert_eq!(query_class("unknown"), None);
 }
 
 fn instantiate_msg(
@@ -1154,6 +1513,42 @@ fn instantiate_msg(
         pauser: Some(PAUSER_ADDR.to_string()),
         cw721_admin: Some(ADMIN_ADDR.to_string()),
         contract_addr_length: None,
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
+    }
+}
+
+/// Sends NFT_CONTRACT_1's token "1" owned by "ekez", and returns the data of the sent packet.
+fn send_nft(
+    deps: DepsMut,
+    env: Env,
+    receiver: &str,
+    channel_id: &str,
+) -> NonFungibleTokenPacketData {
+    let msg = to_json_binary(&IbcOutgoingMsg {
+        receiver: receiver.to_string(),
+        channel_id: channel_id.to_string(),
+        timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+        memo: None,
+    })
+    .unwrap();
+    let res: Response = Ics721Contract::default()
+        .receive_nft(
+            deps,
+            env,
+            &Addr::unchecked(NFT_CONTRACT_1),
+            TokenId::new("1"),
+            "ekez".to_string(),
+            msg,
+        )
+        .unwrap();
+    match res.messages[0].msg.clone() {
+        CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => from_json(data).unwrap(),
+        _ => panic!("unexpected message type"),
     }
 }
 
@@ -1236,6 +1631,12 @@ fn test_migrate() {
         cw721_base_code_id: Some(1),
         cw721_admin: Some("some_other_admin".to_string()),
         contract_addr_length: Some(20),
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
     };
 
     // before migrate, populate legacy
@@ -1313,3 +1714,2020 @@ fn test_migrate() {
         query_class_id_for_nft_contract(deps.as_ref(), NFT_CONTRACT_2.to_string()).unwrap();
     assert_eq!(class_id_2, Some(ClassId::new(CLASS_ID_2)));
 }
+
+#[test]
+fn test_migrate_and_query_channels_by_index() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADMIN_ADDR.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => mock_querier(query),
+    });
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    let info = mock_info(OWNER_ADDR, &[]);
+    let msg = instantiate_msg(None, None);
+    Ics721Contract {}
+        .instantiate(deps.as_mut(), env.clone(), info, msg)
+        .unwrap();
+
+    // before migrate, populate channel maps without indexes
+    let outgoing_class_token_to_channel: Map<(ClassId, TokenId), String> = Map::new("h");
+    let incoming_class_token_to_channel: Map<(ClassId, TokenId), String> = Map::new("i");
+    for (class_id, token_id, channel) in [
+        (NFT_CONTRACT_1, "1", "channel-0"),
+        (NFT_CONTRACT_1, "2", "channel-5"),
+        (NFT_CONTRACT_2, "1", "channel-5"),
+    ] {
+        outgoing_class_token_to_channel
+            .save(
+                deps.as_mut().storage,
+                (ClassId::new(class_id), TokenId::new(token_id)),
+                &channel.to_string(),
+            )
+            .unwrap();
+    }
+    incoming_class_token_to_channel
+        .save(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_1), TokenId::new("1")),
+            &"channel-0".to_string(),
+        )
+        .unwrap();
+
+    let msg = MigrateMsg::WithUpdate {
+        pauser: None,
+        outgoing_proxy: None,
+        incoming_proxy: None,
+        cw721_base_code_id: None,
+        cw721_admin: None,
+        contract_addr_length: None,
+        class_data_refresh_policy: None,
+        update_voucher_collection_info: None,
+        token_metadata_policy: None,
+        voucher_code_id_rules: None,
+        claim_mode: None,
+        callback_gas_limits: None,
+    };
+    let res = Ics721Contract {}
+        .migrate(deps.as_mut(), env.clone(), msg)
+        .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("pending outgoing channel index migration", "true")));
+    assert!(res
+        .attributes
+        .contains(&attr("pending incoming channel index migration", "true")));
+
+    // by channel queries fail, until entries are indexed
+    let err = Ics721Contract {}
+        .query(
+            deps.as_ref(),
+            env.clone(),
+            Ics721QueryMsg::IncomingChannelsByChannel {
+                channel_id: "channel-0".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "incoming channel index migration pending, run MigrateChannelIndexes"
+        ))
+    );
+
+    // entries are indexed in pages
+    let migrate_indexes = ExecuteMsg::MigrateChannelIndexes { limit: Some(2) };
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other", &[]),
+            migrate_indexes.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            migrate_indexes.clone(),
+        )
+        .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("migrated outgoing channel entries", "2")));
+    assert!(res.attributes.contains(&attr("pending", "true")));
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            migrate_indexes,
+        )
+        .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("migrated outgoing channel entries", "1")));
+    assert!(res
+        .attributes
+        .contains(&attr("migrated incoming channel entries", "1")));
+    assert!(res.attributes.contains(&attr("pending", "false")));
+
+    let query = |msg: Ics721QueryMsg| {
+        Ics721Contract {}
+            .query(deps.as_ref(), env.clone(), msg)
+            .unwrap()
+    };
+    let entry = |class_id: &str, token_id: &str, channel: &str| {
+        (
+            (class_id.to_string(), token_id.to_string()),
+            channel.to_string(),
+        )
+    };
+
+    // point queries
+    let channel: Option<String> = from_json(query(Ics721QueryMsg::OutgoingChannel {
+        class_id: NFT_CONTRACT_1.to_string(),
+        token_id: "2".to_string(),
+    }))
+    .unwrap();
+    assert_eq!(channel, Some("channel-5".to_string()));
+    let channel: Option<String> = from_json(query(Ics721QueryMsg::IncomingChannel {
+        class_id: CLASS_ID_1.to_string(),
+        token_id: "2".to_string(),
+    }))
+    .unwrap();
+    assert_eq!(channel, None);
+
+    // by channel
+    let entries: Vec<((String, String), String)> =
+        from_json(query(Ics721QueryMsg::OutgoingChannelsByChannel {
+            channel_id: "channel-5".to_string(),
+            start_after: None,
+            limit: None,
+        }))
+        .unwrap();
+    assert_eq!(
+        entries,
+        [
+            entry(NFT_CONTRACT_1, "2", "channel-5"),
+            entry(NFT_CONTRACT_2, "1", "channel-5")
+        ]
+    );
+    let entries: Vec<((String, String), String)> =
+        from_json(query(Ics721QueryMsg::OutgoingChannelsByChannel {
+            channel_id: "channel-5".to_string(),
+            start_after: Some(ClassToken {
+                class_id: ClassId::new(NFT_CONTRACT_1),
+                token_id: TokenId::new("2"),
+            }),
+            limit: Some(1),
+        }))
+        .unwrap();
+    assert_eq!(entries, [entry(NFT_CONTRACT_2, "1", "channel-5")]);
+    let entries: Vec<((String, String), String)> =
+        from_json(query(Ics721QueryMsg::IncomingChannelsByChannel {
+            channel_id: "channel-0".to_string(),
+            start_after: None,
+            limit: None,
+        }))
+        .unwrap();
+    assert_eq!(entries, [entry(CLASS_ID_1, "1", "channel-0")]);
+
+    // by class
+    let entries: Vec<((String, String), String)> =
+        from_json(query(Ics721QueryMsg::OutgoingChannelsByClass {
+            class_id: NFT_CONTRACT_1.to_string(),
+            start_after: Some("1".to_string()),
+            limit: None,
+        }))
+        .unwrap();
+    assert_eq!(entries, [entry(NFT_CONTRACT_1, "2", "channel-5")]);
+    let entries: Vec<((String, String), String)> =
+        from_json(query(Ics721QueryMsg::IncomingChannelsByClass {
+            class_id: CLASS_ID_2.to_string(),
+            start_after: None,
+            limit: None,
+        }))
+        .unwrap();
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn test_update_voucher_collection_info() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // voucher contract already exists
+    let class_id = ClassId::new("wasm.address1/channel-10/address2");
+    let voucher = Addr::unchecked("voucher");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: voucher.clone(),
+            },
+        )
+        .unwrap();
+
+    let collection_data = CollectionData {
+        owner: Some("owner".to_string()),
+        contract_info: None,
+        name: "name".to_string(),
+        symbol: "symbol".to_string(),
+        extension: None,
+        num_tokens: Some(1),
+        destination_profile: None,
+    };
+    let create_vouchers = |deps: DepsMut, collection_data: &CollectionData| {
+        Ics721Contract::default()
+            .callback_create_vouchers(
+                deps,
+                env.clone(),
+                "bob".to_string(),
+                VoucherCreation {
+                    class: Class {
+                        id: class_id.clone(),
+                        uri: None,
+                        data: Some(to_json_binary(collection_data).unwrap()),
+                    },
+                    tokens: vec![Token {
+                        id: TokenId::new("1"),
+                        uri: None,
+                        data: None,
+                    }],
+                },
+            )
+            .unwrap()
+    };
+
+    // disabled by default, only mint message
+    let res = create_vouchers(deps.as_mut(), &collection_data);
+    assert_eq!(res.messages.len(), 1);
+
+    CW721_ADMIN
+        .save(deps.as_mut().storage, &Some(Addr::unchecked("cw721_admin")))
+        .unwrap();
+    UPDATE_VOUCHER_COLLECTION_INFO
+        .save(deps.as_mut().storage, &true)
+        .unwrap();
+
+    // unchanged collection data, no update
+    let res = create_vouchers(deps.as_mut(), &collection_data);
+    assert_eq!(res.messages.len(), 1);
+
+    // name and royalty changed
+    let collection_data = CollectionData {
+        name: "new name".to_string(),
+        extension: Some(CollectionExtension {
+            description: "description".to_string(),
+            image: "https://ark.pass/image.png".to_string(),
+            external_link: None,
+            explicit_content: None,
+            start_trading_time: None,
+            royalty_info: Some(RoyaltyInfo {
+                payment_address: Addr::unchecked("payment_address"),
+                share: Decimal::percent(5),
+            }),
+        }),
+        ..collection_data
+    };
+    let res = create_vouchers(deps.as_mut(), &collection_data);
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: voucher.to_string(),
+                msg: to_json_binary(
+                    &cw721_metadata_onchain::msg::ExecuteMsg::UpdateCollectionInfo {
+                        collection_info: CollectionInfoMsg {
+                            name: Some("new name".to_string()),
+                            symbol: Some("symbol".to_string()),
+                            extension: Some(CollectionExtensionMsg {
+                                description: Some("description".to_string()),
+                                image: Some("https://ark.pass/image.png".to_string()),
+                                external_link: None,
+                                explicit_content: None,
+                                start_trading_time: None,
+                                // payment address is replaced by cw721 admin
+                                royalty_info: Some(RoyaltyInfoResponse {
+                                    payment_address: "cw721_admin".to_string(),
+                                    share: Decimal::percent(5),
+                                }),
+                            }),
+                        },
+                    }
+                )
+                .unwrap(),
+                funds: vec![],
+            },
+            UPDATE_COLLECTION_INFO_REPLY_ID,
+        )
+    );
+    // update is only reported on reply
+    assert!(!res
+        .events
+        .iter()
+        .any(|e| e.ty == "ics721_update_collection_info"));
+    let reply = |deps: DepsMut, result: SubMsgResult| {
+        Ics721Contract::default()
+            .reply(
+                deps,
+                env.clone(),
+                Reply {
+                    id: UPDATE_COLLECTION_INFO_REPLY_ID,
+                    result,
+                },
+            )
+            .unwrap()
+    };
+    let res = reply(
+        deps.as_mut(),
+        SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    );
+    let event = res
+        .events
+        .iter()
+        .find(|e| e.ty == "ics721_update_collection_info")
+        .unwrap();
+    assert_eq!(
+        event.attributes[2],
+        cosmwasm_std::Attribute::new("changes", "name,description,image,external_link,explicit_content,start_trading_time,royalty_share")
+    );
+
+    // same data again, nothing to update
+    let res = create_vouchers(deps.as_mut(), &collection_data);
+    assert_eq!(res.messages.len(), 1);
+
+    // failed update is reported too
+    let collection_data = CollectionData {
+        name: "another name".to_string(),
+        ..collection_data
+    };
+    let res = create_vouchers(deps.as_mut(), &collection_data);
+    assert_eq!(res.messages.len(), 2);
+    let res = reply(deps.as_mut(), SubMsgResult::Err("unauthorized".to_string()));
+    let event = res
+        .events
+        .iter()
+        .find(|e| e.ty == "ics721_update_collection_info_failed")
+        .unwrap();
+    assert_eq!(
+        event.attributes[1..],
+        [
+            cosmwasm_std::Attribute::new("nft_contract", voucher.to_string()),
+            cosmwasm_std::Attribute::new("changes", "name"),
+            cosmwasm_std::Attribute::new("error", "unauthorized"),
+        ]
+    );
+}
+
+#[test]
+fn test_token_metadata_policy() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // voucher received before
+    let class_id = ClassId::new("wasm.address1/channel-10/address2");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked(NFT_CONTRACT_1),
+            },
+        )
+        .unwrap();
+    CLASS_ID_TO_CLASS
+        .save(
+            deps.as_mut().storage,
+            class_id.clone(),
+            &Class {
+                id: class_id.clone(),
+                uri: None,
+                data: None,
+            },
+        )
+        .unwrap();
+    let snapshot = to_json_binary(&NftExtension {
+        description: Some("snapshot".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    IBC_RECEIVE_TOKEN_METADATA
+        .save(
+            deps.as_mut().storage,
+            (class_id.clone(), TokenId::new("1")),
+            &Some(snapshot.clone()),
+        )
+        .unwrap();
+    // current extension of voucher, as returned by mock querier
+    let live = to_json_binary(&NftExtension {
+        image: Some("https://ark.pass/image.png".to_string()),
+        external_url: Some("https://interchain.arkprotocol.io".to_string()),
+        description: Some("description".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    // returns token data sent in packet
+    let sent_token_data =
+        |deps: DepsMut, env: Env| send_nft(deps, env, "callum", "channel-1").token_data;
+
+    // default policy sends snapshot
+    assert_eq!(
+        sent_token_data(deps.as_mut(), env.clone()),
+        Some(vec![snapshot.clone()])
+    );
+
+    // live policy sends current extension
+    TOKEN_METADATA_POLICY
+        .save(deps.as_mut().storage, &TokenMetadataPolicy::Live)
+        .unwrap();
+    assert_eq!(
+        sent_token_data(deps.as_mut(), env.clone()),
+        Some(vec![live.clone()])
+    );
+
+    // voucher creator is not a trusted updater
+    TOKEN_METADATA_POLICY
+        .save(
+            deps.as_mut().storage,
+            &TokenMetadataPolicy::LiveIfTrustedUpdater(vec!["updater".to_string()]),
+        )
+        .unwrap();
+    assert_eq!(
+        sent_token_data(deps.as_mut(), env.clone()),
+        Some(vec![snapshot.clone()])
+    );
+
+    // voucher creator is a trusted updater
+    TOKEN_METADATA_POLICY
+        .save(
+            deps.as_mut().storage,
+            &TokenMetadataPolicy::LiveIfTrustedUpdater(vec![OWNER_ADDR.to_string()]),
+        )
+        .unwrap();
+    assert_eq!(
+        sent_token_data(deps.as_mut(), env.clone()),
+        Some(vec![live])
+    );
+
+    // snapshot is kept
+    assert_eq!(
+        IBC_RECEIVE_TOKEN_METADATA
+            .load(&deps.storage, (class_id, TokenId::new("1")))
+            .unwrap(),
+        Some(snapshot)
+    );
+}
+
+#[test]
+fn test_receive_nft_to_sdk_module() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+
+    // counterparty is nft-transfer module
+    CHANNEL_INFOS
+        .save(
+            deps.as_mut().storage,
+            "channel-1".to_string(),
+            &ChannelInfo {
+                endpoint: IbcEndpoint {
+                    port_id: format!("wasm.{MOCK_CONTRACT_ADDR}"),
+                    channel_id: "channel-1".to_string(),
+                },
+                counterparty_endpoint: IbcEndpoint {
+                    port_id: "nft-transfer".to_string(),
+                    channel_id: "channel-0".to_string(),
+                },
+                connection_id: None,
+            },
+        )
+        .unwrap();
+
+    let packet_data = send_nft(deps.as_mut(), env, "iaa1receiver", "channel-1");
+
+    // class and token data are converted, original data is kept
+    let class = CLASS_ID_TO_CLASS
+        .load(&deps.storage, ClassId::new(NFT_CONTRACT_1))
+        .unwrap();
+    assert_eq!(
+        packet_data.class_data,
+        encode_class_data_for_sdk_module(&class.data.unwrap()).unwrap()
+    );
+    let class_data: BTreeMap<String, BTreeMap<String, String>> =
+        from_json(packet_data.class_data.unwrap()).unwrap();
+    assert_eq!(class_data["irismod:name"]["value"], "name");
+    assert_eq!(class_data["irismod:symbol"]["value"], "symbol");
+    let token_data: BTreeMap<String, BTreeMap<String, String>> =
+        from_json(&packet_data.token_data.unwrap()[0]).unwrap();
+    assert_eq!(
+        from_json::<NftExtension>(token_data["irismod:token_data"]["value"].as_bytes()).unwrap(),
+        NftExtension {
+            image: Some("https://ark.pass/image.png".to_string()),
+            external_url: Some("https://interchain.arkprotocol.io".to_string()),
+            description: Some("description".to_string()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_receive_nft_to_wasm_port_without_wasm_prefix() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+
+    // counterparty is ICS721 contract on a chain with custom wasm port prefix
+    CHANNEL_INFOS
+        .save(
+            deps.as_mut().storage,
+            "channel-1".to_string(),
+            &ChannelInfo {
+                endpoint: IbcEndpoint {
+                    port_id: format!("wasm.{MOCK_CONTRACT_ADDR}"),
+                    channel_id: "channel-1".to_string(),
+                },
+                counterparty_endpoint: IbcEndpoint {
+                    port_id: "stars.stars1contract".to_string(),
+                    channel_id: "channel-0".to_string(),
+                },
+                connection_id: None,
+            },
+        )
+        .unwrap();
+
+    let packet_data = send_nft(deps.as_mut(), env, "stars1receiver", "channel-1");
+
+    // class data is sent as is
+    let class = CLASS_ID_TO_CLASS
+        .load(&deps.storage, ClassId::new(NFT_CONTRACT_1))
+        .unwrap();
+    assert_eq!(packet_data.class_data, class.data);
+    from_json::<CollectionData>(packet_data.class_data.unwrap()).unwrap();
+}
+
+#[test]
+fn test_destination_profile() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+    let profile = DestinationProfile {
+        name: Some("profile name".to_string()),
+        symbol: None,
+        image: Some("https://ark.pass/profile.png".to_string()),
+        royalty_payee: Some("payee".to_string()),
+    };
+
+    // only collection creator can set profile
+    let set_profile = || ExecuteMsg::SetDestinationProfile {
+        collection: NFT_CONTRACT_1.to_string(),
+        channel_id: "channel-1".to_string(),
+        profile: Some(profile.clone()),
+    };
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("unauthorized", &[]),
+            set_profile(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            set_profile(),
+        )
+        .unwrap();
+
+    // returns class data sent in packet
+    let sent_collection_data = |deps: DepsMut, env: Env, channel_id: &str| -> CollectionData {
+        from_json(
+            send_nft(deps, env, "callum", channel_id)
+                .class_data
+                .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // profile is only sent to the channel it has been set for
+    let collection_data = sent_collection_data(deps.as_mut(), env.clone(), "channel-1");
+    assert_eq!(collection_data.destination_profile, Some(profile.clone()));
+    let collection_data = sent_collection_data(deps.as_mut(), env.clone(), "channel-2");
+    assert_eq!(collection_data.destination_profile, None);
+
+    // target chain applies profile on instantiation
+    let class = Class {
+        id: ClassId::new(format!("wasm.address1/channel-10/{NFT_CONTRACT_1}")),
+        uri: None,
+        data: Some(
+            to_json_binary(&CollectionData {
+                destination_profile: Some(profile),
+                ..collection_data
+            })
+            .unwrap(),
+        ),
+    };
+    let msg: cw721_metadata_onchain::msg::InstantiateMsg = from_json(
+        Ics721Contract::default()
+            .init_msg(deps.as_ref(), &env, &class, None)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(msg.name, "profile name");
+    assert_eq!(msg.symbol, "symbol");
+    let extension = msg.collection_info_extension.unwrap();
+    assert_eq!(
+        extension.image,
+        Some("https://ark.pass/profile.png".to_string())
+    );
+    assert_eq!(
+        extension.royalty_info.unwrap().payment_address,
+        "payee".to_string()
+    );
+}
+
+#[test]
+fn test_royalty_payee() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADMIN_ADDR.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => mock_querier(query),
+    });
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // same account on source and target chain
+    let source_owner =
+        bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("juno").unwrap(), &[1; 20]).unwrap();
+    let target_owner =
+        bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("stars").unwrap(), &[1; 20]).unwrap();
+
+    // voucher with royalties
+    let class_id = ClassId::new("wasm.address1/channel-10/address2");
+    let voucher = Addr::unchecked("voucher");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: voucher.clone(),
+            },
+        )
+        .unwrap();
+    let class = Class {
+        id: class_id.clone(),
+        uri: None,
+        data: Some(
+            to_json_binary(&CollectionData {
+                owner: Some(source_owner),
+                contract_info: None,
+                num_tokens: None,
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                extension: Some(CollectionExtension {
+                    description: "description".to_string(),
+                    image: "https://ark.pass/image.png".to_string(),
+                    external_link: None,
+                    explicit_content: None,
+                    start_trading_time: None,
+                    royalty_info: Some(RoyaltyInfo {
+                        payment_address: Addr::unchecked("payment_address"),
+                        share: Decimal::percent(5),
+                    }),
+                }),
+                destination_profile: None,
+            })
+            .unwrap(),
+        ),
+    };
+    CLASS_ID_TO_CLASS
+        .save(deps.as_mut().storage, class_id.clone(), &class)
+        .unwrap();
+    let register = |payee: &str| ExecuteMsg::RegisterRoyaltyPayee {
+        class_id: class_id.to_string(),
+        payee: payee.to_string(),
+    };
+
+    // same key, but contract (32 byte) address isn't the same account
+    let contract_owner =
+        bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("stars").unwrap(), &[1; 32]).unwrap();
+    let mut contract_class = class.clone();
+    contract_class.data = Some(
+        to_json_binary(&CollectionData {
+            owner: Some(
+                bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("juno").unwrap(), &[1; 32])
+                    .unwrap(),
+            ),
+            ..from_json::<CollectionData>(class.data.clone().unwrap()).unwrap()
+        })
+        .unwrap(),
+    );
+    CLASS_ID_TO_CLASS
+        .save(deps.as_mut().storage, class_id.clone(), &contract_class)
+        .unwrap();
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&contract_owner, &[]),
+            register("contract_payee"),
+        )
+        .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        ROYALTY_PAYEES
+            .may_load(&deps.storage, class_id.clone())
+            .unwrap(),
+        None
+    );
+    CLASS_ID_TO_CLASS
+        .save(deps.as_mut().storage, class_id.clone(), &class)
+        .unwrap();
+
+    // other account needs approval by admin
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            register("claimed_payee"),
+        )
+        .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        ROYALTY_PAYEE_CLAIMS
+            .load(
+                &deps.storage,
+                (class_id.clone(), Addr::unchecked("creator"))
+            )
+            .unwrap(),
+        RoyaltyPayeeClaim {
+            claimer: Addr::unchecked("creator"),
+            payee: Addr::unchecked("claimed_payee"),
+        }
+    );
+    assert_eq!(
+        ROYALTY_PAYEES
+            .may_load(&deps.storage, class_id.clone())
+            .unwrap(),
+        None
+    );
+    let approve = |claimer: &str, payee: &str| ExecuteMsg::ApproveRoyaltyPayee {
+        class_id: class_id.to_string(),
+        claimer: claimer.to_string(),
+        payee: payee.to_string(),
+    };
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            approve("creator", "claimed_payee"),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // claim of another claimer doesn't replace the first claim
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other", &[]),
+            register("other_payee"),
+        )
+        .unwrap();
+    assert_eq!(
+        ROYALTY_PAYEE_CLAIMS
+            .load(
+                &deps.storage,
+                (class_id.clone(), Addr::unchecked("creator"))
+            )
+            .unwrap()
+            .payee,
+        Addr::unchecked("claimed_payee")
+    );
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            approve("other", "claimed_payee"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::RoyaltyPayeeClaimMismatch {
+            class_id: class_id.to_string(),
+            claimer: "other".to_string(),
+            payee: "claimed_payee".to_string(),
+        }
+    );
+
+    // matching claim is approved, all pending claims are removed
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            approve("creator", "claimed_payee"),
+        )
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        ROYALTY_PAYEES
+            .load(&deps.storage, class_id.clone())
+            .unwrap(),
+        Addr::unchecked("claimed_payee")
+    );
+    for claimer in ["creator", "other", contract_owner.as_str()] {
+        assert_eq!(
+            ROYALTY_PAYEE_CLAIMS
+                .may_load(&deps.storage, (class_id.clone(), Addr::unchecked(claimer)))
+                .unwrap(),
+            None
+        );
+    }
+
+    // source owner registers directly, voucher is updated
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&target_owner, &[]),
+            register("payee"),
+        )
+        .unwrap();
+    assert_eq!(
+        ROYALTY_PAYEES
+            .load(&deps.storage, class_id.clone())
+            .unwrap(),
+        Addr::unchecked("payee")
+    );
+    assert_eq!(res.messages.len(), 1);
+    match res.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) => {
+            assert_eq!(contract_addr, voucher.to_string());
+            match from_json(msg).unwrap() {
+                cw721_metadata_onchain::msg::ExecuteMsg::UpdateCollectionInfo {
+                    collection_info,
+                } => assert_eq!(
+                    collection_info
+                        .extension
+                        .unwrap()
+                        .royalty_info
+                        .unwrap()
+                        .payment_address,
+                    "payee"
+                ),
+                _ => panic!("unexpected message type"),
+            }
+        }
+        _ => panic!("unexpected message type"),
+    }
+
+    // new vouchers are instantiated with registered payee
+    let msg: cw721_metadata_onchain::msg::InstantiateMsg = from_json(
+        Ics721Contract::default()
+            .init_msg(deps.as_ref(), &env, &class, None)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        msg.collection_info_extension
+            .unwrap()
+            .royalty_info
+            .unwrap()
+            .payment_address,
+        "payee"
+    );
+}
+
+#[test]
+fn test_claim_collection_ownership() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // voucher owned by source owner
+    let class_id = ClassId::new("wasm.address1/channel-10/address2");
+    let voucher = Addr::unchecked("voucher");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: voucher.clone(),
+            },
+        )
+        .unwrap();
+    let class = Class {
+        id: class_id.clone(),
+        uri: None,
+        data: Some(
+            to_json_binary(&CollectionData {
+                owner: Some("juno1tdakrlgp6hd4c0kswxdczdkgqsvlssj2tzsxzq".to_string()),
+                contract_info: None,
+                num_tokens: None,
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                extension: None,
+                destination_profile: None,
+            })
+            .unwrap(),
+        ),
+    };
+    CLASS_ID_TO_CLASS
+        .save(deps.as_mut().storage, class_id.clone(), &class)
+        .unwrap();
+
+    // ADR-036 signature of claim message for `claimant`
+    let pubkey: Binary =
+        HexBinary::from_hex("02a8cc7af8b9ac1e16ac05d8620d766d44b90b75aa193fa26640f003b6547e0aa8")
+            .unwrap()
+            .into();
+    let signature: Binary = HexBinary::from_hex("80977745abfdc251c51fd94d6f777ae15af0df40554a41b2d0114a4bbd6d382f164fba51f95e92ca61551d6ba423fdec8973149bb95ed6ac6c456c2ea313af87").unwrap().into();
+    let claim = |class_id: &str, pubkey: &Binary| ExecuteMsg::ClaimCollectionOwnership {
+        class_id: class_id.to_string(),
+        pubkey: pubkey.clone(),
+        signature: signature.clone(),
+    };
+
+    // signature can't be used by others
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other", &[]),
+            claim(&class_id, &pubkey),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidOwnershipProof("invalid signature".to_string())
+    );
+
+    // key of another account
+    let mut other_pubkey = pubkey.to_vec();
+    other_pubkey[0] = 3;
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("claimant", &[]),
+            claim(&class_id, &other_pubkey.into()),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidOwnershipProof(
+            "public key doesn't belong to collection owner juno1tdakrlgp6hd4c0kswxdczdkgqsvlssj2tzsxzq"
+                .to_string()
+        )
+    );
+
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("claimant", &[]),
+            claim(&class_id, &pubkey),
+        )
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: voucher.to_string(),
+            msg: to_json_binary(
+                &cw721_metadata_onchain::msg::ExecuteMsg::UpdateCreatorOwnership(
+                    cw_ownable::Action::TransferOwnership {
+                        new_owner: "claimant".to_string(),
+                        expiry: None,
+                    }
+                )
+            )
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+
+    // home collections are owned by creator
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &ClassId::new(NFT_CONTRACT_1),
+            &ClassIdInfo {
+                class_id: ClassId::new(NFT_CONTRACT_1),
+                address: Addr::unchecked(NFT_CONTRACT_1),
+            },
+        )
+        .unwrap();
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env,
+            mock_info("claimant", &[]),
+            claim(NFT_CONTRACT_1, &pubkey),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotVoucherCollection(NFT_CONTRACT_1.to_string())
+    );
+}
+
+#[test]
+fn test_register_class_mapping() {
+    fn admin_querier(query: &WasmQuery) -> QuerierResult {
+        match query {
+            WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+                to_json_binary(&ContractInfoResponse {
+                    code_id: 0,
+                    creator: "creator".to_string(),
+                    admin: Some(ADMIN_ADDR.to_string()),
+                    pinned: false,
+                    ibc_port: None,
+                })
+                .unwrap(),
+            )),
+            _ => mock_querier(query),
+        }
+    }
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(admin_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    let class_id = ClassId::new("wasm.address1/channel-10/address2");
+    let register = ExecuteMsg::RegisterClassMapping {
+        class_id: class_id.to_string(),
+        nft_contract: NFT_CONTRACT_1.to_string(),
+    };
+
+    // only admin
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other", &[]),
+            register.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    // collection creator can't map a class, it doesn't control on source chain
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            register.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // ICS721 must be minter
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            register.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Ics721NotMinter(NFT_CONTRACT_1.to_string())
+    );
+
+    // ICS721 is minter, token "1" exists in nft contract
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { msg, .. } => match from_json::<QueryMsg>(msg) {
+            Ok(QueryMsg::GetMinterOwnership {}) => QuerierResult::Ok(ContractResult::Ok(
+                to_json_binary(&Ownership::<Addr> {
+                    owner: Some(Addr::unchecked(MOCK_CONTRACT_ADDR)),
+                    pending_owner: None,
+                    pending_expiry: None,
+                })
+                .unwrap(),
+            )),
+            Ok(QueryMsg::OwnerOf { token_id, .. }) if token_id == "1" => {
+                QuerierResult::Ok(ContractResult::Ok(
+                    to_json_binary(&cw721::msg::OwnerOfResponse {
+                        owner: "local_owner".to_string(),
+                        approvals: vec![],
+                    })
+                    .unwrap(),
+                ))
+            }
+            Ok(QueryMsg::OwnerOf { .. }) => {
+                QuerierResult::Ok(ContractResult::Err("token not found".to_string()))
+            }
+            _ => admin_querier(query),
+        },
+        _ => admin_querier(query),
+    });
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            register.clone(),
+        )
+        .unwrap();
+    assert_eq!(
+        query_nft_contract_for_class_id(&deps.storage, class_id.clone()).unwrap(),
+        Some(Addr::unchecked(NFT_CONTRACT_1))
+    );
+    assert_eq!(
+        CLASS_MAPPINGS
+            .load(&deps.storage, class_id.clone())
+            .unwrap(),
+        Addr::unchecked(NFT_CONTRACT_1)
+    );
+
+    // class can't be mapped twice
+    let err = Ics721Contract::default()
+        .execute(deps.as_mut(), env, mock_info(ADMIN_ADDR, &[]), register)
+        .unwrap_err();
+    assert_eq!(err, ContractError::ClassAlreadyMapped(class_id.to_string()));
+
+    // incoming NFTs are minted on bound nft contract, unless token ID already exists
+    let token = |id: &str| Token {
+        id: TokenId::new(id),
+        uri: None,
+        data: None,
+    };
+    let err = Ics721Contract::default()
+        .callback_mint(
+            deps.as_mut(),
+            class_id.clone(),
+            vec![token("2"), token("1")],
+            "receiver".to_string(),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TokenIdCollision {
+            nft_contract: NFT_CONTRACT_1.to_string(),
+            token_id: "1".to_string(),
+        }
+    );
+    let res: cosmwasm_std::Response<_> = Ics721Contract::default()
+        .callback_mint(
+            deps.as_mut(),
+            class_id,
+            vec![token("2")],
+            "receiver".to_string(),
+        )
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    match res.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+            assert_eq!(contract_addr, NFT_CONTRACT_1)
+        }
+        _ => panic!("unexpected message type"),
+    }
+}
+
+#[test]
+fn test_voucher_code_id_rules() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    let rules = vec![
+        VoucherCodeIdRule {
+            matcher: VoucherMatcher::Channel("channel-1".to_string()),
+            code_id: 2,
+            init_msg: VoucherInitMsg::Extend(Binary::from(r#"{"expiration_days":30}"#.as_bytes())),
+        },
+        VoucherCodeIdRule {
+            matcher: VoucherMatcher::ClassDataKey("soulbound".to_string()),
+            code_id: 3,
+            init_msg: VoucherInitMsg::Default,
+        },
+        VoucherCodeIdRule {
+            matcher: VoucherMatcher::ClassIdPrefix("wasm.address1/channel-2/".to_string()),
+            code_id: 4,
+            init_msg: VoucherInitMsg::Default,
+        },
+    ];
+
+    // extension must be a JSON object
+    let mut msg = instantiate_msg(None, None);
+    msg.voucher_code_id_rules = Some(vec![VoucherCodeIdRule {
+        matcher: VoucherMatcher::Channel("channel-1".to_string()),
+        code_id: 2,
+        init_msg: VoucherInitMsg::Extend(Binary::from("30".as_bytes())),
+    }]);
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+
+    msg.voucher_code_id_rules = Some(rules.clone());
+    Ics721Contract {}
+        .instantiate(deps.as_mut(), env.clone(), mock_info(OWNER_ADDR, &[]), msg)
+        .unwrap();
+    let stored: Vec<VoucherCodeIdRule> = from_json(
+        Ics721Contract {}
+            .query(
+                deps.as_ref(),
+                env.clone(),
+                Ics721QueryMsg::VoucherCodeIdRules {},
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stored, rules);
+
+    // first matching rule applies, otherwise default code id
+    let code_id = |class_id: &str, class_data: Option<&str>| {
+        get_voucher_code_id(
+            &deps.storage,
+            &ClassId::new(class_id),
+            class_data
+                .map(|data| Binary::from(data.as_bytes()))
+                .as_ref(),
+        )
+        .unwrap()
+    };
+    assert_eq!(code_id("wasm.address1/channel-1/address2", None), 2);
+    assert_eq!(
+        code_id(
+            "wasm.address1/channel-1/address2",
+            Some(r#"{"soulbound":true}"#)
+        ),
+        2
+    );
+    assert_eq!(
+        code_id(
+            "wasm.address1/channel-3/address2",
+            Some(r#"{"soulbound":true}"#)
+        ),
+        3
+    );
+    assert_eq!(
+        code_id(
+            "nft-transfer/channel-3/class",
+            Some(r#"{"irismod:soulbound":{"value":"true"}}"#)
+        ),
+        3
+    );
+    assert_eq!(code_id("wasm.address1/channel-2/address2", None), 4);
+    assert_eq!(
+        code_id("wasm.address1/channel-3/address2", None),
+        CW721_CODE_ID.load(&deps.storage).unwrap()
+    );
+
+    // instantiate message is extended
+    let class = Class {
+        id: ClassId::new("wasm.address1/channel-1/address2"),
+        uri: None,
+        data: None,
+    };
+    let init_msg = Ics721Contract::default()
+        .voucher_init_msg(deps.as_ref(), &env, &class, None, &rules[0].init_msg)
+        .unwrap();
+    let init_msg = String::from_utf8(init_msg.to_vec()).unwrap();
+    assert!(init_msg.contains(r#""expiration_days":30"#));
+    assert!(init_msg.contains(r#""name":"wasm.address1/channel-1/address2""#));
+}
+
+#[test]
+fn test_migrate_voucher_contracts() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { contract_addr } => {
+            let (admin, code_id) = match contract_addr.as_str() {
+                MOCK_CONTRACT_ADDR => (ADMIN_ADDR, 0),
+                "voucher1" | "voucher3" => (MOCK_CONTRACT_ADDR, 1),
+                "voucher2" => (MOCK_CONTRACT_ADDR, 5),
+                _ => ("other", 1),
+            };
+            QuerierResult::Ok(ContractResult::Ok(
+                to_json_binary(&ContractInfoResponse {
+                    code_id,
+                    creator: "creator".to_string(),
+                    admin: Some(admin.to_string()),
+                    pinned: false,
+                    ibc_port: None,
+                })
+                .unwrap(),
+            ))
+        }
+        _ => mock_querier(query),
+    });
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // home collection and vouchers
+    for (class_id, nft_contract) in [
+        (NFT_CONTRACT_1, NFT_CONTRACT_1),
+        ("wasm.address1/channel-1/address2", "voucher1"),
+        ("wasm.address1/channel-1/address3", "voucher2"),
+        ("wasm.address1/channel-1/address4", "voucher3"),
+    ] {
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &ClassId::new(class_id),
+                &ClassIdInfo {
+                    class_id: ClassId::new(class_id),
+                    address: Addr::unchecked(nft_contract),
+                },
+            )
+            .unwrap();
+    }
+    let migrate = |start_after: Option<&str>| ExecuteMsg::MigrateVoucherContracts {
+        new_code_id: 5,
+        msg: Binary::from("{}".as_bytes()),
+        start_after: start_after.map(|s| s.to_string()),
+        limit: Some(2),
+    };
+
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other", &[]),
+            migrate(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // first page: home collection is skipped, voucher is migrated
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            migrate(None),
+        )
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Migrate {
+                contract_addr: "voucher1".to_string(),
+                new_code_id: 5,
+                msg: Binary::from("{}".as_bytes()),
+            },
+            MIGRATE_VOUCHER_REPLY_ID,
+        )]
+    );
+    let next_start_after = res
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "next_start_after")
+        .unwrap()
+        .value
+        .clone();
+    assert_eq!(next_start_after, "wasm.address1/channel-1/address2");
+    // code id is only updated, once all voucher contracts are migrated
+    let code_id = CW721_CODE_ID.load(&deps.storage).unwrap();
+    assert_ne!(code_id, 5);
+    let migration = |nft_contract: &str, deps: Deps| {
+        VOUCHER_MIGRATIONS
+            .load(deps.storage, Addr::unchecked(nft_contract))
+            .unwrap()
+            .status
+    };
+    assert_eq!(
+        migration(NFT_CONTRACT_1, deps.as_ref()),
+        VoucherMigrationStatus::Skipped("ICS721 is not admin".to_string())
+    );
+    assert_eq!(
+        migration("voucher1", deps.as_ref()),
+        VoucherMigrationStatus::Pending
+    );
+
+    // result is recorded on reply
+    Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: MIGRATE_VOUCHER_REPLY_ID,
+                result: SubMsgResult::Err("migrate failed".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        migration("voucher1", deps.as_ref()),
+        VoucherMigrationStatus::Failed("migrate failed".to_string())
+    );
+
+    // later pages must have the same target as the first page
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            ExecuteMsg::MigrateVoucherContracts {
+                new_code_id: 6,
+                msg: Binary::from("{}".as_bytes()),
+                start_after: Some(next_start_after.clone()),
+                limit: Some(2),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::VoucherMigrationMismatch { code_id: 6 });
+
+    // second page: voucher with new code id is skipped
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            migrate(Some(&next_start_after)),
+        )
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "next_start_after"
+            && attr.value == "wasm.address1/channel-1/address4"));
+    assert_eq!(CW721_CODE_ID.load(&deps.storage).unwrap(), code_id);
+    assert_eq!(
+        migration("voucher2", deps.as_ref()),
+        VoucherMigrationStatus::Skipped("already migrated".to_string())
+    );
+    Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: MIGRATE_VOUCHER_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        migration("voucher3", deps.as_ref()),
+        VoucherMigrationStatus::Migrated
+    );
+
+    // last page: code id is kept, since migration of voucher1 failed
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            migrate(Some("wasm.address1/channel-1/address4")),
+        )
+        .unwrap();
+    assert!(res.messages.is_empty());
+    assert!(!res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "next_start_after"));
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "code_id_switched" && attr.value == "false"));
+    assert_eq!(CW721_CODE_ID.load(&deps.storage).unwrap(), code_id);
+
+    // starting over in one page: new code id is used for new voucher contracts, once all migrations replied
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN_ADDR, &[]),
+            ExecuteMsg::MigrateVoucherContracts {
+                new_code_id: 5,
+                msg: Binary::from("{}".as_bytes()),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(CW721_CODE_ID.load(&deps.storage).unwrap(), code_id);
+    for _ in 0..2 {
+        Ics721Contract::default()
+            .reply(
+                deps.as_mut(),
+                env.clone(),
+                Reply {
+                    id: MIGRATE_VOUCHER_REPLY_ID,
+                    result: SubMsgResult::Ok(SubMsgResponse {
+                        events: vec![],
+                        data: None,
+                    }),
+                },
+            )
+            .unwrap();
+    }
+    assert_eq!(
+        migration("voucher1", deps.as_ref()),
+        VoucherMigrationStatus::Migrated
+    );
+    assert_eq!(CW721_CODE_ID.load(&deps.storage).unwrap(), 5);
+}
+
+#[test]
+fn test_repair_class_mapping() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADMIN_ADDR.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        WasmQuery::Smart { contract_addr, msg } => match from_json::<QueryMsg>(msg) {
+            Ok(QueryMsg::GetMinterOwnership {}) => QuerierResult::Ok(ContractResult::Ok(
+                to_json_binary(&Ownership::<Addr> {
+                    owner: Some(Addr::unchecked(MOCK_CONTRACT_ADDR)),
+                    pending_owner: None,
+                    pending_expiry: None,
+                })
+                .unwrap(),
+            )),
+            // only replacement holds outstanding voucher
+            Ok(QueryMsg::OwnerOf { .. }) if contract_addr == "replacement" => {
+                QuerierResult::Ok(ContractResult::Ok(
+                    to_json_binary(&cw721::msg::OwnerOfResponse {
+                        owner: "owner".to_string(),
+                        approvals: vec![],
+                    })
+                    .unwrap(),
+                ))
+            }
+            Ok(QueryMsg::OwnerOf { .. }) => {
+                QuerierResult::Ok(ContractResult::Err("token not found".to_string()))
+            }
+            _ => mock_querier(query),
+        },
+        _ => mock_querier(query),
+    });
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // voucher with one outstanding NFT
+    let class_id = ClassId::new("wasm.address1/channel-1/address2");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    INCOMING_CLASS_TOKEN_TO_CHANNEL
+        .save(
+            deps.as_mut().storage,
+            (class_id.clone(), TokenId::new("1")),
+            &"channel-1".to_string(),
+        )
+        .unwrap();
+    let execute = |deps: DepsMut, sender: &str, msg: ExecuteMsg| {
+        Ics721Contract::default().execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+    let remove = ExecuteMsg::RemoveClassMapping {
+        class_id: class_id.to_string(),
+    };
+    let repoint = |nft_contract: &str| ExecuteMsg::RepointClassMapping {
+        class_id: class_id.to_string(),
+        nft_contract: nft_contract.to_string(),
+    };
+
+    // only admin
+    let err = execute(deps.as_mut(), "other", remove.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // mapping with outstanding vouchers can't be removed or re-instantiated
+    let outstanding = ContractError::OutstandingVouchers {
+        class_id: class_id.to_string(),
+        count: 1,
+    };
+    let err = execute(deps.as_mut(), ADMIN_ADDR, remove.clone()).unwrap_err();
+    assert_eq!(err, outstanding);
+    let err = execute(
+        deps.as_mut(),
+        ADMIN_ADDR,
+        ExecuteMsg::ReinstantiateVoucherContract {
+            class_id: class_id.to_string(),
+            salt: Binary::from("salt".as_bytes()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, outstanding);
+
+    // replacement must hold outstanding vouchers
+    let err = execute(deps.as_mut(), ADMIN_ADDR, repoint("other_voucher")).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingVoucher {
+            nft_contract: "other_voucher".to_string(),
+            token_id: "1".to_string(),
+        }
+    );
+    let res = execute(deps.as_mut(), ADMIN_ADDR, repoint("replacement")).unwrap();
+    let event = res
+        .events
+        .iter()
+        .find(|event| event.ty == "ics721_class_mapping_repair")
+        .unwrap();
+    assert!(event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "previous_nft_contract" && attr.value == "voucher"));
+    assert_eq!(
+        query_nft_contract_for_class_id(&deps.storage, class_id.clone()).unwrap(),
+        Some(Addr::unchecked("replacement"))
+    );
+    // previous contract can be mapped again
+    assert_eq!(
+        query_class_id_for_nft_contract(deps.as_ref(), "voucher".to_string()).unwrap(),
+        None
+    );
+
+    // home collections are not repaired
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &ClassId::new(NFT_CONTRACT_1),
+            &ClassIdInfo {
+                class_id: ClassId::new(NFT_CONTRACT_1),
+                address: Addr::unchecked(NFT_CONTRACT_1),
+            },
+        )
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        ADMIN_ADDR,
+        ExecuteMsg::RemoveClassMapping {
+            class_id: NFT_CONTRACT_1.to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotVoucherCollection(NFT_CONTRACT_1.to_string())
+    );
+
+    // once vouchers are back home, mapping can be removed
+    INCOMING_CLASS_TOKEN_TO_CHANNEL
+        .remove(deps.as_mut().storage, (class_id.clone(), TokenId::new("1")))
+        .unwrap();
+    execute(deps.as_mut(), ADMIN_ADDR, remove).unwrap();
+    assert_eq!(
+        query_nft_contract_for_class_id(&deps.storage, class_id).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_claim_vouchers() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADMIN_ADDR.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => mock_querier(query),
+    });
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    let env = mock_env();
+    let mut msg = instantiate_msg(None, None);
+    msg.claim_mode = Some(ClaimMode::Enabled { expiry: 100 });
+    Ics721Contract {}
+        .instantiate(deps.as_mut(), env.clone(), mock_info(OWNER_ADDR, &[]), msg)
+        .unwrap();
+    let claim_mode: ClaimMode = from_json(
+        Ics721Contract {}
+            .query(deps.as_ref(), env.clone(), Ics721QueryMsg::ClaimMode {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claim_mode, ClaimMode::Enabled { expiry: 100 });
+
+    // vouchers received from juno sender, held in custody for receiver
+    let class_id = ClassId::new("wasm.address1/channel-1/address2");
+    let sender = "juno1tdakrlgp6hd4c0kswxdczdkgqsvlssj2tzsxzq";
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            deps.as_mut().storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    CLASS_ID_TO_CLASS
+        .save(
+            deps.as_mut().storage,
+            class_id.clone(),
+            &Class {
+                id: class_id.clone(),
+                uri: None,
+                data: None,
+            },
+        )
+        .unwrap();
+    let claimable = ClaimableVoucher {
+        receiver: Addr::unchecked("receiver"),
+        sender: sender.to_string(),
+        expires_at: env.block.time.plus_seconds(100),
+    };
+    // voucher sent by a contract, its address on this chain belongs to someone else
+    let contract_sender =
+        bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("juno").unwrap(), &[1; 32]).unwrap();
+    let contract_claimable = ClaimableVoucher {
+        sender: contract_sender,
+        ..claimable.clone()
+    };
+    let mut entries = vec![];
+    for (token_id, claimable) in [
+        ("1", &claimable),
+        ("2", &claimable),
+        ("3", &contract_claimable),
+    ] {
+        let key = (class_id.clone(), TokenId::new(token_id));
+        INCOMING_CLASS_TOKEN_TO_CHANNEL
+            .save(deps.as_mut().storage, key.clone(), &"channel-1".to_string())
+            .unwrap();
+        entries.push((key, claimable.clone()));
+    }
+    Ics721Contract {}
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            ExecuteMsg::Callback(CallbackMsg::AddClaimableVouchers(entries)),
+        )
+        .unwrap();
+
+    let execute = |deps: DepsMut, env: Env, sender: &str, msg: ExecuteMsg| {
+        Ics721Contract::default().execute(deps, env, mock_info(sender, &[]), msg)
+    };
+    let claim = ExecuteMsg::Claim {
+        class_id: class_id.to_string(),
+        token_ids: vec!["1".to_string()],
+    };
+
+    // only receiver may claim
+    let err = execute(deps.as_mut(), env.clone(), "other", claim.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), env.clone(), "receiver", claim.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "voucher".to_string(),
+            msg: to_json_binary(&cw721_metadata_onchain::msg::ExecuteMsg::TransferNft {
+                recipient: "receiver".to_string(),
+                token_id: "1".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let err = execute(deps.as_mut(), env.clone(), "receiver", claim).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoClaimableVoucher {
+            class_id: class_id.to_string(),
+            token_id: "1".to_string(),
+        }
+    );
+
+    // unclaimed vouchers are returned after expiry by admin or sender
+    let return_unclaimed = ExecuteMsg::ReturnUnclaimedVouchers {
+        class_id: class_id.to_string(),
+        token_ids: vec!["2".to_string()],
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(1000)),
+    };
+    let stars_sender = "stars1tdakrlgp6hd4c0kswxdczdkgqsvlssj2fvyqwd";
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        stars_sender,
+        return_unclaimed.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ClaimNotExpired {
+            class_id: class_id.to_string(),
+            token_id: "2".to_string(),
+        }
+    );
+    let mut expired_env = env.clone();
+    expired_env.block.time = env.block.time.plus_seconds(100);
+    let err = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        "other",
+        return_unclaimed.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let stars_contract =
+        bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("stars").unwrap(), &[1; 32]).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        &stars_contract,
+        ExecuteMsg::ReturnUnclaimedVouchers {
+            class_id: class_id.to_string(),
+            token_ids: vec!["3".to_string()],
+            timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(1000)),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // outgoing proxy can't be bypassed
+    OUTGOING_PROXY
+        .save(deps.as_mut().storage, &Some(Addr::unchecked("proxy")))
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        stars_sender,
+        return_unclaimed.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OutgoingProxyBypassed {});
+    OUTGOING_PROXY.save(deps.as_mut().storage, &None).unwrap();
+
+    let res = execute(deps.as_mut(), expired_env, stars_sender, return_unclaimed).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let packet: NonFungibleTokenPacketData = match &res.messages[0].msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket {
+            channel_id, data, ..
+        }) => {
+            assert_eq!(channel_id, "channel-1");
+            from_json(data).unwrap()
+        }
+        _ => panic!("unexpected message"),
+    };
+    assert_eq!(packet.class_id, class_id);
+    assert_eq!(packet.sender, "receiver");
+    assert_eq!(packet.receiver, sender);
+    assert_eq!(
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .load(deps.as_ref().storage, (class_id.clone(), TokenId::new("2")))
+            .unwrap(),
+        "channel-1"
+    );
+    let claimable: Option<ClaimableVoucher> = from_json(
+        Ics721Contract {}
+            .query(
+                deps.as_ref(),
+                env,
+                Ics721QueryMsg::ClaimableVoucher {
+                    class_id: class_id.to_string(),
+                    token_id: "2".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claimable, None);
+}
@@ -0,0 +1,426 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, Binary};
+use cw721::{msg::NftExtensionMsg, NftExtension, Trait};
+use serde::{Deserialize, Serialize};
+
+/// Token data coming from other chains may use a different metadata schema than cw721's `NftExtension`.
+/// A translator maps known foreign formats into the extension of the minted voucher.
+/// Received token data is always stored as is in `IBC_RECEIVE_TOKEN_METADATA`.
+#[cw_serde]
+pub enum MetadataTranslator {
+    /// cw721 `NftExtension`, unknown fields are rejected.
+    Cw721,
+    /// OpenSea-style metadata, as used by cw721-metadata-onchain v0.16 `Metadata` and sg721.
+    /// Unknown fields are ignored and non-string trait values are converted to strings.
+    OpenSea,
+    /// IRISnet x/nft token data, where each `irismod:` prefixed key holds a `{"value": ...}` object.
+    /// This is also the encoding ICS721 uses for sending to SDK modules (e.g. nft-transfer), see `interop`.
+    Irismod,
+    /// Protobuf `Any` in JSON encoding (having a `@type` key), as used for Cosmos SDK x/nft data.
+    /// Handled like `FlatJson`.
+    AnyJson,
+    /// Flat JSON object, e.g. custom game schemas. Well-known keys (like `name` and `image`) are mapped
+    /// to the extension, all other top-level values are added as attributes.
+    FlatJson,
+}
+
+/// Translators used in this order for detecting the format, in case no translator is set for a class.
+/// `FlatJson` is not detected, since it accepts any JSON object.
+pub const DETECTABLE_TRANSLATORS: [MetadataTranslator; 4] = [
+    MetadataTranslator::Cw721,
+    MetadataTranslator::Irismod,
+    MetadataTranslator::AnyJson,
+    MetadataTranslator::OpenSea,
+];
+
+impl MetadataTranslator {
+    /// Returns None, in case token data is not of this format.
+    pub fn translate(&self, data: &Binary) -> Option<NftExtensionMsg> {
+        match self {
+            MetadataTranslator::Cw721 => {
+                from_json::<NftExtension>(data)
+                    .ok()
+                    .map(|ext| NftExtensionMsg {
+                        animation_url: ext.animation_url,
+                        attributes: ext.attributes,
+                        background_color: ext.background_color,
+                        description: ext.description,
+                        external_url: ext.external_url,
+                        image: ext.image,
+                        image_data: ext.image_data,
+                        youtube_url: ext.youtube_url,
+                        name: ext.name,
+                    })
+            }
+            MetadataTranslator::OpenSea => from_json::<OpenSeaMetadata>(data)
+                .ok()
+                .filter(|metadata| metadata.is_known())
+                .map(NftExtensionMsg::from),
+            MetadataTranslator::Irismod => translate_irismod(data),
+            MetadataTranslator::AnyJson => {
+                parse_any_json(data).map(|object| translate_flat_json(object, None))
+            }
+            MetadataTranslator::FlatJson => from_json::<BTreeMap<String, JsonValue>>(data)
+                .ok()
+                .map(|object| translate_flat_json(object, None)),
+        }
+    }
+}
+
+/// Translates token data using the given translator, otherwise the format is detected.
+pub fn translate_token_data(
+    translator: Option<&MetadataTranslator>,
+    data: &Binary,
+) -> Option<NftExtensionMsg> {
+    match translator {
+        Some(translator) => translator.translate(data),
+        None => DETECTABLE_TRANSLATORS
+            .iter()
+            .find_map(|translator| translator.translate(data)),
+    }
+}
+
+/// Generic JSON value, used for parsing foreign schemas.
+/// NB: floats are not supported by `serde-json-wasm`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    String(String),
+    List(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+impl JsonValue {
+    /// Only scalar values are converted, nested values are ignored.
+    pub(crate) fn into_string(self) -> Option<String> {
+        match self {
+            JsonValue::Bool(value) => Some(value.to_string()),
+            JsonValue::UInt(value) => Some(value.to_string()),
+            JsonValue::Int(value) => Some(value.to_string()),
+            JsonValue::String(value) => Some(value),
+            JsonValue::Null | JsonValue::List(_) | JsonValue::Object(_) => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenSeaTrait {
+    display_type: Option<String>,
+    trait_type: Option<String>,
+    value: JsonValue,
+}
+
+#[derive(Deserialize)]
+struct OpenSeaMetadata {
+    image: Option<String>,
+    image_data: Option<String>,
+    external_url: Option<String>,
+    description: Option<String>,
+    name: Option<String>,
+    attributes: Option<Vec<OpenSeaTrait>>,
+    background_color: Option<String>,
+    animation_url: Option<String>,
+    youtube_url: Option<String>,
+}
+
+impl OpenSeaMetadata {
+    /// Any JSON object can be parsed, since all fields are optional. Hence at least one well-known field is required.
+    fn is_known(&self) -> bool {
+        self.name.is_some()
+            || self.description.is_some()
+            || self.image.is_some()
+            || self.image_data.is_some()
+            || self.animation_url.is_some()
+            || self.attributes.is_some()
+    }
+}
+
+impl From<OpenSeaMetadata> for NftExtensionMsg {
+    fn from(metadata: OpenSeaMetadata) -> Self {
+        let attributes = metadata.attributes.map(|attributes| {
+            attributes
+                .into_iter()
+                .filter_map(|t| {
+                    t.value.into_string().map(|value| Trait {
+                        display_type: t.display_type,
+                        // e.g. OpenSea allows traits without type
+                        trait_type: t.trait_type.unwrap_or_default(),
+                        value,
+                    })
+                })
+                .collect()
+        });
+        NftExtensionMsg {
+            animation_url: metadata.animation_url,
+            attributes,
+            background_color: metadata.background_color,
+            description: metadata.description,
+            external_url: metadata.external_url,
+            image: metadata.image,
+            image_data: metadata.image_data,
+            youtube_url: metadata.youtube_url,
+            name: metadata.name,
+        }
+    }
+}
+
+pub(crate) const IRISMOD_PREFIX: &str = "irismod:";
+
+#[derive(Deserialize)]
+struct IrismodValue {
+    value: JsonValue,
+}
+
+/// Parses `irismod:` prefixed key/value data, returned keys are without prefix.
+pub(crate) fn parse_irismod(data: &Binary) -> Option<BTreeMap<String, JsonValue>> {
+    let object = from_json::<BTreeMap<String, IrismodValue>>(data).ok()?;
+    if !object.keys().any(|key| key.starts_with(IRISMOD_PREFIX)) {
+        return None;
+    }
+    Some(
+        object
+            .into_iter()
+            .map(|(key, IrismodValue { value })| {
+                let key = key.strip_prefix(IRISMOD_PREFIX).unwrap_or(&key).to_string();
+                (key, value)
+            })
+            .collect(),
+    )
+}
+
+/// Parses a protobuf `Any` in JSON encoding, returned object is without `@type` key.
+pub(crate) fn parse_any_json(data: &Binary) -> Option<BTreeMap<String, JsonValue>> {
+    let mut object = from_json::<BTreeMap<String, JsonValue>>(data).ok()?;
+    object.remove("@type")?;
+    Some(object)
+}
+
+fn translate_irismod(data: &Binary) -> Option<NftExtensionMsg> {
+    let mut object = parse_irismod(data)?;
+    // irismod uses `token_name` for the name of a token
+    if let Some(name) = object.remove("token_name") {
+        object.insert("name".to_string(), name);
+    }
+    // token data sent by ICS721 to SDK modules, holding the original extension
+    if let Some(JsonValue::String(token_data)) = object.get("token_data") {
+        let token_data = Binary::from(token_data.as_bytes());
+        if let Some(extension) = MetadataTranslator::Cw721
+            .translate(&token_data)
+            .or_else(|| MetadataTranslator::OpenSea.translate(&token_data))
+        {
+            return Some(extension);
+        }
+    }
+    Some(translate_flat_json(object, Some(IRISMOD_PREFIX)))
+}
+
+/// Maps well-known keys to the extension, remaining scalar values are added as attributes.
+fn translate_flat_json(
+    mut object: BTreeMap<String, JsonValue>,
+    trait_type_prefix: Option<&str>,
+) -> NftExtensionMsg {
+    let mut take = |key: &str| object.remove(key).and_then(JsonValue::into_string);
+    let mut extension = NftExtensionMsg {
+        animation_url: take("animation_url"),
+        attributes: None,
+        background_color: take("background_color"),
+        description: take("description"),
+        external_url: take("external_url"),
+        image: take("image"),
+        image_data: take("image_data"),
+        youtube_url: take("youtube_url"),
+        name: take("name"),
+    };
+    let attributes: Vec<Trait> = object
+        .into_iter()
+        .filter_map(|(key, value)| {
+            value.into_string().map(|value| Trait {
+                display_type: None,
+                trait_type: format!("{}{key}", trait_type_prefix.unwrap_or_default()),
+                value,
+            })
+        })
+        .collect();
+    if !attributes.is_empty() {
+        extension.attributes = Some(attributes);
+    }
+    extension
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::to_json_binary;
+
+    use super::*;
+
+    fn binary(json: &str) -> Binary {
+        Binary::from(json.as_bytes())
+    }
+
+    fn attribute(trait_type: &str, value: &str) -> Trait {
+        Trait {
+            display_type: None,
+            trait_type: trait_type.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cw721() {
+        let ext = NftExtension {
+            name: Some("name".to_string()),
+            image: Some("https://ark.pass/image.png".to_string()),
+            attributes: Some(vec![attribute("color", "red")]),
+            ..Default::default()
+        };
+        let data = to_json_binary(&ext).unwrap();
+        let expected = NftExtensionMsg {
+            name: Some("name".to_string()),
+            image: Some("https://ark.pass/image.png".to_string()),
+            attributes: Some(vec![attribute("color", "red")]),
+            ..Default::default()
+        };
+        assert_eq!(
+            MetadataTranslator::Cw721.translate(&data),
+            Some(expected.clone())
+        );
+        assert_eq!(translate_token_data(None, &data), Some(expected));
+
+        // strict, unknown fields are rejected
+        let data = binary(r#"{"name":"name","properties":{}}"#);
+        assert_eq!(MetadataTranslator::Cw721.translate(&data), None);
+    }
+
+    #[test]
+    fn test_cw721_metadata_onchain_v016() {
+        // cw721-metadata-onchain v0.16 `Metadata`, null fields and trait with display type
+        let data = binary(
+            r#"{"image":"ipfs://image","image_data":null,"external_url":null,"description":"description","name":"name","attributes":[{"display_type":"number","trait_type":"level","value":"5"}],"background_color":null,"animation_url":null,"youtube_url":null}"#,
+        );
+        let expected = NftExtensionMsg {
+            image: Some("ipfs://image".to_string()),
+            description: Some("description".to_string()),
+            name: Some("name".to_string()),
+            attributes: Some(vec![Trait {
+                display_type: Some("number".to_string()),
+                trait_type: "level".to_string(),
+                value: "5".to_string(),
+            }]),
+            ..Default::default()
+        };
+        assert_eq!(
+            MetadataTranslator::OpenSea.translate(&data),
+            Some(expected.clone())
+        );
+        assert_eq!(translate_token_data(None, &data), Some(expected));
+    }
+
+    #[test]
+    fn test_sg721() {
+        // sg721 metadata with additional fields and non-string trait values
+        let data = binary(
+            r#"{"name":"Bad Kid #1","image":"ipfs://image","dna":"abc","edition":1,"attributes":[{"trait_type":"rarity","value":42},{"trait_type":"legendary","value":true},{"value":"untyped"}]}"#,
+        );
+        let expected = NftExtensionMsg {
+            name: Some("Bad Kid #1".to_string()),
+            image: Some("ipfs://image".to_string()),
+            attributes: Some(vec![
+                attribute("rarity", "42"),
+                attribute("legendary", "true"),
+                attribute("", "untyped"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(MetadataTranslator::Cw721.translate(&data), None);
+        assert_eq!(
+            MetadataTranslator::OpenSea.translate(&data),
+            Some(expected.clone())
+        );
+        assert_eq!(translate_token_data(None, &data), Some(expected));
+
+        // unknown object is not detected
+        let data = binary(r#"{"foo":"bar"}"#);
+        assert_eq!(MetadataTranslator::OpenSea.translate(&data), None);
+        assert_eq!(translate_token_data(None, &data), None);
+    }
+
+    #[test]
+    fn test_irismod() {
+        let data = binary(
+            r#"{"irismod:token_name":{"value":"name"},"irismod:uri_hash":{"value":"hash"},"irismod:token_data":{"value":"data"}}"#,
+        );
+        let expected = NftExtensionMsg {
+            name: Some("name".to_string()),
+            attributes: Some(vec![
+                attribute("irismod:token_data", "data"),
+                attribute("irismod:uri_hash", "hash"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            MetadataTranslator::Irismod.translate(&data),
+            Some(expected.clone())
+        );
+        assert_eq!(translate_token_data(None, &data), Some(expected));
+
+        // no irismod keys
+        let data = binary(r#"{"name":{"value":"name"}}"#);
+        assert_eq!(MetadataTranslator::Irismod.translate(&data), None);
+    }
+
+    #[test]
+    fn test_any_json() {
+        let data =
+            binary(r#"{"@type":"/game.v1.Item","name":"name","uri":"ipfs://token","level":3}"#);
+        let expected = NftExtensionMsg {
+            name: Some("name".to_string()),
+            attributes: Some(vec![
+                attribute("level", "3"),
+                attribute("uri", "ipfs://token"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            MetadataTranslator::AnyJson.translate(&data),
+            Some(expected.clone())
+        );
+        assert_eq!(translate_token_data(None, &data), Some(expected));
+
+        // no type
+        let data = binary(r#"{"name":"name"}"#);
+        assert_eq!(MetadataTranslator::AnyJson.translate(&data), None);
+    }
+
+    #[test]
+    fn test_flat_json() {
+        // custom game schema
+        let data = binary(
+            r#"{"name":"Sword","image":"ipfs://sword","damage":12,"element":"fire","enchanted":false,"stats":{"speed":3}}"#,
+        );
+        let expected = NftExtensionMsg {
+            name: Some("Sword".to_string()),
+            image: Some("ipfs://sword".to_string()),
+            attributes: Some(vec![
+                attribute("damage", "12"),
+                attribute("element", "fire"),
+                attribute("enchanted", "false"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            MetadataTranslator::FlatJson.translate(&data),
+            Some(expected)
+        );
+        // only used when set for a class
+        assert_eq!(
+            translate_token_data(Some(&MetadataTranslator::FlatJson), &binary("[]")),
+            None
+        );
+    }
+}
@@ -1,10 +1,279 @@
-use cosmwasm_std::{Addr, DepsMut, Empty, Env, StdResult};
-use cw721::msg::NumTokensResponse;
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Api, Binary, Deps, DepsMut, Empty, Env, StdError, StdResult,
+    Storage,
+};
+use cw721::{msg::NumTokensResponse, CollectionExtension};
 use cw_ownable::Ownership;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use ics721_types::token_types::ClassId;
+
+use crate::{
+    state::{
+        CollectionData, UniversalCollectionInfoResponse, VoucherCodeIdRule, CW721_CODE_ID,
+        ROYALTY_PAYEES, VOUCHER_CODE_ID_RULES, VOUCHER_SALT_NONCES,
+    },
+    translators::JsonValue,
+};
 
-use crate::state::{CollectionData, UniversalCollectionInfoResponse};
+/// Type prefix of ibc-hooks for deriving intermediate senders.
+const WASM_HOOK_SENDER_PREFIX: &str = "ibc-wasm-hook-intermediary";
 
 pub fn get_collection_data(deps: &DepsMut, collection: &Addr) -> StdResult<CollectionData> {
+    let owner = get_collection_owner(deps.as_ref(), collection)?;
+    let contract_info = deps.querier.query_wasm_contract_info(collection)?;
+    let UniversalCollectionInfoResponse {
+        name,
+        symbol,
+        extension,
+        updated_at: _,
+    } = deps.querier.query_wasm_smart(
+        collection,
+        #[allow(deprecated)]
+        // For now we use `ContractInfo` which is known across all version, whilst `GetCollectionInfoAndExtension` is only available in v0.19 and higher
+        &cw721_metadata_onchain::msg::QueryMsg::ContractInfo {},
+    )?;
+    let NumTokensResponse { count } = deps.querier.query_wasm_smart(
+        collection,
+        &cw721_metadata_onchain::msg::QueryMsg::NumTokens {},
+    )?;
+
+    Ok(CollectionData {
+        owner,
+        contract_info: Some(contract_info),
+        num_tokens: Some(count),
+        name,
+        symbol,
+        extension,
+        destination_profile: None,
+    })
+}
+
+/// Gets the names of all collection info props, which differ between previous and current collection data.
+/// Props not being part of collection info in cw721 (like owner and num tokens) are ignored.
+pub fn get_collection_info_changes(
+    previous: Option<&CollectionData>,
+    current: &CollectionData,
+) -> Vec<&'static str> {
+    let previous_extension = previous.and_then(|p| p.extension.as_ref());
+    let current_extension = current.extension.as_ref();
+    let mut changes = vec![];
+    if previous.map(|p| &p.name) != Some(&current.name) {
+        changes.push("name");
+    }
+    if previous.map(|p| &p.symbol) != Some(&current.symbol) {
+        changes.push("symbol");
+    }
+    if previous_extension.map(|e| &e.description) != current_extension.map(|e| &e.description) {
+        changes.push("description");
+    }
+    if previous_extension.map(|e| &e.image) != current_extension.map(|e| &e.image) {
+        changes.push("image");
+    }
+    if previous_extension.map(|e| &e.external_link) != current_extension.map(|e| &e.external_link) {
+        changes.push("external_link");
+    }
+    if previous_extension.map(|e| e.explicit_content)
+        != current_extension.map(|e| e.explicit_content)
+    {
+        changes.push("explicit_content");
+    }
+    if previous_extension.map(|e| e.start_trading_time)
+        != current_extension.map(|e| e.start_trading_time)
+    {
+        changes.push("start_trading_time");
+    }
+    // payment address is from another chain, so only share is relevant
+    if previous_extension.and_then(|e| e.royalty_info.as_ref().map(|r| r.share))
+        != current_extension.and_then(|e| e.royalty_info.as_ref().map(|r| r.share))
+    {
+        changes.push("royalty_share");
+    }
+    changes
+}
+
+/// Applies the destination profile set by the source collection owner to the collection data of a voucher: name,
+/// symbol and image are overridden, and the profile is removed from the data. Returns the profile's royalty payee, in
+/// case it is a valid address on this chain.
+pub fn apply_destination_profile(
+    api: &dyn Api,
+    mut collection_data: CollectionData,
+) -> (CollectionData, Option<String>) {
+    let Some(profile) = collection_data.destination_profile.take() else {
+        return (collection_data, None);
+    };
+    if let Some(name) = profile.name {
+        collection_data.name = name;
+    }
+    if let Some(symbol) = profile.symbol {
+        collection_data.symbol = symbol;
+    }
+    if let Some(image) = profile.image {
+        match collection_data.extension.as_mut() {
+            Some(extension) => extension.image = image,
+            None => {
+                collection_data.extension = Some(CollectionExtension {
+                    description: "".to_string(),
+                    image,
+                    external_link: None,
+                    explicit_content: None,
+                    start_trading_time: None,
+                    royalty_info: None,
+                })
+            }
+        }
+    }
+    let royalty_payee = profile
+        .royalty_payee
+        .filter(|payee| api.addr_validate(payee).is_ok());
+    (collection_data, royalty_payee)
+}
+
+/// Returns the royalty payee registered for a class on this chain, otherwise the one of the destination profile.
+pub fn get_royalty_payee(
+    deps: Deps,
+    class_id: &ClassId,
+    profile_royalty_payee: Option<String>,
+) -> StdResult<Option<String>> {
+    Ok(ROYALTY_PAYEES
+        .may_load(deps.storage, class_id.clone())?
+        .map(|payee| payee.to_string())
+        .or(profile_royalty_payee))
+}
+
+/// Whether both addresses belong to the same account on different chains (e.g. `juno1XXX` and `stars1XXX`).
+/// Only 20 byte (key based) account addresses match, since 32 byte addresses of contracts and modules are derived
+/// from chain specific data, so the same bytes on another chain belong to someone else.
+pub fn is_same_account(address: &str, other: &str) -> bool {
+    match (bech32::decode(address), bech32::decode(other)) {
+        (Ok((_, data)), Ok((_, other_data))) => data.len() == 20 && data == other_data,
+        _ => false,
+    }
+}
+
+/// Returns the first voucher code ID rule matching a class.
+pub fn get_voucher_code_id_rule(
+    storage: &dyn Storage,
+    class_id: &ClassId,
+    class_data: Option<&Binary>,
+) -> StdResult<Option<VoucherCodeIdRule>> {
+    Ok(VOUCHER_CODE_ID_RULES
+        .may_load(storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .find(|rule| rule.matcher.matches(class_id, class_data)))
+}
+
+/// Returns the code ID for the voucher contract of a class: code ID of the first matching rule, otherwise `CW721_CODE_ID`.
+pub fn get_voucher_code_id(
+    storage: &dyn Storage,
+    class_id: &ClassId,
+    class_data: Option<&Binary>,
+) -> StdResult<u64> {
+    match get_voucher_code_id_rule(storage, class_id, class_data)? {
+        Some(rule) => Ok(rule.code_id),
+        None => CW721_CODE_ID.load(storage),
+    }
+}
+
+/// Returns the instantiate2 salt for the voucher contract of a class: sha256 of class ID, and once a voucher mapping
+/// has been removed, of class ID and number of removals (`{class_id}/{nonce}`). Salt must be 32 bytes.
+pub fn get_voucher_salt(storage: &dyn Storage, class_id: &ClassId) -> StdResult<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    hasher.update(class_id.as_bytes());
+    if let Some(nonce) = VOUCHER_SALT_NONCES.may_load(storage, class_id.clone())? {
+        hasher.update(format!("/{nonce}").as_bytes());
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Adds top level fields of a JSON object to an instantiate message, existing fields are overwritten.
+pub fn extend_init_msg(init_msg: &Binary, fields: &Binary) -> StdResult<Binary> {
+    let mut init_msg = from_json::<BTreeMap<String, JsonValue>>(init_msg)?;
+    init_msg.extend(from_json::<BTreeMap<String, JsonValue>>(fields)?);
+    to_json_binary(&init_msg)
+}
+
+/// Returns the minter of a collection, supporting cw721 v0.16 and higher.
+pub fn get_collection_minter(deps: Deps, collection: &Addr) -> StdResult<Option<String>> {
+    // cw721 v0.19 and higher holds minter ownership as `GetMinterOwnership`
+    let ownership_result: StdResult<Ownership<Addr>> = deps.querier.query_wasm_smart(
+        collection,
+        &cw721_metadata_onchain::msg::QueryMsg::GetMinterOwnership {},
+    );
+    if let Ok(ownership) = ownership_result {
+        return Ok(ownership.owner.map(|a| a.to_string()));
+    }
+    // cw721 v0.17 and v0.18 holds minter ownership as `Ownership`
+    let ownership_result: StdResult<Ownership<Addr>> = deps.querier.query_wasm_smart(
+        collection,
+        &cw721_base_018::msg::QueryMsg::Ownership::<Addr> {},
+    );
+    if let Ok(ownership) = ownership_result {
+        return Ok(ownership.owner.map(|a| a.to_string()));
+    }
+    // cw721 v0.16 and lower holds minter (simple string storage)
+    let minter_response: cw721_base_016::msg::MinterResponse = deps
+        .querier
+        .query_wasm_smart(collection, &cw721_base_016::QueryMsg::Minter::<Empty> {})?;
+    Ok(Some(minter_response.minter))
+}
+
+/// Checks whether a secp256k1 public key belongs to an address. Like Cosmos SDK accounts, address data is
+/// `ripemd160(sha256(pubkey))`, while the prefix is ignored (see `convert_owner_chain_address`).
+pub fn is_pubkey_of_account(pubkey: &[u8], address: &str) -> bool {
+    let Ok((_hrp, data)) = bech32::decode(address) else {
+        return false;
+    };
+    let pubkey_hash = Ripemd160::digest(Sha256::digest(pubkey));
+    data == pubkey_hash.as_slice()
+}
+
+/// Message signed by collection owner on source chain for claiming ownership of a voucher collection.
+/// It is bound to chain, ICS721 contract, class and claimant, so a signature can't be reused by others.
+pub fn collection_ownership_claim_message(
+    env: &Env,
+    class_id: &ClassId,
+    claimant: &Addr,
+) -> String {
+    format!(
+        "Claim collection ownership of {} on {} (ICS721: {}) for {}",
+        class_id, env.block.chain_id, env.contract.address, claimant
+    )
+}
+
+/// Hash of the ADR-036 sign doc (as used by `signArbitrary` in wallets) for arbitrary data signed by signer.
+pub fn adr036_sign_doc_hash(signer: &str, data: &[u8]) -> Vec<u8> {
+    let sign_doc = format!(
+        r#"{{"account_number":"0","chain_id":"","fee":{{"amount":[],"gas":"0"}},"memo":"","msgs":[{{"type":"sign/MsgSignData","value":{{"data":"{}","signer":"{}"}}}}],"sequence":"0"}}"#,
+        Binary::from(data).to_base64(),
+        signer
+    );
+    Sha256::digest(sign_doc.as_bytes()).to_vec()
+}
+
+/// Derives the sender of a `wasm` memo the way ibc-hooks does: `sha256(sha256("ibc-wasm-hook-intermediary") | "{channel}/{sender}")`,
+/// bech32 encoded with the prefix of this contract's address. Channel is the local channel the packet arrived on.
+/// It is only emitted as an event attribute, the called contract sees ICS721 as sender.
+pub fn derive_intermediate_sender(
+    env: &Env,
+    channel_id: &str,
+    original_sender: &str,
+) -> StdResult<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(WASM_HOOK_SENDER_PREFIX.as_bytes()));
+    hasher.update(format!("{channel_id}/{original_sender}").as_bytes());
+    let (hrp, _data) = bech32::decode(env.contract.address.as_str())
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    bech32::encode::<bech32::Bech32>(hrp, &hasher.finalize())
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// Gets the creator of a collection (cw721 v0.19 and higher), or the minter for older versions.
+pub fn get_collection_owner(deps: Deps, collection: &Addr) -> StdResult<Option<String>> {
     // cw721 v0.19 and higher holds creator ownership (cw-ownable storage) in the contract
     let ownership_result: StdResult<Ownership<Addr>> = deps.querier.query_wasm_smart(
         collection,
@@ -33,31 +302,7 @@ pub fn get_collection_data(deps: &DepsMut, collection: &Addr) -> StdResult<Colle
             }
         }
     };
-    let contract_info = deps.querier.query_wasm_contract_info(collection)?;
-    let UniversalCollectionInfoResponse {
-        name,
-        symbol,
-        extension,
-        updated_at: _,
-    } = deps.querier.query_wasm_smart(
-        collection,
-        #[allow(deprecated)]
-        // For now we use `ContractInfo` which is known across all version, whilst `GetCollectionInfoAndExtension` is only available in v0.19 and higher
-        &cw721_metadata_onchain::msg::QueryMsg::ContractInfo {},
-    )?;
-    let NumTokensResponse { count } = deps.querier.query_wasm_smart(
-        collection,
-        &cw721_metadata_onchain::msg::QueryMsg::NumTokens {},
-    )?;
-
-    Ok(CollectionData {
-        owner,
-        contract_info: Some(contract_info),
-        num_tokens: Some(count),
-        name,
-        symbol,
-        extension,
-    })
+    Ok(owner)
 }
 
 /// Convert owner chain address (e.g. `juno1XXX`) to target owner chain address (e.g. `stars1XXX`).